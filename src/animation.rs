@@ -0,0 +1,409 @@
+//! Animating view geometry.
+//!
+//! An `Animator` moves and resizes views smoothly from one geometry to
+//! another, for windows opening, closing, being tiled or moving between
+//! workspaces. Frames are driven by an event loop `Timer`: on each tick
+//! the geometry of every animated view is interpolated along an easing
+//! curve, set with `set_geometry`, and its output is scheduled to render.
+//!
+//! Layouts often change while windows are still moving. Animating a view
+//! which is already animated starts from wherever it currently is, so it
+//! changes course without jumping. Animations can also be cancelled, which
+//! leaves the view where it is, or finished early, which jumps to the end.
+//!
+//! Like [`Timer`](../event_loop/struct.Timer.html), the animator is not `Send`.
+//!
+//! # Example
+//! ```no_run
+//! use std::cell::RefCell;
+//! use std::time::Duration;
+//! use rustwlc::{Geometry, WlcView};
+//! use rustwlc::animation::{Animator, Easing};
+//!
+//! thread_local! {
+//!     static ANIMATOR: RefCell<Animator> = RefCell::new(Animator::new());
+//! }
+//!
+//! // Slides a view into its place in the layout
+//! fn tile(view: WlcView, geometry: Geometry) {
+//!     ANIMATOR.with(|animator| {
+//!         animator.borrow_mut().animate(view, geometry, Duration::from_millis(200),
+//!                                       Easing::EaseInOut);
+//!     });
+//! }
+//!
+//! extern fn view_destroyed(view: WlcView) {
+//!     ANIMATOR.with(|animator| animator.borrow_mut().view_destroyed(view));
+//! }
+//! # fn main() {}
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::WlcView;
+#[cfg(not(feature = "dummy"))]
+use super::event_loop::Timer;
+#[cfg(feature = "dummy")]
+use super::dummy_event_loop::Timer;
+use super::types::{Geometry, Point, Size, EDGE_NONE};
+
+/// Default time between frames, in milliseconds
+pub const DEFAULT_FRAME_INTERVAL: u32 = 16;
+
+/// How an animation progresses over time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Speeds up, then slows down (quadratic)
+    EaseInOut,
+    /// Starts fast and slows down towards the end (cubic ease-out)
+    Cubic,
+    /// Overshoots the target and settles on it like a damped spring
+    Spring
+}
+
+impl Easing {
+    /// Progress of the animation after `t` of its duration has passed,
+    /// both from 0 to 1.
+    ///
+    /// `Spring` goes past 1 on its way to the end.
+    pub fn apply(self, t: f64) -> f64 {
+        if t <= 0.0 {
+            return 0.0
+        }
+        if t >= 1.0 {
+            return 1.0
+        }
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => if t < 0.5 {
+                2.0 * t * t
+            } else {
+                1.0 - (2.0 - 2.0 * t).powi(2) / 2.0
+            },
+            Easing::Cubic => 1.0 - (1.0 - t).powi(3),
+            Easing::Spring => 1.0 - (-6.0 * t).exp() * (3.0 * PI * t).cos()
+        }
+    }
+}
+
+/// Geometry `progress` of the way from `from` to `to`.
+///
+/// Progress past 1 overshoots `to`. Sizes never go below zero.
+pub fn interpolate(from: Geometry, to: Geometry, progress: f64) -> Geometry {
+    let mix = |a: f64, b: f64| (a + (b - a) * progress).round();
+    Geometry::new(Point::new(mix(from.origin.x as f64, to.origin.x as f64) as i32,
+                             mix(from.origin.y as f64, to.origin.y as f64) as i32),
+                  Size::new(mix(from.size.w as f64, to.size.w as f64).max(0.0) as u32,
+                            mix(from.size.h as f64, to.size.h as f64).max(0.0) as u32))
+}
+
+/// A view moving from one geometry to another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Animation {
+    /// Geometry at the start
+    pub from: Geometry,
+    /// Geometry at the end
+    pub to: Geometry,
+    /// When the animation started
+    pub start: Instant,
+    /// How long the animation takes
+    pub duration: Duration,
+    /// How the animation progresses
+    pub easing: Easing
+}
+
+impl Animation {
+    /// Fraction of the duration that has passed at `now`, from 0 to 1.
+    pub fn elapsed(&self, now: Instant) -> f64 {
+        if self.duration == Duration::from_millis(0) {
+            return 1.0
+        }
+        let elapsed = now.saturating_duration_since(self.start);
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+
+    /// Geometry of the view at `now`.
+    pub fn geometry_at(&self, now: Instant) -> Geometry {
+        interpolate(self.from, self.to, self.easing.apply(self.elapsed(now)))
+    }
+
+    /// Whether the animation is over at `now`.
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.elapsed(now) >= 1.0
+    }
+}
+
+type Animations = Rc<RefCell<HashMap<WlcView, Animation>>>;
+
+/// Animates the geometry of views.
+pub struct Animator {
+    animations: Animations,
+    interval: Rc<RefCell<u32>>,
+    /// Whether the timer is armed, cleared once the last animation ends
+    running: Rc<Cell<bool>>,
+    timer: Option<Timer>
+}
+
+impl Default for Animator {
+    fn default() -> Animator {
+        Animator::new()
+    }
+}
+
+impl Animator {
+    /// Makes an animator with no running animations.
+    ///
+    /// The timer is created with the first animation, so an animator can
+    /// be made before wlc is initialized.
+    pub fn new() -> Animator {
+        Animator {
+            animations: Rc::new(RefCell::new(HashMap::new())),
+            interval: Rc::new(RefCell::new(DEFAULT_FRAME_INTERVAL)),
+            running: Rc::new(Cell::new(false)),
+            timer: None
+        }
+    }
+
+    /// Time between frames, in milliseconds.
+    pub fn frame_interval(&self) -> u32 {
+        *self.interval.borrow()
+    }
+
+    /// Sets the time between frames, in milliseconds.
+    pub fn set_frame_interval(&mut self, ms: u32) {
+        *self.interval.borrow_mut() = ms.max(1);
+    }
+
+    /// Animates a view from where it currently is to `to`.
+    ///
+    /// If the view is already animated it changes course from its current
+    /// geometry. A zero duration moves the view straight away.
+    pub fn animate(&mut self, view: WlcView, to: Geometry, duration: Duration, easing: Easing) {
+        let from = self.current(view)
+            .or_else(|| view.get_geometry())
+            .unwrap_or(to);
+        self.animate_from(view, from, to, duration, easing);
+    }
+
+    /// Animates a view from `from` to `to`, such as from a small geometry
+    /// in the middle of its final one when it opens.
+    pub fn animate_from(&mut self, view: WlcView, from: Geometry, to: Geometry,
+                        duration: Duration, easing: Easing) {
+        if duration == Duration::from_millis(0) || from == to {
+            self.animations.borrow_mut().remove(&view);
+            view.set_geometry(EDGE_NONE, to);
+            view.get_output().schedule_render();
+            return
+        }
+        let animation = Animation { from, to, start: Instant::now(), duration, easing };
+        view.set_geometry(EDGE_NONE, from);
+        self.animations.borrow_mut().insert(view, animation);
+        self.start_timer();
+    }
+
+    /// Points a running animation at a new target, keeping its duration
+    /// and easing. The view changes course from its current geometry.
+    ///
+    /// Returns `false` if the view was not animated.
+    pub fn retarget(&mut self, view: WlcView, to: Geometry) -> bool {
+        let animation = match self.animations.borrow().get(&view) {
+            Some(animation) => *animation,
+            None => return false
+        };
+        self.animate(view, to, animation.duration, animation.easing);
+        true
+    }
+
+    /// Stops animating a view, leaving it where it currently is.
+    ///
+    /// Returns the geometry it was left at, if it was animated.
+    pub fn cancel(&mut self, view: WlcView) -> Option<Geometry> {
+        let current = self.current(view)?;
+        self.animations.borrow_mut().remove(&view);
+        Some(current)
+    }
+
+    /// Stops animating a view, moving it straight to its target.
+    ///
+    /// Returns the target, if the view was animated.
+    pub fn finish(&mut self, view: WlcView) -> Option<Geometry> {
+        let animation = self.animations.borrow_mut().remove(&view)?;
+        view.set_geometry(EDGE_NONE, animation.to);
+        view.get_output().schedule_render();
+        Some(animation.to)
+    }
+
+    /// Stops all animations, moving views straight to their targets.
+    pub fn finish_all(&mut self) {
+        let animations: Vec<_> = self.animations.borrow_mut().drain().collect();
+        for (view, animation) in animations {
+            view.set_geometry(EDGE_NONE, animation.to);
+            view.get_output().schedule_render();
+        }
+    }
+
+    /// Whether a view is being animated.
+    pub fn is_animating(&self, view: WlcView) -> bool {
+        self.animations.borrow().contains_key(&view)
+    }
+
+    /// The running animation of a view.
+    pub fn animation(&self, view: WlcView) -> Option<Animation> {
+        self.animations.borrow().get(&view).cloned()
+    }
+
+    /// Geometry an animated view is heading to.
+    ///
+    /// Layouts should use this rather than `get_geometry`, which gives the
+    /// geometry of the current frame.
+    pub fn target(&self, view: WlcView) -> Option<Geometry> {
+        self.animations.borrow().get(&view).map(|animation| animation.to)
+    }
+
+    /// Geometry an animated view has right now.
+    pub fn current(&self, view: WlcView) -> Option<Geometry> {
+        self.animations.borrow().get(&view)
+            .map(|animation| animation.geometry_at(Instant::now()))
+    }
+
+    /// Stops animating a destroyed view. Call from the `view_destroyed`
+    /// callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.animations.borrow_mut().remove(&view);
+    }
+
+    /// Arms the timer unless it is already running. Rearming it would
+    /// push the next frame back, so views retargeted faster than the
+    /// frame interval would never move.
+    fn start_timer(&mut self) {
+        if self.timer.is_none() {
+            let animations = self.animations.clone();
+            let interval = self.interval.clone();
+            let running = self.running.clone();
+            self.timer = Timer::new(move || {
+                if step(&animations) {
+                    Some(*interval.borrow())
+                } else {
+                    running.set(false);
+                    None
+                }
+            });
+        }
+        match self.timer {
+            Some(ref timer) => if !self.running.get() {
+                self.running.set(timer.update(self.frame_interval()));
+            },
+            // Without a timer there are no frames, so skip to the end
+            None => self.finish_all()
+        }
+    }
+}
+
+/// Moves every animated view to its geometry for this frame. Returns
+/// whether any animations are left.
+fn step(animations: &Animations) -> bool {
+    let now = Instant::now();
+    let (frame, left) = {
+        let mut animations = animations.borrow_mut();
+        let frame: Vec<_> = animations.iter()
+            .map(|(&view, animation)| (view, animation.geometry_at(now)))
+            .collect();
+        animations.retain(|_, animation| !animation.is_finished(now));
+        (frame, !animations.is_empty())
+    };
+    // Setting geometry is done without the animations borrowed, in case
+    // it calls back into the compositor.
+    for (view, geometry) in frame {
+        view.set_geometry(EDGE_NONE, geometry);
+        view.get_output().schedule_render();
+    }
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easing_curves() {
+        for &easing in &[Easing::Linear, Easing::EaseInOut, Easing::Cubic, Easing::Spring] {
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseInOut.apply(0.25) < 0.25);
+        assert!(Easing::EaseInOut.apply(0.75) > 0.75);
+        assert_eq!(Easing::Cubic.apply(0.5), 0.875);
+        // The spring overshoots, then settles
+        let peak = (1..100).map(|i| Easing::Spring.apply(i as f64 / 100.0))
+            .fold(0.0, f64::max);
+        assert!(peak > 1.0 && peak < 1.5, "{}", peak);
+        assert!((Easing::Spring.apply(0.99) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn interpolation() {
+        let from = Geometry::new(Point::new(0, 0), Size::new(100, 100));
+        let to = Geometry::new(Point::new(100, -50), Size::new(300, 50));
+        assert_eq!(interpolate(from, to, 0.0), from);
+        assert_eq!(interpolate(from, to, 1.0), to);
+        assert_eq!(interpolate(from, to, 0.5),
+                   Geometry::new(Point::new(50, -25), Size::new(200, 75)));
+        assert_eq!(interpolate(from, to, 1.5),
+                   Geometry::new(Point::new(150, -75), Size::new(400, 25)));
+        assert_eq!(interpolate(from, to, 3.0).size.h, 0);
+    }
+
+    #[test]
+    fn animation_progress() {
+        let start = Instant::now();
+        let animation = Animation {
+            from: Geometry::new(Point::new(0, 0), Size::new(100, 100)),
+            to: Geometry::new(Point::new(200, 0), Size::new(100, 100)),
+            start,
+            duration: Duration::from_millis(100),
+            easing: Easing::Linear
+        };
+        assert_eq!(animation.geometry_at(start), animation.from);
+        assert_eq!(animation.geometry_at(start + Duration::from_millis(25)),
+                   Geometry::new(Point::new(50, 0), Size::new(100, 100)));
+        assert!(!animation.is_finished(start + Duration::from_millis(99)));
+        assert!(animation.is_finished(start + Duration::from_millis(100)));
+        assert_eq!(animation.geometry_at(start + Duration::from_secs(5)), animation.to);
+        let instant = Animation { duration: Duration::from_millis(0), ..animation };
+        assert!(instant.is_finished(start));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn retargeting_keeps_frames_coming() {
+        use super::super::dummy_event_loop::advance;
+
+        let view = unsafe { WlcView::dummy(1) };
+        view.set_geometry(EDGE_NONE, Geometry::new(Point::new(0, 0), Size::new(100, 100)));
+        let mut animator = Animator::new();
+        let mut frames = 0;
+        // Like an interactive drag, retargeting faster than the frame interval
+        for step in 1..10 {
+            let to = Geometry::new(Point::new(step * 100, 0), Size::new(100, 100));
+            animator.animate(view, to, Duration::from_secs(60), Easing::Linear);
+            frames += advance(10);
+        }
+        assert_eq!(frames, 4);
+        assert!(animator.running.get());
+
+        animator.finish(view);
+        assert_eq!(advance(DEFAULT_FRAME_INTERVAL), 1);
+        assert!(!animator.running.get());
+        assert_eq!(advance(DEFAULT_FRAME_INTERVAL), 0);
+    }
+}
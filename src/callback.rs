@@ -173,6 +173,84 @@ extern "C" {
                                                      -> PositionerConstraintAdjustmentBits;
 }
 
+// Once checked mode or the registry is enabled, the crate hooks into output
+// and view creation, destruction and property changes to keep track of live
+// handles. User callbacks are kept here and called from the hooks.
+static mut HOOKS_INSTALLED: bool = false;
+static mut OUTPUT_CREATED: Option<extern "C" fn(WlcOutput) -> bool> = None;
+static mut OUTPUT_DESTROYED: Option<extern "C" fn(WlcOutput)> = None;
+static mut VIEW_CREATED: Option<extern "C" fn(WlcView) -> bool> = None;
+static mut VIEW_DESTROYED: Option<extern "C" fn(WlcView)> = None;
+static mut VIEW_PROPERTIES_CHANGED: Option<extern "C" fn(WlcView, ViewPropertyType)> = None;
+
+extern "C" fn output_created_hook(output: WlcOutput) -> bool {
+    ::checked::created(output);
+    ::registry::output_created(output);
+    let keep = match unsafe { OUTPUT_CREATED } {
+        Some(callback) => callback(output),
+        None => true
+    };
+    if !keep {
+        ::registry::output_destroyed(output);
+        ::checked::destroyed(output);
+    }
+    keep
+}
+
+extern "C" fn output_destroyed_hook(output: WlcOutput) {
+    if let Some(callback) = unsafe { OUTPUT_DESTROYED } {
+        callback(output);
+    }
+    ::registry::output_destroyed(output);
+    ::checked::destroyed(output);
+}
+
+extern "C" fn view_created_hook(view: WlcView) -> bool {
+    ::checked::created(view);
+    ::registry::view_created(view);
+    let keep = match unsafe { VIEW_CREATED } {
+        Some(callback) => callback(view),
+        None => true
+    };
+    if !keep {
+        ::registry::view_destroyed(view);
+        ::checked::destroyed(view);
+    }
+    keep
+}
+
+extern "C" fn view_destroyed_hook(view: WlcView) {
+    if let Some(callback) = unsafe { VIEW_DESTROYED } {
+        callback(view);
+    }
+    ::registry::view_destroyed(view);
+    ::checked::destroyed(view);
+}
+
+extern "C" fn view_properties_changed_hook(view: WlcView, mask: ViewPropertyType) {
+    ::registry::view_properties_changed(view);
+    if let Some(callback) = unsafe { VIEW_PROPERTIES_CHANGED } {
+        callback(view, mask);
+    }
+}
+
+/// Registers the crate's hooks for creation and destruction of outputs and
+/// views and for view property changes.
+///
+/// Until this is called the public setters register the user's callback
+/// with wlc directly. Afterwards they only store it for the hook to call, so
+/// callbacks set before or after this keep firing.
+pub(crate) fn install_hooks() {
+    unsafe {
+        HOOKS_INSTALLED = true;
+        wlc_set_output_created_cb(output_created_hook);
+        wlc_set_output_destroyed_cb(output_destroyed_hook);
+        wlc_set_view_created_cb(view_created_hook);
+        wlc_set_view_destroyed_cb(view_destroyed_hook);
+        wlc_set_view_properties_updated_cb(view_properties_changed_hook);
+    }
+}
+
 /// Callback invoked when an output is created.
 /// Return `true` to allow the output to exist.
 ///
@@ -188,7 +266,10 @@ extern "C" {
 /// ```
 pub fn output_created(callback: extern "C" fn(output: WlcOutput) -> bool) {
     unsafe {
-        wlc_set_output_created_cb(callback);
+        OUTPUT_CREATED = Some(callback);
+        if !HOOKS_INSTALLED {
+            wlc_set_output_created_cb(callback);
+        }
     }
 }
 
@@ -205,7 +286,10 @@ pub fn output_created(callback: extern "C" fn(output: WlcOutput) -> bool) {
 /// ```
 pub fn output_destroyed(callback: extern "C" fn(output: WlcOutput)) {
     unsafe {
-        wlc_set_output_destroyed_cb(callback);
+        OUTPUT_DESTROYED = Some(callback);
+        if !HOOKS_INSTALLED {
+            wlc_set_output_destroyed_cb(callback);
+        }
     }
 }
 
@@ -300,7 +384,10 @@ pub fn output_render_post(callback: extern "C" fn(output: WlcOutput)) {
 /// ```
 pub fn view_created(callback: extern "C" fn(view: WlcView) -> bool) {
     unsafe {
-        wlc_set_view_created_cb(callback);
+        VIEW_CREATED = Some(callback);
+        if !HOOKS_INSTALLED {
+            wlc_set_view_created_cb(callback);
+        }
     }
 }
 
@@ -320,7 +407,10 @@ pub fn view_created(callback: extern "C" fn(view: WlcView) -> bool) {
 /// ```
 pub fn view_destroyed(callback: extern "C" fn(view: WlcView)) {
     unsafe {
-        wlc_set_view_destroyed_cb(callback);
+        VIEW_DESTROYED = Some(callback);
+        if !HOOKS_INSTALLED {
+            wlc_set_view_destroyed_cb(callback);
+        }
     }
 }
 
@@ -588,7 +678,10 @@ pub fn compositor_terminate(callback: extern "C" fn()) {
 /// * `mask`: Bitflag of which property is being updated
 pub fn view_properties_changed(callback: extern "C" fn(handle: WlcView, mask: ViewPropertyType)) {
     unsafe {
-        wlc_set_view_properties_updated_cb(callback);
+        VIEW_PROPERTIES_CHANGED = Some(callback);
+        if !HOOKS_INSTALLED {
+            wlc_set_view_properties_updated_cb(callback);
+        }
     }
 }
 
@@ -639,3 +732,36 @@ pub fn positioner_get_constraint_adjustment(view: WlcView)
         wlc_view_positioner_get_constraint_adjustment(view)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static VIEWS_CREATED: AtomicUsize = AtomicUsize::new(0);
+    static OUTPUTS_DESTROYED: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn on_view_created(_view: WlcView) -> bool {
+        VIEWS_CREATED.fetch_add(1, Ordering::SeqCst);
+        false
+    }
+
+    extern "C" fn on_output_destroyed(_output: WlcOutput) {
+        OUTPUTS_DESTROYED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn hooks_forward_to_user_callbacks() {
+        // Set the stored callbacks the way the setters do, without wlc
+        unsafe {
+            VIEW_CREATED = Some(on_view_created);
+            OUTPUT_DESTROYED = Some(on_output_destroyed);
+        }
+        assert!(!view_created_hook(WlcView(7)));
+        assert_eq!(VIEWS_CREATED.load(Ordering::SeqCst), 1);
+        output_destroyed_hook(WlcOutput(3));
+        assert_eq!(OUTPUTS_DESTROYED.load(Ordering::SeqCst), 1);
+        // Without a user callback outputs are kept
+        assert!(output_created_hook(WlcOutput(3)));
+    }
+}
@@ -0,0 +1,454 @@
+//! Drawing into pixel buffers on the CPU.
+//!
+//! A `Canvas` is an RGBA8888 buffer, the format `render::write_pixels`
+//! takes, with simple drawing operations. Everything is drawn with
+//! straight (not premultiplied) alpha blending, and only inside the
+//! canvas's clip geometry. Canvases do not need wlc until they are pushed
+//! to an output, so drawing code can be tested in memory.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::{Geometry, Point, Size, WlcOutput};
+//! use rustwlc::canvas::Canvas;
+//!
+//! // From an output_render_post callback
+//! fn draw_overlay(output: WlcOutput) {
+//!     let mut canvas = Canvas::new(Size::new(200, 40));
+//!     canvas.fill_rect(Geometry::new(Point::origin(), Size::new(200, 40)),
+//!                      [0, 0, 0, 160]);
+//!     canvas.stroke_rect(Geometry::new(Point::origin(), Size::new(200, 40)),
+//!                        1, [255, 255, 255, 255]);
+//!     canvas.draw_text(Point::new(8, 13), "Recording", [255, 0, 0, 255], 2);
+//!     canvas.push(output, Point::new(10, 10));
+//! }
+//! # fn main() {}
+//! ```
+
+use std::cmp;
+
+use super::types::{Geometry, Point, Size};
+use super::render::{self, wlc_pixel_format};
+use super::image::{Image, PixelFormat};
+use super::font;
+
+use super::WlcOutput;
+
+/// An RGBA8888 pixel buffer to draw into.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Canvas {
+    image: Image,
+    clip: Geometry
+}
+
+impl Canvas {
+    /// Creates a transparent canvas.
+    pub fn new(size: Size) -> Canvas {
+        Canvas {
+            image: Image::new(size, PixelFormat::Rgba8888),
+            clip: Geometry::new(Point::origin(), size)
+        }
+    }
+
+    /// Creates a canvas filled with a color.
+    pub fn filled(size: Size, color: [u8; 4]) -> Canvas {
+        let mut canvas = Canvas::new(size);
+        canvas.clear(color);
+        canvas
+    }
+
+    /// Creates a canvas with the contents of an image.
+    pub fn from_image(image: &Image) -> Canvas {
+        let mut canvas = Canvas::new(image.size);
+        for y in 0..image.size.h {
+            for x in 0..image.size.w {
+                canvas.image.set_pixel(x, y, image.rgba(x, y));
+            }
+        }
+        canvas
+    }
+
+    /// Gets the contents of the canvas as an image.
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Gets the contents of the canvas as an image.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Gets the pixel data, `size.w * 4` bytes per row from top to bottom.
+    pub fn data(&self) -> &[u8] {
+        &self.image.data
+    }
+
+    /// Gets the size of the canvas.
+    pub fn size(&self) -> Size {
+        self.image.size
+    }
+
+    /// Gets the geometry drawing is limited to.
+    pub fn clip(&self) -> Geometry {
+        self.clip
+    }
+
+    /// Limits drawing to a geometry, or to the whole canvas if `None`.
+    ///
+    /// The clip is always kept inside the canvas.
+    pub fn set_clip(&mut self, clip: Option<Geometry>) {
+        let bounds = Geometry::new(Point::origin(), self.size());
+        self.clip = match clip {
+            Some(clip) => intersect(clip, bounds),
+            None => bounds
+        };
+    }
+
+    /// Gets a pixel as red, green, blue, alpha.
+    ///
+    /// Returns transparent black outside of the canvas.
+    pub fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+        if x < 0 || y < 0 || x as u32 >= self.size().w || y as u32 >= self.size().h {
+            return [0; 4]
+        }
+        self.image.pixel(x as u32, y as u32)
+    }
+
+    /// Replaces a pixel without blending, if it is inside the clip.
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: [u8; 4]) {
+        if self.is_clipped(x as i64, y as i64) {
+            return
+        }
+        self.image.set_pixel(x as u32, y as u32, color);
+    }
+
+    /// Blends a color over a pixel, if it is inside the clip.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: [u8; 4]) {
+        self.blend(x as i64, y as i64, color);
+    }
+
+    /// Replaces every pixel inside the clip without blending.
+    pub fn clear(&mut self, color: [u8; 4]) {
+        let clip = self.clip;
+        for y in clip.origin.y..clip.origin.y + clip.size.h as i32 {
+            for x in clip.origin.x..clip.origin.x + clip.size.w as i32 {
+                self.image.set_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+
+    /// Blends a color over a rectangle.
+    pub fn fill_rect(&mut self, rect: Geometry, color: [u8; 4]) {
+        let area = intersect(rect, self.clip);
+        for y in area.origin.y..area.origin.y + area.size.h as i32 {
+            for x in area.origin.x..area.origin.x + area.size.w as i32 {
+                self.blend(x as i64, y as i64, color);
+            }
+        }
+    }
+
+    /// Blends the outline of a rectangle, `width` pixels thick, inside the
+    /// rectangle.
+    pub fn stroke_rect(&mut self, rect: Geometry, width: u32, color: [u8; 4]) {
+        let Geometry { origin, size } = rect;
+        if width.saturating_mul(2) >= cmp::min(size.w, size.h) {
+            self.fill_rect(rect, color);
+            return
+        }
+        let inner_top = origin.y + width as i32;
+        let inner_height = size.h - 2 * width;
+        self.fill_rect(Geometry::new(origin, Size::new(size.w, width)), color);
+        self.fill_rect(Geometry::new(Point::new(origin.x, inner_top + inner_height as i32),
+                                     Size::new(size.w, width)), color);
+        self.fill_rect(Geometry::new(Point::new(origin.x, inner_top),
+                                     Size::new(width, inner_height)), color);
+        self.fill_rect(Geometry::new(Point::new(origin.x + (size.w - width) as i32, inner_top),
+                                     Size::new(width, inner_height)), color);
+    }
+
+    /// Blends a one pixel wide line between two points, including both.
+    pub fn draw_line(&mut self, from: Point, to: Point, color: [u8; 4]) {
+        // Only the part inside the clip is walked, however far off it ends
+        let (from, to) = match clip_line(from, to, self.clip) {
+            Some(line) => line,
+            None => return
+        };
+        let (mut x, mut y) = (from.x as i64, from.y as i64);
+        let (x1, y1) = (to.x as i64, to.y as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.blend(x, y, color);
+            if x == x1 && y == y1 {
+                break
+            }
+            let double = 2 * error;
+            if double >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if double <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Blends a line of text from the built-in font, with its top left
+    /// corner at `origin`. Returns the width of the text.
+    pub fn draw_text(&mut self, origin: Point, text: &str, color: [u8; 4], scale: u32) -> u32 {
+        font::rasterize(origin.x, origin.y, text, scale, |x, y| self.blend(x, y, color));
+        font::text_size(text, scale.max(1)).w
+    }
+
+    /// Blends another canvas over this one, with its top left corner at
+    /// `origin`.
+    pub fn blit(&mut self, source: &Canvas, origin: Point) {
+        let area = intersect(Geometry::new(origin, source.size()), self.clip);
+        for y in area.origin.y..area.origin.y + area.size.h as i32 {
+            for x in area.origin.x..area.origin.x + area.size.w as i32 {
+                let color = source.pixel(x - origin.x, y - origin.y);
+                self.blend(x as i64, y as i64, color);
+            }
+        }
+    }
+
+    /// Copies the part of the canvas inside a geometry into a new canvas.
+    ///
+    /// The geometry is clamped to the canvas.
+    pub fn crop(&self, geometry: Geometry) -> Canvas {
+        let area = intersect(geometry, Geometry::new(Point::origin(), self.size()));
+        let mut canvas = Canvas::new(area.size);
+        for y in 0..area.size.h {
+            let start = ((area.origin.y as u32 + y) * self.image.stride) as usize
+                + area.origin.x as usize * 4;
+            let row = &self.image.data[start..start + area.size.w as usize * 4];
+            let target = (y * canvas.image.stride) as usize;
+            canvas.image.data[target..target + row.len()].copy_from_slice(row);
+        }
+        canvas
+    }
+
+    /// Writes the canvas to the framebuffer of the output being rendered,
+    /// with its top left corner at `origin` in framebuffer pixels.
+    ///
+    /// This must be called while `output` is rendering, such as from its
+    /// `output_render_post` callback. Pixels are written as is; the
+    /// framebuffer is not blended with. Parts outside of the output are
+    /// skipped.
+    pub fn push(&self, output: WlcOutput, origin: Point) {
+        if let Some(resolution) = output.get_resolution() {
+            self.push_clipped(resolution, origin);
+        }
+    }
+
+    /// Writes the canvas to the framebuffer, skipping any part outside of a
+    /// framebuffer of size `resolution`.
+    ///
+    /// See `push`.
+    pub fn push_clipped(&self, resolution: Size, origin: Point) {
+        let visible = intersect(Geometry::new(origin, self.size()),
+                                Geometry::new(Point::origin(), resolution));
+        if visible.size.w == 0 || visible.size.h == 0 {
+            return
+        }
+        let local = Geometry::new(Point::new(visible.origin.x - origin.x,
+                                             visible.origin.y - origin.y),
+                                  visible.size);
+        if local.size == self.size() {
+            render::write_pixels(wlc_pixel_format::WLC_RGBA8888, visible, self.data());
+        } else {
+            let cropped = self.crop(local);
+            render::write_pixels(wlc_pixel_format::WLC_RGBA8888, visible, cropped.data());
+        }
+    }
+
+    fn is_clipped(&self, x: i64, y: i64) -> bool {
+        let clip = self.clip;
+        x < clip.origin.x as i64 || y < clip.origin.y as i64
+            || x >= clip.origin.x as i64 + clip.size.w as i64
+            || y >= clip.origin.y as i64 + clip.size.h as i64
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 4]) {
+        if color[3] == 0 || self.is_clipped(x, y) {
+            return
+        }
+        let (x, y) = (x as u32, y as u32);
+        let blended = blend(self.image.pixel(x, y), color);
+        self.image.set_pixel(x, y, blended);
+    }
+}
+
+/// Blends `source` over `destination`, both red, green, blue, alpha with
+/// straight alpha.
+pub fn blend(destination: [u8; 4], source: [u8; 4]) -> [u8; 4] {
+    let source_alpha = source[3] as u32;
+    if source_alpha == 255 {
+        return source
+    }
+    if source_alpha == 0 {
+        return destination
+    }
+    // Alphas scaled to 255 * 255
+    let destination_alpha = destination[3] as u32 * (255 - source_alpha);
+    let alpha = source_alpha * 255 + destination_alpha;
+    let mut result = [0; 4];
+    for channel in 0..3 {
+        let value = source[channel] as u32 * source_alpha * 255
+            + destination[channel] as u32 * destination_alpha;
+        result[channel] = ((value + alpha / 2) / alpha) as u8;
+    }
+    result[3] = ((alpha + 127) / 255) as u8;
+    result
+}
+
+/// Clips a line to a geometry with the Cohen–Sutherland algorithm. Returns
+/// `None` if no part of the line is inside it.
+fn clip_line(from: Point, to: Point, clip: Geometry) -> Option<(Point, Point)> {
+    const LEFT: u8 = 1;
+    const RIGHT: u8 = 2;
+    const TOP: u8 = 4;
+    const BOTTOM: u8 = 8;
+    if clip.size.w == 0 || clip.size.h == 0 {
+        return None
+    }
+    let (left, top) = (clip.origin.x as f64, clip.origin.y as f64);
+    let (right, bottom) = (left + clip.size.w as f64 - 1.0, top + clip.size.h as f64 - 1.0);
+    let outcode = |x: f64, y: f64| {
+        let horizontal = if x < left { LEFT } else if x > right { RIGHT } else { 0 };
+        let vertical = if y < top { TOP } else if y > bottom { BOTTOM } else { 0 };
+        horizontal | vertical
+    };
+    let (mut x0, mut y0) = (from.x as f64, from.y as f64);
+    let (mut x1, mut y1) = (to.x as f64, to.y as f64);
+    let (mut code0, mut code1) = (outcode(x0, y0), outcode(x1, y1));
+    loop {
+        if code0 | code1 == 0 {
+            let point = |x: f64, y: f64| {
+                Point::new(x.round().max(left).min(right) as i32,
+                           y.round().max(top).min(bottom) as i32)
+            };
+            return Some((point(x0, y0), point(x1, y1)))
+        }
+        if code0 & code1 != 0 {
+            return None
+        }
+        // Move an end outside the clip onto the edge it is beyond
+        let code = if code0 != 0 { code0 } else { code1 };
+        let (x, y) = if code & TOP != 0 {
+            (x0 + (x1 - x0) * (top - y0) / (y1 - y0), top)
+        } else if code & BOTTOM != 0 {
+            (x0 + (x1 - x0) * (bottom - y0) / (y1 - y0), bottom)
+        } else if code & LEFT != 0 {
+            (left, y0 + (y1 - y0) * (left - x0) / (x1 - x0))
+        } else {
+            (right, y0 + (y1 - y0) * (right - x0) / (x1 - x0))
+        };
+        if code == code0 {
+            x0 = x;
+            y0 = y;
+            code0 = outcode(x0, y0);
+        } else {
+            x1 = x;
+            y1 = y;
+            code1 = outcode(x1, y1);
+        }
+    }
+}
+
+/// Gets the overlap of two geometries, or an empty geometry if they don't
+/// overlap.
+fn intersect(a: Geometry, b: Geometry) -> Geometry {
+    let left = cmp::max(a.origin.x as i64, b.origin.x as i64);
+    let top = cmp::max(a.origin.y as i64, b.origin.y as i64);
+    let right = cmp::min(a.origin.x as i64 + a.size.w as i64,
+                         b.origin.x as i64 + b.size.w as i64);
+    let bottom = cmp::min(a.origin.y as i64 + a.size.h as i64,
+                          b.origin.y as i64 + b.size.h as i64);
+    if right <= left || bottom <= top {
+        return Geometry::new(Point::new(left as i32, top as i32), Size::zero())
+    }
+    Geometry::new(Point::new(left as i32, top as i32),
+                  Size::new((right - left) as u32, (bottom - top) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn blending() {
+        assert_eq!(blend(BLUE, RED), RED);
+        assert_eq!(blend(BLUE, [255, 0, 0, 0]), BLUE);
+        assert_eq!(blend(BLUE, [255, 0, 0, 128]), [128, 0, 127, 255]);
+        // Over transparent, the color is kept and only alpha changes
+        assert_eq!(blend([0, 0, 0, 0], [255, 0, 0, 128]), [255, 0, 0, 128]);
+        assert_eq!(blend([0, 0, 255, 128], [255, 0, 0, 128]), [170, 0, 85, 192]);
+    }
+
+    #[test]
+    fn rectangles() {
+        let mut canvas = Canvas::filled(Size::new(10, 10), BLUE);
+        canvas.fill_rect(Geometry::new(Point::new(-5, -5), Size::new(7, 7)), RED);
+        assert_eq!(canvas.pixel(1, 1), RED);
+        assert_eq!(canvas.pixel(2, 2), BLUE);
+        let mut canvas = Canvas::new(Size::new(6, 6));
+        canvas.stroke_rect(Geometry::new(Point::new(1, 1), Size::new(4, 4)), 1, RED);
+        assert_eq!(canvas.pixel(1, 1), RED);
+        assert_eq!(canvas.pixel(4, 2), RED);
+        assert_eq!(canvas.pixel(2, 2), [0; 4]);
+        assert_eq!(canvas.pixel(5, 5), [0; 4]);
+        // A stroke wider than the rectangle fills it
+        canvas.stroke_rect(Geometry::new(Point::new(0, 0), Size::new(3, 3)), 5, BLUE);
+        assert!((0..3).all(|x| (0..3).all(|y| canvas.pixel(x, y) == BLUE)));
+    }
+
+    #[test]
+    fn lines() {
+        let mut canvas = Canvas::new(Size::new(5, 5));
+        canvas.draw_line(Point::new(0, 0), Point::new(4, 4), RED);
+        assert!((0..5).all(|i| canvas.pixel(i, i) == RED));
+        assert_eq!(canvas.pixel(1, 0), [0; 4]);
+        canvas.draw_line(Point::new(4, 0), Point::new(-10, 0), BLUE);
+        assert!((0..5).all(|x| canvas.pixel(x, 0) == BLUE));
+        // Ends far outside the canvas are clipped before drawing
+        let mut canvas = Canvas::new(Size::new(5, 5));
+        canvas.draw_line(Point::new(i32::MIN, 2), Point::new(i32::MAX, 2), RED);
+        assert!((0..5).all(|x| canvas.pixel(x, 2) == RED));
+        canvas.draw_line(Point::new(-1_000_000_000, -1_000_000_000),
+                         Point::new(1_000_000_000, 1_000_000_000), BLUE);
+        assert!((0..5).all(|i| canvas.pixel(i, i) == BLUE));
+        assert_eq!(canvas.pixel(1, 2), RED);
+        // A line passing above the canvas draws nothing
+        canvas.draw_line(Point::new(i32::MIN, i32::MIN), Point::new(i32::MAX, -1), RED);
+        assert!((1..5).all(|x| canvas.pixel(x, 0) == [0; 4]));
+    }
+
+    #[test]
+    fn clipping_and_blitting() {
+        let mut canvas = Canvas::new(Size::new(4, 4));
+        canvas.set_clip(Some(Geometry::new(Point::new(1, 1), Size::new(10, 2))));
+        assert_eq!(canvas.clip(), Geometry::new(Point::new(1, 1), Size::new(3, 2)));
+        canvas.clear(RED);
+        assert_eq!(canvas.pixel(0, 1), [0; 4]);
+        assert_eq!(canvas.pixel(3, 2), RED);
+        assert_eq!(canvas.pixel(3, 3), [0; 4]);
+        canvas.set_clip(None);
+
+        let source = Canvas::filled(Size::new(2, 2), [0, 0, 255, 128]);
+        canvas.blit(&source, Point::new(2, 2));
+        assert_eq!(canvas.pixel(2, 2), [127, 0, 128, 255]);
+        assert_eq!(canvas.pixel(3, 3), [0, 0, 255, 128]);
+
+        let cropped = canvas.crop(Geometry::new(Point::new(2, 1), Size::new(5, 2)));
+        assert_eq!(cropped.size(), Size::new(2, 2));
+        assert_eq!(cropped.pixel(0, 0), RED);
+        assert_eq!(cropped.pixel(0, 1), [127, 0, 128, 255]);
+    }
+}
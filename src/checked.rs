@@ -0,0 +1,287 @@
+//! Opt-in detection of stale view and output handles.
+//!
+//! `WlcView` and `WlcOutput` are plain numbers which wlc reuses once the
+//! view or output they refer to is destroyed. A handle kept past that point
+//! silently refers to whatever wlc creates next with the same number.
+//!
+//! After `enable` is called, the crate tracks which handles are alive
+//! through its own `view_created`, `view_destroyed`, `output_created` and
+//! `output_destroyed` hooks, and counts a generation for every handle each
+//! time it is created. Handles wrapped in `Checked` remember the generation
+//! they were made in, and refuse to be used once it is over:
+//! `Checked::get` returns an error, and calling `WlcView` or `WlcOutput`
+//! methods through the wrapper panics with a message saying what happened.
+//!
+//! Callbacks registered in `callback` are still called as usual, checked
+//! mode runs its hooks around them. Handles are seen as alive for the
+//! whole of the user's `view_destroyed` and `output_destroyed` callbacks.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::WlcView;
+//! use rustwlc::checked::{self, CheckedView};
+//!
+//! // Once, before running wlc
+//! checked::enable();
+//!
+//! fn remember(view: WlcView) -> CheckedView {
+//!     CheckedView::new(view).expect("View is not alive")
+//! }
+//!
+//! fn focus_later(view: &CheckedView) {
+//!     match view.get() {
+//!         Ok(view) => view.focus(),
+//!         Err(err) => println!("Not focusing: {}", err)
+//!     }
+//!     // Panics if the view is gone
+//!     view.bring_to_front();
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use libc::uintptr_t;
+
+use super::{WlcOutput, WlcView};
+#[cfg(not(feature = "dummy"))]
+use super::callback;
+#[cfg(feature = "dummy")]
+use super::dummy_callback as callback;
+
+/// Handles which can be checked.
+pub trait Tracked: Copy + fmt::Debug {
+    /// The raw wlc handle.
+    fn raw(self) -> uintptr_t;
+
+    /// Name of the handle type.
+    fn kind() -> &'static str;
+}
+
+impl Tracked for WlcView {
+    fn raw(self) -> uintptr_t {
+        self.0
+    }
+
+    fn kind() -> &'static str {
+        "WlcView"
+    }
+}
+
+impl Tracked for WlcOutput {
+    fn raw(self) -> uintptr_t {
+        self.0
+    }
+
+    fn kind() -> &'static str {
+        "WlcOutput"
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Generation {
+    number: u64,
+    alive: bool
+}
+
+/// Generations of handles, by kind and raw handle.
+#[derive(Debug, Default)]
+struct Generations {
+    handles: HashMap<(&'static str, uintptr_t), Generation>
+}
+
+impl Generations {
+    fn created<T: Tracked>(&mut self, handle: T) {
+        let generation = self.handles.entry((T::kind(), handle.raw()))
+            .or_insert(Generation { number: 0, alive: false });
+        generation.number += 1;
+        generation.alive = true;
+    }
+
+    fn destroyed<T: Tracked>(&mut self, handle: T) {
+        if let Some(generation) = self.handles.get_mut(&(T::kind(), handle.raw())) {
+            generation.alive = false;
+        }
+    }
+
+    /// The generation of a live handle. The null handle is always alive.
+    fn current<T: Tracked>(&self, handle: T) -> Option<u64> {
+        if handle.raw() == 0 {
+            return Some(0)
+        }
+        self.handles.get(&(T::kind(), handle.raw()))
+            .filter(|generation| generation.alive)
+            .map(|generation| generation.number)
+    }
+
+    fn check<T: Tracked>(&self, handle: T, generation: u64) -> Result<(), &'static str> {
+        match self.current(handle) {
+            Some(current) if current == generation => Ok(()),
+            Some(_) => Err("Handle was reused after it was destroyed"),
+            None => Err("Handle was destroyed")
+        }
+    }
+}
+
+/// `None` while checked mode is off.
+static GENERATIONS: Mutex<Option<Generations>> = Mutex::new(None);
+
+fn with<F, R>(f: F) -> Option<R> where F: FnOnce(&mut Generations) -> R {
+    let mut generations = GENERATIONS.lock().unwrap_or_else(|err| err.into_inner());
+    generations.as_mut().map(f)
+}
+
+/// Turns on checked mode.
+///
+/// Outputs and views which already exist are taken as alive. The crate's
+/// hooks are registered with wlc; callbacks set through `callback` before or
+/// after this are called from those hooks.
+pub fn enable() {
+    let mut generations = Generations::default();
+    for output in WlcOutput::list() {
+        generations.created(output);
+        for view in output.get_views() {
+            generations.created(view);
+        }
+    }
+    *GENERATIONS.lock().unwrap_or_else(|err| err.into_inner()) = Some(generations);
+    callback::install_hooks();
+}
+
+/// Turns off checked mode. All checks pass while it is off.
+pub fn disable() {
+    *GENERATIONS.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Whether checked mode is on.
+pub fn is_enabled() -> bool {
+    with(|_| ()).is_some()
+}
+
+/// Whether a handle is alive. Always `true` while checked mode is off.
+pub fn is_alive<T: Tracked>(handle: T) -> bool {
+    with(|generations| generations.current(handle).is_some()).unwrap_or(true)
+}
+
+/// The generation of a live handle, counting from 1 for the first time wlc
+/// created it. `None` if the handle is not alive or checked mode is off.
+pub fn generation<T: Tracked>(handle: T) -> Option<u64> {
+    with(|generations| generations.current(handle)).and_then(|generation| generation)
+}
+
+// Called from the callback hooks, which dummy callbacks do not have
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn created<T: Tracked>(handle: T) {
+    with(|generations| generations.created(handle));
+}
+
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn destroyed<T: Tracked>(handle: T) {
+    with(|generations| generations.destroyed(handle));
+}
+
+/// A handle which knows which generation it belongs to.
+///
+/// Dereferencing it gives the handle, so its methods can be called
+/// directly, but panics if the handle is no longer alive. While checked
+/// mode is off it behaves like the plain handle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Checked<T: Tracked> {
+    handle: T,
+    generation: u64
+}
+
+/// A view checked for staleness.
+pub type CheckedView = Checked<WlcView>;
+
+/// An output checked for staleness.
+pub type CheckedOutput = Checked<WlcOutput>;
+
+impl<T: Tracked> Checked<T> {
+    /// Wraps a live handle in its current generation.
+    ///
+    /// Fails if checked mode is on and the handle is not alive.
+    pub fn new(handle: T) -> Result<Checked<T>, &'static str> {
+        match with(|generations| generations.current(handle)) {
+            Some(Some(generation)) => Ok(Checked { handle, generation }),
+            Some(None) => Err("Handle is not alive"),
+            None => Ok(Checked { handle, generation: 0 })
+        }
+    }
+
+    /// The generation the handle belongs to, or 0 if it was made while
+    /// checked mode was off.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The handle, without checking it.
+    pub fn unchecked(&self) -> T {
+        self.handle
+    }
+
+    /// The handle, if it still refers to the same view or output.
+    pub fn get(&self) -> Result<T, &'static str> {
+        let generation = self.generation;
+        if generation == 0 {
+            return Ok(self.handle)
+        }
+        match with(|generations| generations.check(self.handle, generation)) {
+            Some(Err(err)) => Err(err),
+            _ => Ok(self.handle)
+        }
+    }
+
+    /// Whether the handle still refers to the same view or output.
+    pub fn is_alive(&self) -> bool {
+        self.get().is_ok()
+    }
+}
+
+impl<T: Tracked> Deref for Checked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        if let Err(err) = self.get() {
+            panic!("{} {:?} of generation {} used after it was destroyed: {}",
+                   T::kind(), self.handle, self.generation, err);
+        }
+        &self.handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generations() {
+        let mut generations = Generations::default();
+        let view = WlcView(10);
+        assert_eq!(generations.current(view), None);
+        assert_eq!(generations.current(WlcView(0)), Some(0));
+
+        generations.created(view);
+        assert_eq!(generations.current(view), Some(1));
+        assert!(generations.check(view, 1).is_ok());
+        generations.destroyed(view);
+        assert_eq!(generations.current(view), None);
+        assert_eq!(generations.check(view, 1), Err("Handle was destroyed"));
+
+        // wlc reuses the handle for a new view
+        generations.created(view);
+        assert_eq!(generations.current(view), Some(2));
+        assert_eq!(generations.check(view, 1),
+                   Err("Handle was reused after it was destroyed"));
+        assert!(generations.check(view, 2).is_ok());
+
+        // Views and outputs are counted apart
+        assert_eq!(generations.current(WlcOutput(10)), None);
+        generations.destroyed(WlcOutput(10));
+        generations.created(WlcOutput(10));
+        assert_eq!(generations.current(WlcOutput(10)), Some(1));
+    }
+}
@@ -0,0 +1,420 @@
+//! Borders and title bars drawn by the compositor.
+//!
+//! wlc does not draw any decorations, and Wayland clients that don't draw
+//! their own rely on the compositor. `Decorations` draws a border around
+//! each view's visible geometry and a title bar with the view's title and
+//! close and maximize buttons above it, using a `Canvas`.
+//!
+//! Decorations are drawn outside of the view, so the window manager should
+//! leave room for them when laying out views; `frame_geometry` and
+//! `client_geometry` convert between the two.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::*;
+//! use rustwlc::decorations::{Decorations, DecorationStyle};
+//!
+//! lazy_static! {
+//!     static ref DECORATIONS: Mutex<Decorations> =
+//!         Mutex::new(Decorations::new(DecorationStyle::default()));
+//! }
+//!
+//! extern fn view_render_post(view: WlcView) {
+//!     DECORATIONS.lock().unwrap().draw(view);
+//! }
+//!
+//! extern fn pointer_button(_view: WlcView, _time: u32, _mods: &KeyboardModifiers,
+//!                          _button: u32, state: ButtonState, point: &Point) -> bool {
+//!     let output = WlcOutput::focused();
+//!     // Don't pass clicks on decorations to clients
+//!     DECORATIONS.lock().unwrap().pointer_button(output, state, *point).is_some()
+//! }
+//! # fn main() {}
+//! ```
+
+use std::cmp;
+use std::collections::HashSet;
+
+use super::{WlcOutput, WlcView};
+use super::types::{ButtonState, Geometry, Point, ResizeEdge, Size,
+                   EDGE_NONE, RESIZE_BOTTOM, RESIZE_LEFT, RESIZE_RIGHT, RESIZE_TOP,
+                   VIEW_ACTIVATED, VIEW_FULLSCREEN, VIEW_MAXIMIZED,
+                   VIEW_BIT_OVERRIDE_REDIRECT, VIEW_BIT_POPUP, VIEW_BIT_SPLASH,
+                   VIEW_BIT_UNMANAGED};
+use super::canvas::Canvas;
+use super::font;
+use super::screenshot::scale_geometry;
+
+/// Colors used to draw a view's decorations, as red, green, blue, alpha.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Theme {
+    /// Color of the border
+    pub border: [u8; 4],
+    /// Background of the title bar
+    pub title: [u8; 4],
+    /// Color of the title text
+    pub text: [u8; 4],
+    /// Color of the button symbols
+    pub button: [u8; 4]
+}
+
+/// Sizes and colors of decorations.
+///
+/// Sizes are in output-local coordinates, and are multiplied by the
+/// output's scale when drawn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DecorationStyle {
+    /// Width of the border around the view and title bar
+    pub border_width: u32,
+    /// Height of the title bar, or 0 for no title bar
+    pub title_height: u32,
+    /// Size of a pixel of the built-in font
+    pub font_scale: u32,
+    /// Whether to draw close and maximize buttons
+    pub buttons: bool,
+    /// Colors for the view with `VIEW_ACTIVATED` set
+    pub focused: Theme,
+    /// Colors for all other views
+    pub unfocused: Theme
+}
+
+impl Default for DecorationStyle {
+    fn default() -> DecorationStyle {
+        DecorationStyle {
+            border_width: 2,
+            title_height: 18,
+            font_scale: 2,
+            buttons: true,
+            focused: Theme {
+                border: [0x28, 0x55, 0x77, 0xff],
+                title: [0x28, 0x55, 0x77, 0xff],
+                text: [0xff, 0xff, 0xff, 0xff],
+                button: [0xff, 0xff, 0xff, 0xff]
+            },
+            unfocused: Theme {
+                border: [0x33, 0x33, 0x33, 0xff],
+                title: [0x22, 0x22, 0x22, 0xff],
+                text: [0x88, 0x88, 0x88, 0xff],
+                button: [0x88, 0x88, 0x88, 0xff]
+            }
+        }
+    }
+}
+
+/// Part of a view's decorations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// The title bar, outside of the buttons
+    Title,
+    /// The close button
+    Close,
+    /// The maximize button
+    Maximize,
+    /// The border, with the edges it borders
+    Border(ResizeEdge)
+}
+
+/// Draws decorations and handles clicks on them.
+#[derive(Debug, Clone)]
+pub struct Decorations {
+    /// How decorations are drawn
+    pub style: DecorationStyle,
+    undecorated: HashSet<WlcView>
+}
+
+impl Decorations {
+    /// Creates decorations with the given style.
+    pub fn new(style: DecorationStyle) -> Decorations {
+        Decorations { style, undecorated: HashSet::new() }
+    }
+
+    /// Turns decorations on or off for a view, such as one which draws its
+    /// own.
+    pub fn set_decorated(&mut self, view: WlcView, decorated: bool) {
+        if decorated {
+            self.undecorated.remove(&view);
+        } else {
+            self.undecorated.insert(view);
+        }
+    }
+
+    /// Whether a view gets decorations.
+    ///
+    /// Popups, splash screens, unmanaged and override-redirect views and
+    /// fullscreen views are never decorated.
+    pub fn is_decorated(&self, view: WlcView) -> bool {
+        !self.undecorated.contains(&view)
+            && (view.get_type() & (VIEW_BIT_OVERRIDE_REDIRECT | VIEW_BIT_UNMANAGED
+                                   | VIEW_BIT_SPLASH | VIEW_BIT_POPUP)).is_empty()
+            && !view.get_state().contains(VIEW_FULLSCREEN)
+    }
+
+    /// Forgets a view. Call this from the `view_destroyed` callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.undecorated.remove(&view);
+    }
+
+    /// Gets the geometry of the decorations around a view's geometry.
+    pub fn frame_geometry(&self, geometry: Geometry) -> Geometry {
+        let border = self.style.border_width;
+        let top = border + self.style.title_height;
+        Geometry::new(Point::new(geometry.origin.x - border as i32,
+                                 geometry.origin.y - top as i32),
+                      Size::new(geometry.size.w + 2 * border,
+                                geometry.size.h + top + border))
+    }
+
+    /// Gets the geometry a view should have to fit decorations in `frame`.
+    pub fn client_geometry(&self, frame: Geometry) -> Geometry {
+        let border = self.style.border_width;
+        let top = border + self.style.title_height;
+        Geometry::new(Point::new(frame.origin.x + border as i32,
+                                 frame.origin.y + top as i32),
+                      Size::new(frame.size.w.saturating_sub(2 * border),
+                                frame.size.h.saturating_sub(top + border)))
+    }
+
+    /// Gets the title bar above a view's geometry.
+    pub fn title_geometry(&self, geometry: Geometry) -> Geometry {
+        let height = self.style.title_height;
+        Geometry::new(Point::new(geometry.origin.x, geometry.origin.y - height as i32),
+                      Size::new(geometry.size.w, height))
+    }
+
+    /// Gets the geometry of a button in the title bar of a view's geometry.
+    ///
+    /// Buttons are squares at the right of the title bar, with the close
+    /// button rightmost. Returns `None` if buttons are off or the title bar
+    /// is too narrow for them.
+    pub fn button_geometry(&self, geometry: Geometry, button: Region) -> Option<Geometry> {
+        let title = self.title_geometry(geometry);
+        let side = title.size.h;
+        let index = match button {
+            Region::Close => 1,
+            Region::Maximize => 2,
+            _ => return None
+        };
+        if !self.style.buttons || side == 0 || title.size.w < side * 3 {
+            return None
+        }
+        let x = title.origin.x + title.size.w as i32 - (side * index) as i32;
+        Some(Geometry::new(Point::new(x, title.origin.y), Size::new(side, side)))
+    }
+
+    /// Finds the part of the decorations around a view's geometry under a
+    /// point.
+    pub fn hit_test(&self, geometry: Geometry, point: Point) -> Option<Region> {
+        if !self.frame_geometry(geometry).contains_point_exclusive(point) {
+            return None
+        }
+        for &button in &[Region::Close, Region::Maximize] {
+            match self.button_geometry(geometry, button) {
+                Some(button_geometry) if button_geometry.contains_point_exclusive(point) => {
+                    return Some(button)
+                },
+                _ => {}
+            }
+        }
+        let title = self.title_geometry(geometry);
+        if title.size.h > 0 && title.contains_point_exclusive(point) {
+            return Some(Region::Title)
+        }
+        let (x, y) = (geometry.origin.x, geometry.origin.y);
+        let mut edges = EDGE_NONE;
+        if point.x < x {
+            edges |= RESIZE_LEFT;
+        } else if point.x >= x + geometry.size.w as i32 {
+            edges |= RESIZE_RIGHT;
+        }
+        if point.y < title.origin.y {
+            edges |= RESIZE_TOP;
+        } else if point.y >= y + geometry.size.h as i32 {
+            edges |= RESIZE_BOTTOM;
+        }
+        if edges == EDGE_NONE {
+            // Inside the view itself
+            return None
+        }
+        Some(Region::Border(edges))
+    }
+
+    /// Finds the topmost decorated view on an output whose decorations are
+    /// under a point, in output-local coordinates.
+    pub fn view_at(&self, output: WlcOutput, point: Point) -> Option<(WlcView, Region)> {
+        let mask = output.get_mask();
+        for view in output.get_views().into_iter().rev() {
+            if view.get_mask() & mask == 0 {
+                continue
+            }
+            let geometry = view.get_visible_geometry();
+            if geometry.contains_point_exclusive(point) {
+                // Views on top hide the decorations of views below
+                return None
+            }
+            if !self.is_decorated(view) {
+                continue
+            }
+            if let Some(region) = self.hit_test(geometry, point) {
+                return Some((view, region))
+            }
+        }
+        None
+    }
+
+    /// Handles a click on decorations.
+    ///
+    /// Call this from the `pointer_button` callback with the focused
+    /// output. When the close button is pressed the view is asked to close.
+    /// The maximize button toggles `VIEW_MAXIMIZED`; the window manager
+    /// still has to change the view's geometry. Clicks on the title bar and
+    /// border are left to the window manager, such as to start a move or
+    /// resize.
+    ///
+    /// Returns the view and region clicked on, or `None` if the point is
+    /// not on any decorations.
+    pub fn pointer_button(&self, output: WlcOutput, state: ButtonState, point: Point)
+                          -> Option<(WlcView, Region)> {
+        let (view, region) = self.view_at(output, point)?;
+        if state == ButtonState::Pressed {
+            match region {
+                Region::Close => view.close(),
+                Region::Maximize => {
+                    let maximized = view.get_state().contains(VIEW_MAXIMIZED);
+                    view.set_state(VIEW_MAXIMIZED, !maximized);
+                },
+                _ => {}
+            }
+        }
+        Some((view, region))
+    }
+
+    /// Draws the decorations of a view.
+    ///
+    /// Call this from the `view_render_pre` or `view_render_post` callback.
+    /// Decorations are drawn outside of the view, so either works; views
+    /// above it are drawn later and cover its decorations.
+    pub fn draw(&self, view: WlcView) {
+        if !self.is_decorated(view) {
+            return
+        }
+        let output = view.get_output();
+        let resolution = match output.get_resolution() {
+            Some(resolution) => resolution,
+            None => return
+        };
+        let scale = cmp::max(output.get_scale(), 1);
+        let theme = if view.get_state().contains(VIEW_ACTIVATED) {
+            self.style.focused
+        } else {
+            self.style.unfocused
+        };
+        let geometry = view.get_visible_geometry();
+        let frame = self.frame_geometry(geometry);
+        let border = self.style.border_width;
+        let inner_height = geometry.size.h + self.style.title_height;
+        let inner_top = geometry.origin.y - self.style.title_height as i32;
+        let borders = [
+            Geometry::new(frame.origin, Size::new(frame.size.w, border)),
+            Geometry::new(Point::new(frame.origin.x, geometry.origin.y + geometry.size.h as i32),
+                          Size::new(frame.size.w, border)),
+            Geometry::new(Point::new(frame.origin.x, inner_top),
+                          Size::new(border, inner_height)),
+            Geometry::new(Point::new(geometry.origin.x + geometry.size.w as i32, inner_top),
+                          Size::new(border, inner_height))
+        ];
+        for &rect in &borders {
+            let rect = scale_geometry(rect, scale);
+            Canvas::filled(rect.size, theme.border).push_clipped(resolution, rect.origin);
+        }
+        let title = self.title_geometry(geometry);
+        if title.size.h > 0 && title.size.w > 0 {
+            let canvas = self.draw_title_bar(geometry, &view.get_title(), theme, scale);
+            canvas.push_clipped(resolution, scale_geometry(title, scale).origin);
+        }
+    }
+
+    /// Draws the title bar of a view in framebuffer pixels.
+    fn draw_title_bar(&self, geometry: Geometry, title: &str, theme: Theme,
+                      scale: u32) -> Canvas {
+        let bar = self.title_geometry(geometry);
+        let mut canvas = Canvas::filled(scale_geometry(bar, scale).size, theme.title);
+        let font_scale = self.style.font_scale.max(1) * scale;
+        let text_height = font::GLYPH_HEIGHT * font_scale;
+        let top = (canvas.size().h as i32 - text_height as i32) / 2;
+        let padding = (4 * scale) as i32;
+        let mut text_width = canvas.size().w as i32 - 2 * padding;
+        if let Some(maximize) = self.button_geometry(geometry, Region::Maximize) {
+            text_width = (maximize.origin.x - bar.origin.x) * scale as i32 - 2 * padding;
+        }
+        if text_width > 0 {
+            let text = font::ellipsize(title, text_width as u32, font_scale);
+            canvas.draw_text(Point::new(padding, top), &text, theme.text, font_scale);
+        }
+        for &(button, symbol) in &[(Region::Close, 'x'), (Region::Maximize, 'o')] {
+            if let Some(geometry) = self.button_geometry(geometry, button) {
+                let left = (geometry.origin.x - bar.origin.x) * scale as i32;
+                let side = (geometry.size.w * scale) as i32;
+                let glyph = (font::GLYPH_WIDTH * font_scale) as i32;
+                canvas.draw_text(Point::new(left + (side - glyph) / 2, top),
+                                 &symbol.to_string(), theme.button, font_scale);
+            }
+        }
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::RESIZE_TOPLEFT;
+
+    fn style() -> DecorationStyle {
+        DecorationStyle { border_width: 2, title_height: 10, ..DecorationStyle::default() }
+    }
+
+    #[test]
+    fn frame_geometry() {
+        let decorations = Decorations::new(style());
+        let view = Geometry::new(Point::new(100, 100), Size::new(200, 100));
+        let frame = decorations.frame_geometry(view);
+        assert_eq!(frame, Geometry::new(Point::new(98, 88), Size::new(204, 114)));
+        assert_eq!(decorations.client_geometry(frame), view);
+        assert_eq!(decorations.title_geometry(view),
+                   Geometry::new(Point::new(100, 90), Size::new(200, 10)));
+    }
+
+    #[test]
+    fn hit_testing() {
+        let decorations = Decorations::new(style());
+        let view = Geometry::new(Point::new(100, 100), Size::new(200, 100));
+        assert_eq!(decorations.hit_test(view, Point::new(150, 150)), None);
+        assert_eq!(decorations.hit_test(view, Point::new(50, 50)), None);
+        assert_eq!(decorations.hit_test(view, Point::new(295, 95)), Some(Region::Close));
+        assert_eq!(decorations.hit_test(view, Point::new(285, 95)), Some(Region::Maximize));
+        assert_eq!(decorations.hit_test(view, Point::new(150, 95)), Some(Region::Title));
+        assert_eq!(decorations.hit_test(view, Point::new(98, 88)),
+                   Some(Region::Border(RESIZE_TOPLEFT)));
+        assert_eq!(decorations.hit_test(view, Point::new(300, 150)),
+                   Some(Region::Border(RESIZE_RIGHT)));
+        assert_eq!(decorations.hit_test(view, Point::new(150, 201)),
+                   Some(Region::Border(RESIZE_BOTTOM)));
+        // No room for buttons in a narrow title bar
+        let narrow = Geometry::new(Point::new(0, 20), Size::new(25, 10));
+        assert_eq!(decorations.hit_test(narrow, Point::new(20, 15)), Some(Region::Title));
+    }
+
+    #[test]
+    fn title_bar() {
+        let decorations = Decorations::new(DecorationStyle { font_scale: 1, ..style() });
+        let view = Geometry::new(Point::new(0, 20), Size::new(100, 50));
+        let theme = decorations.style.focused;
+        let canvas = decorations.draw_title_bar(view, "I", theme, 2);
+        assert_eq!(canvas.size(), Size::new(200, 20));
+        assert_eq!(canvas.pixel(0, 0), theme.title);
+        // The middle of the 'I', after 8 pixels of padding
+        assert_eq!(canvas.pixel(8 + 2 * 2, 10), theme.text);
+    }
+}
@@ -0,0 +1,85 @@
+//! Dummy timers for the event loop.
+//!
+//! There is no wlc event loop, so timers are kept in a mock table instead
+//! and only fire when the mock clock is moved on with `advance`. The table
+//! is per thread, like the mock handle table, so tests running in parallel
+//! do not fire each other's timers.
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+struct TimerData {
+    /// Milliseconds until the timer fires, `None` while disarmed
+    remaining: Cell<Option<u32>>,
+    callback: RefCell<Box<dyn FnMut() -> Option<u32>>>
+}
+
+thread_local! {
+    static TIMERS: RefCell<Vec<Weak<TimerData>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A dummy timer on wlc's event loop.
+pub struct Timer {
+    data: Rc<TimerData>
+}
+
+impl Timer {
+    /// Dummy adds a disarmed timer to the mock event loop.
+    ///
+    /// Always returns `Some`
+    pub fn new<F>(callback: F) -> Option<Timer>
+        where F: FnMut() -> Option<u32> + 'static {
+        let data = Rc::new(TimerData {
+            remaining: Cell::new(None),
+            callback: RefCell::new(Box::new(callback))
+        });
+        TIMERS.with(|timers| timers.borrow_mut().push(Rc::downgrade(&data)));
+        Some(Timer { data })
+    }
+
+    /// Dummy arms the timer to fire after `ms` milliseconds of `advance`.
+    ///
+    /// An already armed timer is rescheduled, and a delay of 0 disarms it.
+    /// Always returns `true`
+    pub fn update(&self, ms: u32) -> bool {
+        self.data.remaining.set(if ms > 0 { Some(ms) } else { None });
+        true
+    }
+
+    /// Dummy disarms the timer without removing it.
+    pub fn stop(&self) {
+        self.update(0);
+    }
+}
+
+/// Moves the mock clock on by `ms` milliseconds, firing every timer of
+/// this thread which is due. A timer fires at most once per call; if its
+/// callback returns `Some(ms)` it is re-armed from then.
+///
+/// Returns how many timers fired.
+pub fn advance(ms: u32) -> usize {
+    let timers: Vec<_> = TIMERS.with(|timers| {
+        let mut timers = timers.borrow_mut();
+        timers.retain(|timer| timer.upgrade().is_some());
+        timers.iter().filter_map(Weak::upgrade).collect()
+    });
+    let mut fired = 0;
+    for timer in timers {
+        match timer.remaining.get() {
+            Some(remaining) if remaining <= ms => {
+                timer.remaining.set(None);
+                let next = (timer.callback.borrow_mut())();
+                // A callback which dropped its own timer does not re-arm it
+                match next {
+                    Some(ms) if ms > 0 && Rc::strong_count(&timer) > 1 =>
+                        timer.remaining.set(Some(ms)),
+                    _ => {}
+                }
+                fired += 1;
+            },
+            Some(remaining) => timer.remaining.set(Some(remaining - ms)),
+            None => {}
+        }
+    }
+    fired
+}
@@ -1,10 +1,17 @@
 //! Contains dummy definitions for wlc handle types.
+//!
+//! Dummy handles have the same API as the real ones. What wlc would store
+//! for a handle is kept in a per-thread mock table: setters write it and
+//! getters read it back.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
 
 extern crate libc;
-use libc::{uint32_t, pid_t};
+use libc::{uintptr_t, pid_t};
 
 #[cfg(feature="wlc-wayland")]
-use libc::c_void;
+use libc::{c_void, uint32_t};
 
 #[cfg(feature="wlc-wayland")]
 use wayland_sys::server::{wl_resource, wl_client};
@@ -16,47 +23,151 @@ use wayland_sys::common::wl_interface;
 use super::dummy_wayland::WlcResource;
 
 use super::types::{Geometry, ResizeEdge, Size, ViewType, ViewState};
+use super::gamma::GammaRamp;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// Represents a handle to a wlc view.
 ///
-pub struct WlcView {
-    handle: uint32_t,
+pub struct WlcView(pub uintptr_t);
+
+impl fmt::Debug for WlcView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WlcView")
+            .field("handle", &self.0 as &dyn Debug)
+            .field("title", &self.get_title() as &dyn Debug)
+            .field("class", &self.get_class() as &dyn Debug)
+            .finish()
+    }
+}
+
+impl fmt::Display for WlcView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut name = self.get_title();
+        if name.is_empty() {
+            name = self.get_class();
+            if name.is_empty() {
+                name = format!("WlcView({handle})", handle=self.0);
+            }
+        }
+        write!(f, "WlcView {{ name: {name} }}", name=name)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Represents a handle to a wlc output.
+pub struct WlcOutput(pub uintptr_t);
+
+impl fmt::Debug for WlcOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WlcOutput")
+            .field("handle", &self.0 as &dyn Debug)
+            .field("name", &self.get_name() as &dyn Debug)
+            .field("views", &self.get_views() as &dyn Debug)
+            .finish()
+    }
+}
+
+impl fmt::Display for WlcOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.get_name();
+        write!(f, "WlcOutput {{ handle: {handle} name: {name} }}", handle=self.0, name=name)
+    }
+}
+
+/// What the mock keeps for a view.
+#[derive(Debug, Clone)]
+struct ViewData {
     title: String,
     class: String,
     app_id: String,
     pid: pid_t,
     output: WlcOutput,
+    parent: WlcView,
     geometry: Geometry,
     visible_geometry: Geometry,
-    focus: bool,
     mask: u32,
     view_type: ViewType,
-    view_state: ViewState,
+    view_state: ViewState
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-/// Represents a handle to a wlc output.
-pub struct WlcOutput {
-    handle: uint32_t,
+impl Default for ViewData {
+    fn default() -> ViewData {
+        ViewData {
+            title: String::new(),
+            class: String::new(),
+            app_id: String::new(),
+            pid: 0,
+            output: WlcOutput(0),
+            parent: WlcView(0),
+            geometry: Geometry::zero(),
+            visible_geometry: Geometry::zero(),
+            mask: 0,
+            view_type: ViewType::empty(),
+            view_state: ViewState::empty()
+        }
+    }
+}
+
+/// What the mock keeps for an output.
+#[derive(Debug, Clone)]
+struct OutputData {
     name: String,
     sleep: bool,
     scaling: u32,
     mask: u32,
     resolution: Option<Size>,
     virtual_resolution: Option<Size>,
-    views: Vec<WlcView>
+    views: Vec<WlcView>,
+    gamma: Option<GammaRamp>,
+    renders_scheduled: u32
+}
+
+impl Default for OutputData {
+    fn default() -> OutputData {
+        OutputData {
+            name: String::new(),
+            sleep: false,
+            scaling: 1,
+            mask: 0,
+            resolution: None,
+            virtual_resolution: None,
+            views: Vec::new(),
+            gamma: None,
+            renders_scheduled: 0
+        }
+    }
+}
+
+thread_local! {
+    static VIEWS: RefCell<BTreeMap<uintptr_t, ViewData>> =
+        const { RefCell::new(BTreeMap::new()) };
+    static OUTPUTS: RefCell<BTreeMap<uintptr_t, OutputData>> =
+        const { RefCell::new(BTreeMap::new()) };
+    static FOCUSED_OUTPUT: RefCell<WlcOutput> = const { RefCell::new(WlcOutput(0)) };
+    static FOCUSED_VIEW: RefCell<WlcView> = const { RefCell::new(WlcView(0)) };
+}
+
+fn with_view<T, F: FnOnce(&mut ViewData) -> T>(view: WlcView, f: F) -> T {
+    VIEWS.with(|views| f(views.borrow_mut().entry(view.0).or_insert_with(ViewData::default)))
+}
+
+fn with_output<T, F: FnOnce(&mut OutputData) -> T>(output: WlcOutput, f: F) -> T {
+    OUTPUTS.with(|outputs| {
+        f(outputs.borrow_mut().entry(output.0).or_insert_with(OutputData::default))
+    })
 }
 
 impl From<WlcView> for WlcOutput {
     fn from(view: WlcView) -> Self {
-        unsafe { WlcOutput::dummy(view.handle) }
+        WlcOutput(view.0)
     }
 }
 
 impl From<WlcOutput> for WlcView {
     fn from(output: WlcOutput) -> Self {
-        unsafe { WlcView::dummy(output.handle) }
+        WlcView(output.0)
     }
 }
 
@@ -92,18 +203,15 @@ impl WlcOutput {
         WlcView::from(self)
     }
 
+    /// Determines if this is a null a output (invalid).
+    #[inline]
+    pub fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
     /// Create a dummy WlcOutput for testing purposes.
     ///
-    /// # Unsafety
-    /// The following operations on a dummy WlcOutput will cause crashes:
-    ///
-    /// - `WlcOutput::focused` when wlc is not running
-    /// - `WlcOutput::list` when wlc is not running
-    /// - `WlcOutput::set_resolution` on a dummy output
-    ///
-    /// In addition, `WlcOutput::set_views` will return an error.
-    ///
-    /// All other methods can be used on dummy outputs.
+    /// Outputs other than 0 are added to `WlcOutput::list`.
     ///
     /// # Example
     /// ```rust
@@ -116,16 +224,11 @@ impl WlcOutput {
     /// }
     /// ```
     pub unsafe fn dummy(code: u32) -> WlcOutput {
-        WlcOutput {
-            handle: code,
-            name: "".into(),
-            sleep: false,
-            scaling: 1,
-            mask: 0,
-            resolution: None,
-            virtual_resolution: None,
-            views: Vec::new()
+        let output = WlcOutput(code as uintptr_t);
+        if !output.is_null() {
+            with_output(output, |_| {});
         }
+        output
     }
 
     // TODO Implement mocks for user data
@@ -146,81 +249,102 @@ impl WlcOutput {
 
     /// Dummy scheduling for output for rendering next frame.
     ///
-    /// If the output was already scheduled, this is
-    /// a no-op; if output is currently rendering,
-    /// it will render immediately after.
+    /// Only counts the call, see `get_dummy_renders_scheduled`.
     pub fn schedule_render(self) {
-        println!("Dummy call to wlc_output_schedule_render")
+        with_output(self, |data| data.renders_scheduled += 1)
     }
 
-    // TODO Mock this
+    /// How many times `schedule_render` was called on the dummy output.
+    ///
+    /// wlc has no getter for this, so this only exists on dummy outputs.
+    pub fn get_dummy_renders_scheduled(self) -> u32 {
+        with_output(self, |data| data.renders_scheduled)
+    }
 
     /// Dummy gets a list of the current outputs.
     ///
-    /// Always returns an empty list.
+    /// Returns the outputs made with `WlcOutput::dummy` or changed on this
+    /// thread.
     pub fn list() -> Vec<WlcOutput> {
-        Vec::new()
+        OUTPUTS.with(|outputs| {
+            outputs.borrow().keys().filter(|&&handle| handle != 0).map(|&handle| WlcOutput(handle))
+                .collect()
+        })
     }
 
     /// Dummy gets the currently focused output.
     ///
-    /// Always panics
+    /// Returns the output last passed to `WlcOutput::focus`.
     pub fn focused() -> WlcOutput {
-        unimplemented!()
+        FOCUSED_OUTPUT.with(|focused| *focused.borrow())
     }
 
     /// Dummy gets the name of the WlcOutput.
     pub fn get_name(self) -> String {
-        self.name
+        with_output(self, |data| data.name.clone())
+    }
+
+    /// Sets the name the dummy output reports.
+    ///
+    /// wlc has no setter for this, so this only exists on dummy outputs.
+    pub fn set_dummy_name(self, name: &str) {
+        with_output(self, |data| data.name = name.into())
     }
 
     /// Dummy gets the sleep status of the output.
     pub fn get_sleep(self) -> bool {
-        self.sleep
+        with_output(self, |data| data.sleep)
     }
 
     /// Dummy sets the sleep status of the output.
-    pub fn set_sleep(&mut self, sleep: bool) {
-        self.sleep = sleep
+    pub fn set_sleep(self, sleep: bool) {
+        with_output(self, |data| data.sleep = sleep)
     }
 
     /// Dummy gets the output's real resolution. Do not use for coordinate boundary.
     pub fn get_resolution(self) -> Option<Size> {
-        self.resolution
+        with_output(self, |data| data.resolution)
     }
 
     /// Dummy get the virtual resolution. Helpful for getting resolution on high dpi displays.
     pub fn get_virtual_resolution(self) -> Option<Size> {
-        self.virtual_resolution
+        with_output(self, |data| data.virtual_resolution)
     }
 
     /// Dummy sets the resolution of the output.
-    pub fn set_resolution(&mut self, size: Size, scaling: u32) {
-        self.scaling = scaling;
-        self.resolution = Some(Size {
-            w: size.w * scaling,
-            h: size.h * scaling
+    ///
+    /// Like wlc, `size` is the real resolution, and the virtual resolution
+    /// is it divided by `scaling`.
+    pub fn set_resolution(self, size: Size, scaling: u32) {
+        let scaling = scaling.max(1);
+        with_output(self, |data| {
+            data.scaling = scaling;
+            data.resolution = Some(size);
+            data.virtual_resolution = Some(Size {
+                w: size.w / scaling,
+                h: size.h / scaling
+            })
         })
     }
 
     /// Dummy gets the scaling for the output.
     pub fn get_scale(self) -> u32 {
-        self.scaling
+        with_output(self, |data| data.scaling)
     }
 
     /// Dummy get views in stack order.
     pub fn get_views(self) -> Vec<WlcView> {
-        self.views
+        with_output(self, |data| data.views.clone())
     }
 
     /// Dummy gets the mask of this output
     pub fn get_mask(self) -> u32 {
-        self.mask
+        with_output(self, |data| data.mask)
     }
 
     /// Dummy sets the mask for this output
-    pub fn set_mask(&mut self, mask: u32) {
-        self.mask = mask
+    pub fn set_mask(self, mask: u32) {
+        with_output(self, |data| data.mask = mask)
     }
 
     /// # Deprecated
@@ -233,15 +357,35 @@ impl WlcOutput {
     /// Dummy set the views of a given output.
     ///
     /// Always succeeds
-    pub fn set_views(&mut self, views: &[WlcView]) -> Result<(), &'static str> {
-        Ok(self.views = views.iter().map(|v| v.clone()).collect())
+    pub fn set_views(self, views: &[WlcView]) -> Result<(), &'static str> {
+        with_output(self, |data| data.views = views.to_vec());
+        Ok(())
     }
 
     /// Dummy focuses compositor on a specific output.
     ///
-    /// Does nothing.
-    pub fn focus(_output: Option<WlcOutput>) {
-        println!("Dummy call to wlc_output_focus");
+    /// Pass in Option::None for no focus.
+    pub fn focus(output: Option<WlcOutput>) {
+        FOCUSED_OUTPUT.with(|focused| *focused.borrow_mut() = output.unwrap_or(WlcOutput(0)))
+    }
+
+    /// Dummy gets the number of entries in each of this output's gamma ramps.
+    ///
+    /// Returns the size of the last ramp set with `set_gamma`, or 0 if none
+    /// was set.
+    pub fn get_gamma_size(self) -> u16 {
+        with_output(self, |data| data.gamma.as_ref().map_or(0, |ramp| ramp.size() as u16))
+    }
+
+    /// Dummy sets the gamma ramps of this output.
+    ///
+    /// Any valid ramp is accepted, and sets the gamma size.
+    pub fn set_gamma(self, ramp: &GammaRamp) -> Result<(), &'static str> {
+        if !ramp.is_valid() {
+            return Err("Gamma ramp size does not match output")
+        }
+        with_output(self, |data| data.gamma = Some(ramp.clone()));
+        Ok(())
     }
 }
 
@@ -258,17 +402,6 @@ impl WlcView {
 
     /// Create a dummy WlcView for testing purposes.
     ///
-    /// # Unsafety
-    /// The following methods on views may crash the program:
-    ///
-    /// - `WlcView::focus` if wlc is not running
-    /// - `WlcView::send_to_back` if wlc is not running
-    /// - `WlcView::send_below` if wlc is not running
-    /// - `WlcView::bring_above` if wlc is not running
-    /// - `WlcView::bring_to_font` if wlc is not running
-    ///
-    /// All other methods can be used on dummy views.
-    ///
     /// # Note
     /// `WlcView::root()` is equivalent to `WlcView::dummy(0)`.
     ///
@@ -287,20 +420,7 @@ impl WlcView {
     /// }
     /// ```
     pub unsafe fn dummy(code: u32) -> WlcView {
-        WlcView {
-            handle: code,
-            title: "".into(),
-            class: "".into(),
-            app_id: "".into(),
-            pid: 0 as pid_t,
-            output: WlcOutput::dummy(0),
-            geometry: Geometry::zero(),
-            visible_geometry: Geometry::zero(),
-            focus: false,
-            mask: 0,
-            view_type: ViewType::empty(),
-            view_state: ViewState::empty(),
-        }
+        WlcView(code as uintptr_t)
     }
 
     /// Returns a reference to the root window (desktop background).
@@ -312,9 +432,7 @@ impl WlcView {
     /// assert!(view.is_root());
     /// ```
     pub fn root() -> WlcView {
-        unsafe {
-            WlcView::dummy(0)
-        }
+        WlcView(0)
     }
 
     /// Whether this view is the root window (desktop background).
@@ -328,7 +446,7 @@ impl WlcView {
     /// ```
     #[inline]
     pub fn is_root(self) -> bool {
-        self.handle == 0
+        self.0 == 0
     }
 
     /// Whether this view is not the root window (desktop background).
@@ -345,7 +463,7 @@ impl WlcView {
     /// ```
     #[inline]
     pub fn is_window(self) -> bool {
-        self.handle != 0
+        self.0 != 0
     }
 
     // TODO Mock user data
@@ -373,17 +491,26 @@ impl WlcView {
 
     /// Dummy gets the WlcOutput this view is currently part of.
     pub fn get_output(self) -> WlcOutput {
-        self.output
+        with_view(self, |data| data.output)
     }
 
     /// Dummy sets the output that the view renders on.
-    pub fn set_output(&mut self, output: WlcOutput) {
-        self.output = output
+    pub fn set_output(self, output: WlcOutput) {
+        with_view(self, |data| data.output = output)
     }
 
     /// Dummy brings this view to focus.
-    pub fn focus(&mut self) {
-        self.focus = true
+    ///
+    /// Can be called on `WlcView::root()` to lose all focus.
+    pub fn focus(self) {
+        FOCUSED_VIEW.with(|focused| *focused.borrow_mut() = self)
+    }
+
+    /// Gets the view last passed to `WlcView::focus`, or the root view.
+    ///
+    /// wlc has no getter for this, so this only exists on dummy views.
+    pub fn dummy_focused() -> WlcView {
+        FOCUSED_VIEW.with(|focused| *focused.borrow())
     }
 
     /// Dummy sends the view to the back of the compositor
@@ -417,93 +544,104 @@ impl WlcView {
 
     /// Dummy gets the current visibilty bitmask for the view.
     pub fn get_mask(self) -> u32 {
-        self.mask
+        with_view(self, |data| data.mask)
     }
 
     /// Dummy sets the visibilty bitmask for the view.
-    pub fn set_mask(&mut self, mask: u32) {
-        self.mask = mask
+    pub fn set_mask(self, mask: u32) {
+        with_view(self, |data| data.mask = mask)
     }
 
     /// Dummy gets the geometry of the view.
     ///
     /// Always returns Some
     pub fn get_geometry(self) -> Option<Geometry> {
-        Some(self.geometry)
+        Some(with_view(self, |data| data.geometry))
     }
 
     /// Dummy gets the geometry of the view (that wlc displays).
     pub fn get_visible_geometry(self) -> Geometry {
-        self.visible_geometry
+        with_view(self, |data| data.visible_geometry)
     }
 
     /// Dummy sets the geometry of the view.
     ///
-    /// Ignores `edges`
-    pub fn set_geometry(&mut self, _edges: ResizeEdge, geometry: Geometry) {
-        self.geometry = geometry;
+    /// Ignores `edges`, and sets the visible geometry as well.
+    pub fn set_geometry(self, _edges: ResizeEdge, geometry: Geometry) {
+        with_view(self, |data| {
+            data.geometry = geometry;
+            data.visible_geometry = geometry;
+        })
     }
 
     /// Gets the type bitfield of the curent view
     pub fn get_type(self) -> ViewType {
-        self.view_type
+        with_view(self, |data| data.view_type)
     }
 
     /// Dummy set flag in the type field. Toggle indicates whether it is set.
-    pub fn set_type(&mut self, view_type: ViewType, toggle: bool) {
-        if toggle {
-            self.view_type.insert(view_type)
+    pub fn set_type(self, view_type: ViewType, toggle: bool) {
+        with_view(self, |data| if toggle {
+            data.view_type.insert(view_type)
         } else {
-            self.view_type.remove(view_type)
-        }
+            data.view_type.remove(view_type)
+        })
     }
 
     /// Dummy get the current ViewState bitfield.
     pub fn get_state(self) -> ViewState {
-        self.view_state
+        with_view(self, |data| data.view_state)
     }
 
     /// Dummy set ViewState bit. Toggle indicates whether it is set or not.
-    pub fn set_state(&mut self, state: ViewState, toggle: bool) {
-        if toggle {
-            self.view_state.insert(state)
+    pub fn set_state(self, state: ViewState, toggle: bool) {
+        with_view(self, |data| if toggle {
+            data.view_state.insert(state)
         } else {
-            self.view_state.remove(state)
-        }
+            data.view_state.remove(state)
+        })
     }
 
     /// Dummy gets parent view, returns `WlcView::root()` if this view has no parent.
-    ///
-    /// Will always panic
     pub fn get_parent(self) -> WlcView {
-        unimplemented!()
+        with_view(self, |data| data.parent)
     }
 
     /// Dummy set the parent of this view.
-    ///
-    /// Will always panic
-    pub fn set_parent(self, _parent: &WlcView) {
-        unimplemented!()
+    pub fn set_parent(self, parent: &WlcView) {
+        with_view(self, |data| data.parent = *parent)
     }
 
     /// Dummy get the title of the view
     pub fn get_title(self) -> String {
-        self.title
+        with_view(self, |data| data.title.clone())
     }
 
     /// Dummy get class (shell surface only).
     pub fn get_class(self) -> String {
-        self.class
+        with_view(self, |data| data.class.clone())
     }
 
     /// Dummy get app id (xdg-surface only).
     pub fn get_app_id(self) -> String {
-        self.app_id
+        with_view(self, |data| data.app_id.clone())
     }
 
     /// Get the pid associated with this `WlcView`.
     pub fn get_pid(self) -> pid_t {
-        self.pid
+        with_view(self, |data| data.pid)
+    }
+
+    /// Sets the title, class, app id and pid the dummy view reports.
+    ///
+    /// wlc has no setters for these, so this only exists on dummy views.
+    pub fn set_dummy_properties(self, title: &str, class: &str, app_id: &str, pid: pid_t) {
+        with_view(self, |data| {
+            data.title = title.into();
+            data.class = class.into();
+            data.app_id = app_id.into();
+            data.pid = pid;
+        })
     }
 
     // TODO Mock these functions
@@ -539,3 +677,56 @@ impl WlcView {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn dummy_views() {
+        let view = unsafe { WlcView::dummy(1) };
+        assert_eq!(view.get_title(), "");
+        assert!(view.get_parent().is_root());
+        view.set_dummy_properties("title", "class", "app", 10);
+        assert_eq!(view.get_class(), "class");
+        assert_eq!(format!("{}", view), "WlcView { name: title }");
+
+        let geometry = Geometry::new(Point::new(1, 2), Size::new(3, 4));
+        view.set_geometry(EDGE_NONE, geometry);
+        assert_eq!(view.get_geometry(), Some(geometry));
+        assert_eq!(view.get_visible_geometry(), geometry);
+        view.set_mask(2);
+        view.set_state(VIEW_MAXIMIZED, true);
+        view.set_parent(&WlcView(2));
+        assert_eq!((view.get_mask(), view.get_state()), (2, VIEW_MAXIMIZED));
+        assert_eq!(view.get_parent(), WlcView(2));
+        // Other handles are left alone
+        assert_eq!(WlcView(2).get_mask(), 0);
+        assert!(WlcView::dummy_focused().is_root());
+        view.focus();
+        assert_eq!(WlcView::dummy_focused(), view);
+        WlcView::root().focus();
+        assert!(WlcView::dummy_focused().is_root());
+    }
+
+    #[test]
+    fn dummy_outputs() {
+        let output = unsafe { WlcOutput::dummy(1) };
+        assert_eq!(WlcOutput::list(), vec![output]);
+        assert!(WlcOutput::focused().is_null());
+        WlcOutput::focus(Some(output));
+        assert_eq!(WlcOutput::focused(), output);
+
+        output.set_resolution(Size::new(1600, 1200), 2);
+        assert_eq!(output.get_resolution(), Some(Size::new(1600, 1200)));
+        assert_eq!(output.get_virtual_resolution(), Some(Size::new(800, 600)));
+        // Changing only the scale keeps the mode
+        output.set_resolution(output.get_resolution().unwrap(), 1);
+        assert_eq!(output.get_virtual_resolution(), Some(Size::new(1600, 1200)));
+        output.set_views(&[WlcView(1), WlcView(2)]).unwrap();
+        assert_eq!(output.get_views(), vec![WlcView(1), WlcView(2)]);
+        assert_eq!(output.get_gamma_size(), 0);
+        output.set_gamma(&gamma::GammaRamp::identity(4)).unwrap();
+        assert_eq!(output.get_gamma_size(), 4);
+    }
+}
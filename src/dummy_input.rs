@@ -18,20 +18,48 @@ pub mod pointer {
     /// Sets the current mouse position. Required on mouse move callback.
     pub fn set_position(point: Point) {
     }
+
+    /// Gets the current position of the mouse.
+    pub fn get_position_v2() -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    /// Sets the current mouse position. Required on mouse move callback.
+    pub fn set_position_v2(x: f64, y: f64) {
+    }
 }
 
 pub mod keyboard {
 //! Methods for interacting with the keyboard
+    use std::cell::RefCell;
     use super::super::types::{KeyboardModifiers};
     use super::super::xkb::Keysym;
 
+    thread_local! {
+        // Keys reported as held, per thread like the dummy handles' state
+        static HELD_KEYS: RefCell<Vec<u32>> = const { RefCell::new(Vec::new()) };
+    }
+
     /// Get currently held keys.
     /// # Panics
     /// All the time, this function hasn't been implemented yet
+    #[deprecated(since="0.7.1", note="Use current_keys()->Vec<u32> instead")]
     pub fn get_current_keys<'a>() -> &'a[u32] {
         unimplemented!();
     }
 
+    /// Get currently held keys, as set by `set_dummy_keys`.
+    pub fn current_keys() -> Vec<u32> {
+        HELD_KEYS.with(|keys| keys.borrow().clone())
+    }
+
+    /// Sets the keys `current_keys` reports as held on this thread.
+    ///
+    /// wlc has no setter for these, so this only exists on dummy input.
+    pub fn set_dummy_keys(keys: &[u32]) {
+        HELD_KEYS.with(|held| *held.borrow_mut() = keys.to_vec());
+    }
+
     /// Gets a keysym given a key and modifiers.
     pub fn get_keysym_for_key(key: u32, modifiers: KeyboardModifiers) -> Keysym {
         unimplemented!()
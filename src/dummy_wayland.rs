@@ -4,6 +4,7 @@ use wayland_sys::server::{wl_display};
 use libc::uintptr_t;
 
 use types::{Size, Geometry};
+use render::SurfaceTextures;
 
 
 /// ## Requires `wlc-wayland` feature
@@ -51,4 +52,13 @@ impl WlcResource {
     pub fn get_subsurface_geometry(self) -> Geometry {
         self.subsurface_geometry
     }
+
+    /// # Requires `wlc-wayland` feature
+    ///
+    /// Gets the textures wlc's renderer uses for this surface.
+    ///
+    /// Dummy surfaces have no textures, so this is always `None`.
+    pub fn get_textures(self) -> Option<SurfaceTextures> {
+        None
+    }
 }
@@ -0,0 +1,136 @@
+//! Timers on wlc's event loop.
+//!
+//! wlc runs a single event loop on the compositor thread. Work that has to
+//! happen later (animations, key repeat, gradual colour changes) can be
+//! scheduled on it with a `Timer` instead of spawning threads, which are not
+//! allowed to call into wlc.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::event_loop::Timer;
+//!
+//! let mut ticks = 0;
+//! // Runs every 100ms, ten times
+//! let timer = Timer::new(move || {
+//!     ticks += 1;
+//!     if ticks < 10 { Some(100) } else { None }
+//! }).expect("Could not create timer");
+//! timer.update(100);
+//! // The timer is removed from the event loop when it is dropped,
+//! // so it must be kept somewhere for as long as it should run.
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::ptr;
+use std::rc::Rc;
+
+use libc::{c_int, c_void};
+
+/// Opaque wlc event source
+#[allow(non_camel_case_types)]
+enum wlc_event_source {}
+
+#[cfg_attr(feature = "static-wlc", link(name = "wlc", kind = "static"))]
+#[cfg_attr(not(feature = "static-wlc"), link(name = "wlc"))]
+extern "C" {
+    fn wlc_event_loop_add_timer(cb: extern "C" fn(arg: *mut c_void) -> c_int,
+                                arg: *mut c_void) -> *mut wlc_event_source;
+
+    fn wlc_event_source_timer_update(source: *mut wlc_event_source,
+                                     ms_delay: i32) -> bool;
+
+    fn wlc_event_source_remove(source: *mut wlc_event_source);
+}
+
+struct TimerData {
+    /// Null once the timer is removed
+    source: Cell<*mut wlc_event_source>,
+    callback: RefCell<Box<dyn FnMut() -> Option<u32>>>
+}
+
+/// A timer on wlc's event loop.
+///
+/// A new timer is disarmed; call `update` to schedule it. When it fires
+/// the callback is run on the compositor thread. If the callback returns
+/// `Some(ms)` the timer is re-armed to fire again after that many
+/// milliseconds, otherwise it stays disarmed until the next `update`.
+///
+/// The timer is removed from the event loop when dropped. A callback may
+/// drop its own timer, such as one kept in a `RefCell` it can reach; the
+/// callback then finishes running but the timer does not fire again.
+///
+/// Timers can only be used on the compositor thread, so a `Timer` is not
+/// `Send`. Neither is anything which holds one; such values are best kept
+/// in a `thread_local!` on the compositor thread.
+pub struct Timer {
+    data: Rc<TimerData>
+}
+
+impl Timer {
+    /// Adds a disarmed timer to the event loop.
+    ///
+    /// Returns `None` if wlc could not create the timer, such as when wlc
+    /// has not been initialized.
+    pub fn new<F>(callback: F) -> Option<Timer>
+        where F: FnMut() -> Option<u32> + 'static {
+        let data = Rc::new(TimerData {
+            source: Cell::new(ptr::null_mut()),
+            callback: RefCell::new(Box::new(callback))
+        });
+        // The event loop holds a reference of its own, released on drop
+        let arg = Rc::into_raw(data.clone()) as *mut c_void;
+        let source = unsafe { wlc_event_loop_add_timer(timer_callback, arg) };
+        if source.is_null() {
+            unsafe { drop(Rc::from_raw(arg as *const TimerData)); }
+            return None
+        }
+        data.source.set(source);
+        Some(Timer { data })
+    }
+
+    /// Arms the timer to fire after `ms` milliseconds.
+    ///
+    /// An already armed timer is rescheduled. A delay of 0 disarms the
+    /// timer. Returns `false` if wlc could not update the timer.
+    pub fn update(&self, ms: u32) -> bool {
+        unsafe { wlc_event_source_timer_update(self.data.source.get(), clamp_delay(ms)) }
+    }
+
+    /// Disarms the timer without removing it.
+    pub fn stop(&self) {
+        self.update(0);
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let source = self.data.source.replace(ptr::null_mut());
+        unsafe {
+            wlc_event_source_remove(source);
+            drop(Rc::from_raw(Rc::as_ptr(&self.data)));
+        }
+    }
+}
+
+extern "C" fn timer_callback(arg: *mut c_void) -> c_int {
+    let arg = arg as *const TimerData;
+    // Holds the data for the whole call, in case the callback drops its timer
+    let data = unsafe {
+        Rc::increment_strong_count(arg);
+        Rc::from_raw(arg)
+    };
+    let next = (data.callback.borrow_mut())();
+    let source = data.source.get();
+    match next {
+        Some(ms) if ms > 0 && !source.is_null() => unsafe {
+            wlc_event_source_timer_update(source, clamp_delay(ms));
+        },
+        _ => {}
+    }
+    0
+}
+
+/// wlc takes a signed delay, anything longer is as good as never.
+fn clamp_delay(ms: u32) -> i32 {
+    if ms > i32::MAX as u32 { i32::MAX } else { ms as i32 }
+}
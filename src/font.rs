@@ -0,0 +1,251 @@
+//! A built-in 5x7 bitmap font for printable ASCII.
+//!
+//! The font is small and has no dependencies, which makes it suitable for
+//! title bars and on-screen messages drawn with `render::write_pixels`.
+//! Characters outside of printable ASCII are drawn as `?`.
+//!
+//! # Example
+//! ```rust
+//! use rustwlc::Size;
+//! use rustwlc::font;
+//! use rustwlc::image::{Image, PixelFormat};
+//!
+//! let size = font::text_size("Hello", 2);
+//! let mut image = Image::new(size, PixelFormat::Rgba8888);
+//! font::draw_text(&mut image, 0, 0, "Hello", [255, 255, 255, 255], 2);
+//! ```
+
+use super::types::Size;
+use super::image::{Image, PixelFormat};
+
+/// Width of a glyph in font pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+/// Height of a glyph in font pixels.
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Horizontal distance between the start of two glyphs, in font pixels.
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// Glyphs for ' ' to '~', one byte per column, least significant bit at
+/// the top.
+static GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x56, 0x20, 0x50], // &
+    [0x00, 0x00, 0x07, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x2a, 0x1c, 0x7f, 0x1c, 0x2a], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x10, 0x08, 0x08, 0x10, 0x08], // ~
+];
+
+/// Gets the columns of a character's glyph.
+///
+/// Characters without a glyph get the glyph for `?`.
+pub fn glyph(c: char) -> &'static [u8; 5] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize
+    };
+    &GLYPHS[index]
+}
+
+/// Whether a pixel of a character's glyph is set.
+///
+/// Coordinates are in font pixels; anything outside the glyph is unset.
+pub fn is_set(c: char, x: u32, y: u32) -> bool {
+    x < GLYPH_WIDTH && y < GLYPH_HEIGHT && glyph(c)[x as usize] & (1 << y) != 0
+}
+
+/// Gets the size of a line of text drawn with each font pixel `scale`
+/// pixels wide.
+pub fn text_size(text: &str, scale: u32) -> Size {
+    let chars = text.chars().count() as u32;
+    if chars == 0 {
+        return Size::new(0, GLYPH_HEIGHT * scale)
+    }
+    Size::new((chars * ADVANCE - 1) * scale, GLYPH_HEIGHT * scale)
+}
+
+/// Shortens text to fit in `width` pixels, ending it with `...` if it had
+/// to be cut.
+pub fn ellipsize(text: &str, width: u32, scale: u32) -> String {
+    if text_size(text, scale).w <= width {
+        return text.into()
+    }
+    let fits = (width / scale.max(1) + 1) / ADVANCE;
+    if fits <= 3 {
+        return text.chars().take(fits as usize).collect()
+    }
+    let mut short: String = text.chars().take(fits as usize - 3).collect();
+    short.push_str("...");
+    short
+}
+
+/// Calls `f` with the coordinates of every pixel set in a line of text
+/// with its top left corner at `(x, y)`.
+///
+/// Coordinates may be negative or beyond any image being drawn to.
+pub fn rasterize<F>(x: i32, y: i32, text: &str, scale: u32, mut f: F)
+    where F: FnMut(i64, i64) {
+    let scale = scale.max(1);
+    for (index, c) in text.chars().enumerate() {
+        let left = x as i64 + (index as u32 * ADVANCE * scale) as i64;
+        for gx in 0..GLYPH_WIDTH {
+            for gy in 0..GLYPH_HEIGHT {
+                if !is_set(c, gx, gy) {
+                    continue
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        f(left + (gx * scale + dx) as i64,
+                          y as i64 + (gy * scale + dy) as i64);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws a line of text into an image, with its top left corner at
+/// `(x, y)`.
+///
+/// `color` is red, green, blue, alpha and is written as is, without
+/// blending. Pixels outside of the image are skipped. Returns the width of
+/// the text in pixels. See `Canvas::draw_text` for blended text.
+pub fn draw_text(image: &mut Image, x: i32, y: i32, text: &str,
+                 color: [u8; 4], scale: u32) -> u32 {
+    let pixel = match image.format {
+        PixelFormat::Rgba8888 => color,
+        PixelFormat::Bgra8888 => [color[2], color[1], color[0], color[3]]
+    };
+    let (width, height) = (image.size.w as i64, image.size.h as i64);
+    rasterize(x, y, text, scale, |px, py| {
+        if px >= 0 && py >= 0 && px < width && py < height {
+            image.set_pixel(px as u32, py as u32, pixel);
+        }
+    });
+    text_size(text, scale.max(1)).w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs() {
+        // The top of an 'A' is three pixels in the middle
+        let top: Vec<bool> = (0..GLYPH_WIDTH).map(|x| is_set('A', x, 0)).collect();
+        assert_eq!(top, vec![false, true, true, true, false]);
+        assert!(is_set('|', 2, 6));
+        assert!(!is_set('|', 2, 7));
+        assert_eq!(glyph('\u{e9}'), glyph('?'));
+    }
+
+    #[test]
+    fn measuring() {
+        assert_eq!(text_size("", 1), Size::new(0, 7));
+        assert_eq!(text_size("ab", 2), Size::new(22, 14));
+        assert_eq!(ellipsize("short", 100, 1), "short");
+        // 6 characters fit in 35 pixels
+        assert_eq!(ellipsize("a long title", 35, 1), "a l...");
+    }
+
+    #[test]
+    fn drawing() {
+        let mut image = Image::new(Size::new(6, 7), PixelFormat::Bgra8888);
+        assert_eq!(draw_text(&mut image, 1, 0, "I", [255, 0, 0, 255], 1), 5);
+        // The middle of the 'I' is in column 3 after the offset
+        assert_eq!(image.rgba(3, 3), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(3, 3), [0, 0, 255, 255]);
+        assert_eq!(image.rgba(1, 3), [0, 0, 0, 0]);
+        // Clipped at the right edge without panicking
+        draw_text(&mut image, 4, 0, "W", [255, 255, 255, 255], 1);
+    }
+}
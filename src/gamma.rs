@@ -0,0 +1,406 @@
+//! Gamma ramps for colour temperature and brightness control.
+//!
+//! wlc lets a compositor replace each output's gamma ramp, a lookup table
+//! mapping every input intensity of the red, green and blue channels to an
+//! output intensity. This is how tools like redshift dim the screen or make
+//! it warmer at night.
+//!
+//! A `GammaRamp` is generated from `ColorSettings` and applied to an output
+//! with `WlcOutput::set_gamma`. A `NightLight` does this on a timer,
+//! moving between a day and a night colour temperature around sunrise and
+//! sunset.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::WlcOutput;
+//! use rustwlc::gamma::{ColorSettings, GammaRamp};
+//!
+//! let output = WlcOutput::focused();
+//! let settings = ColorSettings { temperature: 4500, brightness: 0.8,
+//!                                .. ColorSettings::default() };
+//! if let Some(ramp) = GammaRamp::for_output(output, &settings) {
+//!     output.set_gamma(&ramp).expect("Gamma size changed");
+//! }
+//! ```
+
+use super::WlcOutput;
+#[cfg(not(feature = "dummy"))]
+use super::event_loop::Timer;
+#[cfg(feature = "dummy")]
+use super::dummy_event_loop::Timer;
+
+/// Colour temperature of neutral white, which leaves colours unchanged.
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+/// Lowest supported colour temperature.
+pub const MIN_TEMPERATURE: u32 = 1000;
+
+/// Highest supported colour temperature.
+pub const MAX_TEMPERATURE: u32 = 25000;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// Colour adjustments used to generate a `GammaRamp`.
+///
+/// The default settings produce an identity ramp which leaves colours
+/// unchanged.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorSettings {
+    /// White point in Kelvin. Lower is warmer (redder), 6500 is neutral.
+    pub temperature: u32,
+    /// Gamma correction per channel (red, green, blue). 1.0 is linear.
+    pub gamma: [f64; 3],
+    /// Multiplier for all channels. 1.0 is unchanged, 0.0 is black.
+    pub brightness: f64,
+    /// Contrast around mid-grey. 1.0 is unchanged, 0.0 is flat grey.
+    pub contrast: f64,
+    /// Whether to invert all colours
+    pub inverted: bool
+}
+
+impl Default for ColorSettings {
+    fn default() -> ColorSettings {
+        ColorSettings {
+            temperature: NEUTRAL_TEMPERATURE,
+            gamma: [1.0, 1.0, 1.0],
+            brightness: 1.0,
+            contrast: 1.0,
+            inverted: false
+        }
+    }
+}
+
+impl ColorSettings {
+    /// Maps an intensity in `0.0 ..= 1.0` through the settings for one
+    /// channel (0 = red, 1 = green, 2 = blue).
+    pub fn map(&self, channel: usize, value: f64) -> f64 {
+        self.map_white(temperature_to_rgb(self.temperature), channel, value)
+    }
+
+    /// Like `map`, with the white point of the temperature already worked
+    /// out, so ramps do not redo it for every entry.
+    fn map_white(&self, white: [f64; 3], channel: usize, value: f64) -> f64 {
+        let mut value = (value - 0.5) * self.contrast + 0.5;
+        value *= self.brightness * white[channel];
+        value = clamp(value);
+        let gamma = self.gamma[channel];
+        if gamma > 0.0 {
+            value = value.powf(1.0 / gamma);
+        }
+        if self.inverted {
+            value = 1.0 - value;
+        }
+        clamp(value)
+    }
+}
+
+/// A gamma lookup table for each colour channel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GammaRamp {
+    /// Red channel lookup table
+    pub red: Vec<u16>,
+    /// Green channel lookup table
+    pub green: Vec<u16>,
+    /// Blue channel lookup table
+    pub blue: Vec<u16>
+}
+
+impl GammaRamp {
+    /// Creates a ramp of the given size which leaves colours unchanged.
+    pub fn identity(size: usize) -> GammaRamp {
+        GammaRamp::new(size, &ColorSettings::default())
+    }
+
+    /// Creates a ramp of the given size from colour settings.
+    pub fn new(size: usize, settings: &ColorSettings) -> GammaRamp {
+        let white = temperature_to_rgb(settings.temperature);
+        let channel = |index: usize| -> Vec<u16> {
+            (0..size).map(|step| {
+                let value = if size > 1 { step as f64 / (size - 1) as f64 } else { 1.0 };
+                (settings.map_white(white, index, value) * u16::MAX as f64).round() as u16
+            }).collect()
+        };
+        GammaRamp { red: channel(0), green: channel(1), blue: channel(2) }
+    }
+
+    /// Creates a ramp sized for an output from colour settings.
+    ///
+    /// Returns `None` if the output does not support gamma ramps.
+    pub fn for_output(output: WlcOutput, settings: &ColorSettings) -> Option<GammaRamp> {
+        match output.get_gamma_size() {
+            0 => None,
+            size => Some(GammaRamp::new(size as usize, settings))
+        }
+    }
+
+    /// The number of entries in each channel.
+    pub fn size(&self) -> usize {
+        self.red.len()
+    }
+
+    /// Whether all three channels have the same number of entries.
+    pub fn is_valid(&self) -> bool {
+        self.red.len() == self.green.len() && self.red.len() == self.blue.len()
+            && self.red.len() <= u16::MAX as usize
+    }
+}
+
+/// Gets the red, green and blue multipliers for a colour temperature.
+///
+/// The temperature is clamped to `MIN_TEMPERATURE ..= MAX_TEMPERATURE`.
+/// Neutral white (6500K) gives `[1.0, 1.0, 1.0]`; warmer temperatures
+/// reduce green and blue, cooler ones reduce red.
+pub fn temperature_to_rgb(temperature: u32) -> [f64; 3] {
+    if temperature == NEUTRAL_TEMPERATURE {
+        return [1.0, 1.0, 1.0]
+    }
+    let color = blackbody(temperature);
+    let neutral = blackbody(NEUTRAL_TEMPERATURE);
+    [clamp(color[0] / neutral[0]),
+     clamp(color[1] / neutral[1]),
+     clamp(color[2] / neutral[2])]
+}
+
+/// Approximates the colour of a black body, after Tanner Helland.
+fn blackbody(temperature: u32) -> [f64; 3] {
+    let temperature = temperature.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE);
+    let t = temperature as f64 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_727_446 * (t - 60.0).powf(-0.133_204_759_2)
+    };
+    let green = if t <= 66.0 {
+        99.470_802_586_1 * t.ln() - 161.119_568_166_1
+    } else {
+        288.122_169_528_3 * (t - 60.0).powf(-0.075_514_849_2)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_731_223_1 * (t - 10.0).ln() - 305.044_792_730_7
+    };
+    [clamp(red / 255.0), clamp(green / 255.0), clamp(blue / 255.0)]
+}
+
+fn clamp(value: f64) -> f64 {
+    value.clamp(0.0, 1.0)
+}
+
+/// A gradual change of colour, such as sunrise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Transition {
+    /// When the transition starts, in seconds after local midnight
+    pub start: u32,
+    /// How long the transition takes, in seconds
+    pub duration: u32
+}
+
+impl Transition {
+    /// Creates a transition starting at a local time of day.
+    pub fn at(hour: u32, minute: u32, duration: u32) -> Transition {
+        Transition { start: (hour * 60 + minute) * 60 % SECONDS_PER_DAY, duration }
+    }
+
+    fn end(&self) -> u32 {
+        (self.start + self.duration) % SECONDS_PER_DAY
+    }
+}
+
+/// Seconds from `from` until `to`, going forward around midnight.
+fn seconds_between(from: u32, to: u32) -> u32 {
+    (to + SECONDS_PER_DAY - from % SECONDS_PER_DAY) % SECONDS_PER_DAY
+}
+
+/// Automatically warms the screen at night, like redshift.
+///
+/// The temperature is `day_temperature` between the end of `sunrise` and
+/// the start of `sunset`, `night_temperature` between the end of `sunset`
+/// and the start of `sunrise`, and changes linearly in between.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NightLight {
+    /// Settings used for everything other than the temperature
+    pub settings: ColorSettings,
+    /// Temperature during the day
+    pub day_temperature: u32,
+    /// Temperature during the night
+    pub night_temperature: u32,
+    /// Change from night to day
+    pub sunrise: Transition,
+    /// Change from day to night
+    pub sunset: Transition,
+    /// How often to update the gamma ramps, in milliseconds
+    pub interval: u32
+}
+
+impl Default for NightLight {
+    fn default() -> NightLight {
+        NightLight {
+            settings: ColorSettings::default(),
+            day_temperature: NEUTRAL_TEMPERATURE,
+            night_temperature: 3500,
+            sunrise: Transition::at(6, 0, 60 * 60),
+            sunset: Transition::at(19, 30, 60 * 60),
+            interval: 10_000
+        }
+    }
+}
+
+impl NightLight {
+    /// How far into the night it is at a time of day, from 0.0 (day) to
+    /// 1.0 (night).
+    pub fn night_factor(&self, seconds: u32) -> f64 {
+        let seconds = seconds % SECONDS_PER_DAY;
+        let into_sunrise = seconds_between(self.sunrise.start, seconds);
+        if into_sunrise < self.sunrise.duration {
+            return 1.0 - into_sunrise as f64 / self.sunrise.duration as f64
+        }
+        let into_sunset = seconds_between(self.sunset.start, seconds);
+        if into_sunset < self.sunset.duration {
+            return into_sunset as f64 / self.sunset.duration as f64
+        }
+        let day_length = seconds_between(self.sunrise.end(), self.sunset.start);
+        if seconds_between(self.sunrise.end(), seconds) < day_length {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// The colour temperature at a time of day, in seconds after midnight.
+    pub fn temperature_at(&self, seconds: u32) -> u32 {
+        let day = self.day_temperature as f64;
+        let night = self.night_temperature as f64;
+        (day + (night - day) * self.night_factor(seconds)).round() as u32
+    }
+
+    /// The colour settings at a time of day, in seconds after midnight.
+    pub fn settings_at(&self, seconds: u32) -> ColorSettings {
+        ColorSettings { temperature: self.temperature_at(seconds), .. self.settings }
+    }
+
+    /// Applies the settings for the current local time to every output.
+    pub fn apply_now(&self) {
+        let settings = self.settings_at(local_seconds_of_day());
+        for output in WlcOutput::list() {
+            if let Some(ramp) = GammaRamp::for_output(output, &settings) {
+                let _ = output.set_gamma(&ramp);
+            }
+        }
+    }
+
+    /// Starts updating every output's gamma ramp on a timer.
+    ///
+    /// The ramps are applied straight away and then every `interval`
+    /// milliseconds, so a long transition changes smoothly. Dropping the
+    /// returned timer stops the updates but leaves the last ramps applied;
+    /// use `reset_gamma` to restore them.
+    pub fn start(self) -> Option<Timer> {
+        let interval = self.interval.max(1);
+        let timer = Timer::new(move || {
+            self.apply_now();
+            Some(interval)
+        });
+        if let Some(ref timer) = timer {
+            timer.update(1);
+        }
+        timer
+    }
+}
+
+/// Restores the identity gamma ramp on every output.
+pub fn reset_gamma() {
+    for output in WlcOutput::list() {
+        if let Some(ramp) = GammaRamp::for_output(output, &ColorSettings::default()) {
+            let _ = output.set_gamma(&ramp);
+        }
+    }
+}
+
+/// Seconds since local midnight.
+fn local_seconds_of_day() -> u32 {
+    unsafe {
+        let now = libc::time(::std::ptr::null_mut());
+        let mut tm: libc::tm = ::std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return (now as u64 % SECONDS_PER_DAY as u64) as u32
+        }
+        (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as u32 % SECONDS_PER_DAY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_ramp() {
+        let ramp = GammaRamp::identity(256);
+        assert!(ramp.is_valid());
+        assert_eq!(ramp.size(), 256);
+        assert_eq!(ramp.red[0], 0);
+        assert_eq!(ramp.red[255], u16::MAX);
+        assert_eq!(ramp.green[128], (128.0 / 255.0 * 65535.0f64).round() as u16);
+        assert_eq!(ramp.red, ramp.blue);
+        assert!(GammaRamp::identity(0).red.is_empty());
+    }
+
+    #[test]
+    fn adjustments() {
+        let dim = GammaRamp::new(3, &ColorSettings { brightness: 0.5, .. ColorSettings::default() });
+        assert_eq!(dim.red, vec![0, 16384, 32768]);
+        let inverted = GammaRamp::new(2, &ColorSettings { inverted: true, .. ColorSettings::default() });
+        assert_eq!(inverted.green, vec![u16::MAX, 0]);
+        let flat = GammaRamp::new(3, &ColorSettings { contrast: 0.0, .. ColorSettings::default() });
+        assert_eq!(flat.blue, vec![32768, 32768, 32768]);
+        let gamma = ColorSettings { gamma: [2.0, 1.0, 1.0], .. ColorSettings::default() };
+        assert!((gamma.map(0, 0.25) - 0.5).abs() < 1e-9);
+        assert!((gamma.map(1, 0.25) - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn temperatures() {
+        assert_eq!(temperature_to_rgb(NEUTRAL_TEMPERATURE), [1.0, 1.0, 1.0]);
+        let warm = temperature_to_rgb(3000);
+        assert_eq!(warm[0], 1.0);
+        assert!(warm[1] < 1.0 && warm[2] < warm[1]);
+        let cold = temperature_to_rgb(10000);
+        assert!(cold[0] < 1.0);
+        assert_eq!(cold[2], 1.0);
+        assert_eq!(temperature_to_rgb(0), temperature_to_rgb(MIN_TEMPERATURE));
+    }
+
+    #[test]
+    fn night_light_schedule() {
+        let light = NightLight {
+            day_temperature: 6000,
+            night_temperature: 4000,
+            sunrise: Transition::at(6, 0, 3600),
+            sunset: Transition::at(20, 0, 3600),
+            .. NightLight::default()
+        };
+        let hour = |h: u32| h * 3600;
+        assert_eq!(light.temperature_at(hour(3)), 4000);
+        assert_eq!(light.temperature_at(hour(6)), 4000);
+        assert_eq!(light.temperature_at(hour(6) + 1800), 5000);
+        assert_eq!(light.temperature_at(hour(12)), 6000);
+        assert_eq!(light.temperature_at(hour(20) + 900), 5500);
+        assert_eq!(light.temperature_at(hour(23)), 4000);
+    }
+
+    #[test]
+    fn night_light_across_midnight() {
+        let light = NightLight {
+            day_temperature: 6000,
+            night_temperature: 4000,
+            sunrise: Transition::at(8, 0, 0),
+            sunset: Transition::at(23, 30, 3600),
+            .. NightLight::default()
+        };
+        assert_eq!(light.temperature_at(0), 5000);
+        assert_eq!(light.temperature_at(2 * 3600), 4000);
+        assert_eq!(light.temperature_at(8 * 3600), 6000);
+    }
+}
@@ -22,7 +22,9 @@ use super::wayland::WlcResource;
 
 use super::pointer_to_string;
 use super::types::{Geometry, ResizeEdge, Point, Size, ViewType, ViewState};
-use super::render::{wlc_output_get_renderer, wlc_output_schedule_render, wlc_renderer};
+use super::render::{wlc_output_get_renderer, wlc_output_schedule_render, wlc_renderer,
+                    wlc_output_get_gamma_size, wlc_output_set_gamma};
+use super::gamma::GammaRamp;
 
 #[repr(C)]
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -49,7 +51,7 @@ impl fmt::Display for WlcView {
                 name = format!("WlcView({handle})", handle=self.0);
             }
         }
-        write!(f, "WlcOutput {{ name: {name} }}", name=name)
+        write!(f, "WlcView {{ name: {name} }}", name=name)
     }
 }
 
@@ -448,6 +450,32 @@ impl WlcOutput {
             wlc_output_get_renderer(self.0)
         }
     }
+
+    /// Gets the number of entries in each of this output's gamma ramps.
+    ///
+    /// Returns 0 if the output does not support gamma ramps.
+    pub fn get_gamma_size(self) -> u16 {
+        unsafe { wlc_output_get_gamma_size(self.0) }
+    }
+
+    /// Sets the gamma ramps of this output.
+    ///
+    /// Returns an error if the ramp's size does not match `get_gamma_size`.
+    /// See the `gamma` module for building ramps.
+    pub fn set_gamma(self, ramp: &GammaRamp) -> Result<(), &'static str> {
+        let size = self.get_gamma_size();
+        if !ramp.is_valid() || ramp.size() != size as usize {
+            return Err("Gamma ramp size does not match output")
+        }
+        // wlc takes mutable pointers, so hand it copies
+        let (mut red, mut green, mut blue) =
+            (ramp.red.clone(), ramp.green.clone(), ramp.blue.clone());
+        unsafe {
+            wlc_output_set_gamma(self.0, size, red.as_mut_ptr(),
+                                 green.as_mut_ptr(), blue.as_mut_ptr());
+        }
+        Ok(())
+    }
 }
 
 impl WlcView {
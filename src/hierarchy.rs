@@ -0,0 +1,317 @@
+//! The tree of views and their transient children.
+//!
+//! `WlcView::get_parent` only links a view to its parent. Dialogs and other
+//! transient windows are easier to manage as a tree: a `ViewTree` gathers
+//! the parents of a set of views and answers which views are children or
+//! descendants of which.
+//!
+//! The functions in this module keep transients with their parents:
+//! `set_geometry` and `set_output` move a view's descendants along with it,
+//! `stack_modals` keeps modal children (`VIEW_BIT_MODAL`) directly above
+//! their parents, and `center_transient` places a new child over the middle
+//! of its parent.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::WlcView;
+//! use rustwlc::hierarchy;
+//!
+//! extern fn view_created(view: WlcView) -> bool {
+//!     hierarchy::center_transient(view);
+//!     view.bring_to_front();
+//!     hierarchy::stack_modals(view.get_output()).ok();
+//!     view.focus();
+//!     true
+//! }
+//!
+//! // Moving a window moves its dialogs with it
+//! fn move_to(view: WlcView, x: i32, y: i32) {
+//!     if let Some(mut geometry) = view.get_geometry() {
+//!         geometry.origin.x = x;
+//!         geometry.origin.y = y;
+//!         hierarchy::set_geometry(view, geometry);
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use super::{WlcOutput, WlcView};
+use super::types::{Geometry, Point, Size, EDGE_NONE, VIEW_BIT_MODAL};
+
+/// Parents and children of a set of views.
+#[derive(Debug, Clone, Default)]
+pub struct ViewTree {
+    views: Vec<WlcView>,
+    parents: HashMap<WlcView, WlcView>,
+    children: HashMap<WlcView, Vec<WlcView>>
+}
+
+impl ViewTree {
+    /// Builds the tree of some views from their parents.
+    pub fn new(views: &[WlcView]) -> ViewTree {
+        let parents: Vec<_> = views.iter().map(|&view| (view, view.get_parent())).collect();
+        ViewTree::from_parents(&parents)
+    }
+
+    /// Builds the tree of the views of an output, in stacking order.
+    pub fn of_output(output: WlcOutput) -> ViewTree {
+        ViewTree::new(&output.get_views())
+    }
+
+    /// Builds a tree from pairs of views and their parents. A parent of
+    /// `WlcView::root()` means the view has no parent.
+    pub fn from_parents(parents: &[(WlcView, WlcView)]) -> ViewTree {
+        let mut tree = ViewTree::default();
+        for &(view, parent) in parents {
+            tree.views.push(view);
+            if !parent.is_root() && parent != view {
+                tree.parents.insert(view, parent);
+                tree.children.entry(parent).or_default().push(view);
+            }
+        }
+        tree
+    }
+
+    /// The views of the tree, in the order they were given.
+    pub fn views(&self) -> &[WlcView] {
+        &self.views
+    }
+
+    /// The parent of a view, if it has one.
+    pub fn parent(&self, view: WlcView) -> Option<WlcView> {
+        self.parents.get(&view).cloned()
+    }
+
+    /// The children of a view, in the order the views were given.
+    pub fn children(&self, view: WlcView) -> &[WlcView] {
+        self.children.get(&view).map(|children| &children[..]).unwrap_or(&[])
+    }
+
+    /// The parent of a view, its parent, and so on.
+    pub fn ancestors(&self, view: WlcView) -> Vec<WlcView> {
+        let mut ancestors = Vec::new();
+        let mut current = view;
+        while let Some(parent) = self.parent(current) {
+            // Clients can make loops of parents
+            if parent == view || ancestors.contains(&parent) {
+                break
+            }
+            ancestors.push(parent);
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// The topmost ancestor of a view, or the view if it has no parent.
+    pub fn root_of(&self, view: WlcView) -> WlcView {
+        self.ancestors(view).last().cloned().unwrap_or(view)
+    }
+
+    /// Views of the tree without a parent.
+    pub fn roots(&self) -> Vec<WlcView> {
+        self.views.iter().cloned().filter(|view| self.parent(*view).is_none()).collect()
+    }
+
+    /// The children of a view, their children, and so on, depth first.
+    pub fn descendants(&self, view: WlcView) -> Vec<WlcView> {
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::new();
+        seen.insert(view);
+        self.collect_descendants(view, &mut seen, &mut descendants);
+        descendants
+    }
+
+    fn collect_descendants(&self, view: WlcView, seen: &mut HashSet<WlcView>,
+                           descendants: &mut Vec<WlcView>) {
+        for &child in self.children(view) {
+            if seen.insert(child) {
+                descendants.push(child);
+                self.collect_descendants(child, seen, descendants);
+            }
+        }
+    }
+
+    /// Whether `view` is a descendant of `ancestor`.
+    pub fn is_descendant(&self, view: WlcView, ancestor: WlcView) -> bool {
+        self.ancestors(view).contains(&ancestor)
+    }
+}
+
+/// Sets the geometry of a view and moves its descendants by as much as its
+/// origin moved.
+pub fn set_geometry(view: WlcView, geometry: Geometry) {
+    let old = view.get_geometry();
+    view.set_geometry(EDGE_NONE, geometry);
+    let old = match old {
+        Some(old) => old,
+        None => return
+    };
+    let tree = ViewTree::of_output(view.get_output());
+    for child in tree.descendants(view) {
+        if let Some(child_geometry) = child.get_geometry() {
+            child.set_geometry(EDGE_NONE, follow(old, geometry, child_geometry));
+        }
+    }
+}
+
+/// Moves a view and its descendants to an output, giving the descendants
+/// the view's mask.
+pub fn set_output(view: WlcView, output: WlcOutput) {
+    let tree = ViewTree::of_output(view.get_output());
+    view.set_output(output);
+    let mask = view.get_mask();
+    for child in tree.descendants(view) {
+        child.set_output(output);
+        child.set_mask(mask);
+    }
+}
+
+/// Centers a view with a parent over the parent, keeping the view's size
+/// and keeping it on the output where possible.
+///
+/// Returns `false` if the view has no parent.
+pub fn center_transient(view: WlcView) -> bool {
+    let parent = view.get_parent();
+    if parent.is_root() {
+        return false
+    }
+    let (parent_geometry, geometry) = match (parent.get_geometry(), view.get_geometry()) {
+        (Some(parent_geometry), Some(geometry)) => (parent_geometry, geometry),
+        _ => return false
+    };
+    let bounds = view.get_output().get_virtual_resolution()
+        .map(|size| Geometry::new(Point::origin(), size));
+    view.set_geometry(EDGE_NONE, centered(parent_geometry, geometry.size, bounds));
+    true
+}
+
+/// Restacks an output so every modal view is directly above its parent and
+/// the parent's other descendants, then writes the order back if it
+/// changed.
+pub fn stack_modals(output: WlcOutput) -> Result<(), &'static str> {
+    let tree = ViewTree::of_output(output);
+    let order = modals_above(&tree, |view| view.get_type().contains(VIEW_BIT_MODAL));
+    if order.as_slice() != tree.views() {
+        output.set_views(&order)?;
+    }
+    Ok(())
+}
+
+/// The views of a tree, in the given stacking order, with every modal view
+/// moved directly above its parent and the parent's other descendants.
+///
+/// Modal views whose parent is not in the tree keep their place.
+pub fn modals_above<F>(tree: &ViewTree, is_modal: F) -> Vec<WlcView>
+    where F: Fn(WlcView) -> bool {
+    let (mut modals, mut order): (Vec<_>, Vec<_>) = tree.views().iter().cloned()
+        .partition(|&view| {
+            is_modal(view) && matches!(tree.parent(view), Some(parent) if tree.views.contains(&parent))
+        });
+    // Parents are placed before their modal children
+    modals.sort_by_key(|&view| tree.ancestors(view).len());
+    for modal in modals {
+        let parent = tree.parent(modal).expect("Modal views have parents");
+        let above = order.iter()
+            .rposition(|&view| view == parent || tree.is_descendant(view, parent))
+            .map_or(order.len(), |index| index + 1);
+        order.insert(above, modal);
+    }
+    order
+}
+
+/// Moves `child` by as much as its parent's origin moved from `old` to
+/// `new`.
+pub fn follow(old: Geometry, new: Geometry, child: Geometry) -> Geometry {
+    Geometry::new(Point::new(child.origin.x + new.origin.x - old.origin.x,
+                             child.origin.y + new.origin.y - old.origin.y),
+                  child.size)
+}
+
+/// A geometry of `size` centered over `parent`, moved inside `bounds` if it
+/// would stick out of them and fits.
+pub fn centered(parent: Geometry, size: Size, bounds: Option<Geometry>) -> Geometry {
+    let mut origin = Point::new(
+        parent.origin.x + (parent.size.w as i32 - size.w as i32) / 2,
+        parent.origin.y + (parent.size.h as i32 - size.h as i32) / 2);
+    if let Some(bounds) = bounds {
+        let max_x = bounds.origin.x + bounds.size.w as i32 - size.w as i32;
+        let max_y = bounds.origin.y + bounds.size.h as i32 - size.h as i32;
+        origin.x = origin.x.min(max_x).max(bounds.origin.x);
+        origin.y = origin.y.min(max_y).max(bounds.origin.y);
+    }
+    Geometry::new(origin, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 has children 2 and 3, 3 has child 4, and 5 has no parent
+    fn tree() -> ViewTree {
+        ViewTree::from_parents(&[(WlcView(1), WlcView::root()), (WlcView(2), WlcView(1)),
+                                 (WlcView(5), WlcView::root()), (WlcView(3), WlcView(1)),
+                                 (WlcView(4), WlcView(3))])
+    }
+
+    #[test]
+    fn tree_queries() {
+        let tree = tree();
+        assert_eq!(tree.parent(WlcView(4)), Some(WlcView(3)));
+        assert_eq!(tree.parent(WlcView(1)), None);
+        assert_eq!(tree.children(WlcView(1)), &[WlcView(2), WlcView(3)]);
+        assert!(tree.children(WlcView(5)).is_empty());
+        assert_eq!(tree.ancestors(WlcView(4)), vec![WlcView(3), WlcView(1)]);
+        assert_eq!(tree.root_of(WlcView(4)), WlcView(1));
+        assert_eq!(tree.root_of(WlcView(5)), WlcView(5));
+        assert_eq!(tree.roots(), vec![WlcView(1), WlcView(5)]);
+        assert_eq!(tree.descendants(WlcView(1)), vec![WlcView(2), WlcView(3), WlcView(4)]);
+        assert!(tree.is_descendant(WlcView(4), WlcView(1)));
+        assert!(!tree.is_descendant(WlcView(1), WlcView(4)));
+
+        // Loops of parents end
+        let looped = ViewTree::from_parents(&[(WlcView(1), WlcView(2)), (WlcView(2), WlcView(1))]);
+        assert_eq!(looped.ancestors(WlcView(1)), vec![WlcView(2)]);
+        assert_eq!(looped.descendants(WlcView(1)), vec![WlcView(2)]);
+    }
+
+    #[test]
+    fn modal_stacking() {
+        // Stacked bottom to top: 2 (modal of 1), 1, 5, 4 (modal of 3), 3
+        let tree = ViewTree::from_parents(&[(WlcView(2), WlcView(1)), (WlcView(1), WlcView::root()),
+                                            (WlcView(5), WlcView::root()),
+                                            (WlcView(4), WlcView(3)), (WlcView(3), WlcView(1))]);
+        let modal = |view: WlcView| view == WlcView(2) || view == WlcView(4);
+        assert_eq!(modals_above(&tree, modal),
+                   vec![WlcView(1), WlcView(5), WlcView(3), WlcView(4), WlcView(2)]);
+        // Already stacked
+        let stacked = ViewTree::from_parents(&[(WlcView(1), WlcView::root()),
+                                               (WlcView(2), WlcView(1))]);
+        assert_eq!(modals_above(&stacked, modal), vec![WlcView(1), WlcView(2)]);
+        // The parent is elsewhere
+        let orphan = ViewTree::from_parents(&[(WlcView(2), WlcView(9)),
+                                              (WlcView(1), WlcView::root())]);
+        assert_eq!(modals_above(&orphan, modal), vec![WlcView(2), WlcView(1)]);
+    }
+
+    #[test]
+    fn placement() {
+        let old_parent = Geometry::new(Point::new(0, 0), Size::new(100, 100));
+        let new_parent = Geometry::new(Point::new(50, -10), Size::new(200, 200));
+        let child = Geometry::new(Point::new(10, 10), Size::new(20, 20));
+        assert_eq!(follow(old_parent, new_parent, child),
+                   Geometry::new(Point::new(60, 0), Size::new(20, 20)));
+        let parent = Geometry::new(Point::new(100, 100), Size::new(400, 300));
+        assert_eq!(centered(parent, Size::new(200, 100), None),
+                   Geometry::new(Point::new(200, 200), Size::new(200, 100)));
+        assert_eq!(centered(parent, Size::new(600, 100), None),
+                   Geometry::new(Point::new(0, 200), Size::new(600, 100)));
+        let bounds = Some(Geometry::new(Point::new(0, 0), Size::new(550, 1000)));
+        assert_eq!(centered(parent, Size::new(600, 100), bounds),
+                   Geometry::new(Point::new(0, 200), Size::new(600, 100)));
+        let right = Geometry::new(Point::new(400, 0), Size::new(150, 100));
+        assert_eq!(centered(right, Size::new(300, 100), bounds),
+                   Geometry::new(Point::new(250, 0), Size::new(300, 100)));
+    }
+}
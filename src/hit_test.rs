@@ -0,0 +1,248 @@
+//! Finding what is under the pointer.
+//!
+//! `HitTest` walks the views of an output from the top of the stacking
+//! order down, and finds the first one under a point: in a subsurface, in
+//! the view's own surface, or in its decorations. The `Hit` says which part
+//! was hit, where the point is inside it, and which resize edges the point
+//! is near, so pointer handling can pick between moving, resizing and
+//! passing the event on.
+//!
+//! Only views visible on the output are hit, that is views whose mask
+//! shares a bit with the output's mask.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::{WlcOutput, Point, EDGE_NONE};
+//! use rustwlc::hit_test::HitTest;
+//!
+//! fn cursor_for(output: WlcOutput, point: Point) -> &'static str {
+//!     match HitTest::default().at(output, point) {
+//!         Some(ref hit) if hit.edge != EDGE_NONE => "resize",
+//!         Some(_) => "default",
+//!         None => "background"
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use libc::uintptr_t;
+
+use super::{WlcOutput, WlcView};
+use super::decorations::{Decorations, Region};
+#[cfg(all(feature = "wlc-wayland", not(feature = "dummy")))]
+use super::surface_render::SurfaceTree;
+use super::types::{Geometry, Point, ResizeEdge, EDGE_NONE, RESIZE_BOTTOM, RESIZE_LEFT,
+                   RESIZE_RIGHT, RESIZE_TOP};
+
+/// The part of a view which was hit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Part {
+    /// The view's own surface
+    Surface,
+    /// A subsurface of the view, by wlc handle
+    Subsurface(uintptr_t),
+    /// The view's decorations
+    Decoration(Region)
+}
+
+/// A view under a point.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Hit {
+    /// The view
+    pub view: WlcView,
+    /// Which part of it
+    pub part: Part,
+    /// The point relative to the top left corner of the part hit. For
+    /// decorations it is relative to the view's visible geometry, so it
+    /// can be negative.
+    pub local: Point,
+    /// Edges of the view the point is close to, `EDGE_NONE` if none
+    pub edge: ResizeEdge
+}
+
+/// Settings for hit-testing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HitTest {
+    /// How far inside a view's border a point is still near the edge, in
+    /// pixels. 0 only counts edges from decorations.
+    pub edge_size: u32,
+    /// Whether to test subsurfaces, which can stick out of their view.
+    /// Subsurfaces are only found with the `wlc-wayland` feature.
+    pub subsurfaces: bool
+}
+
+impl Default for HitTest {
+    fn default() -> HitTest {
+        HitTest { edge_size: 8, subsurfaces: true }
+    }
+}
+
+impl HitTest {
+    /// The topmost view on an output under a point in output-local
+    /// coordinates.
+    pub fn at(&self, output: WlcOutput, point: Point) -> Option<Hit> {
+        self.find(output, point, None)
+    }
+
+    /// The topmost view on an output under a point, including the
+    /// decorations of decorated views.
+    pub fn at_decorated(&self, output: WlcOutput, point: Point, decorations: &Decorations)
+                        -> Option<Hit> {
+        self.find(output, point, Some(decorations))
+    }
+
+    /// Every view on an output under a point, from the top down.
+    pub fn all_at(&self, output: WlcOutput, point: Point) -> Vec<Hit> {
+        visible_views(output).into_iter()
+            .filter_map(|view| self.view(view, point))
+            .collect()
+    }
+
+    fn find(&self, output: WlcOutput, point: Point, decorations: Option<&Decorations>)
+            -> Option<Hit> {
+        for view in visible_views(output) {
+            if let Some(hit) = self.view(view, point) {
+                return Some(hit)
+            }
+            let decorations = match decorations {
+                Some(decorations) if decorations.is_decorated(view) => decorations,
+                _ => continue
+            };
+            let geometry = view.get_visible_geometry();
+            if let Some(region) = decorations.hit_test(geometry, point) {
+                let edge = match region {
+                    Region::Border(edge) => edge,
+                    _ => EDGE_NONE
+                };
+                return Some(Hit {
+                    view,
+                    part: Part::Decoration(region),
+                    local: relative(point, geometry),
+                    edge
+                })
+            }
+        }
+        None
+    }
+
+    /// Tests a single view and its subsurfaces, without decorations.
+    pub fn view(&self, view: WlcView, point: Point) -> Option<Hit> {
+        let geometry = view.get_visible_geometry();
+        if self.subsurfaces {
+            let layout = surface_layout(view, geometry);
+            // The root surface is first, subsurfaces above it
+            if let Some(&(surface, at)) = topmost(layout.get(1..).unwrap_or(&[]), point) {
+                return Some(Hit {
+                    view,
+                    part: Part::Subsurface(surface),
+                    local: relative(point, at),
+                    edge: edge_zone(geometry, point, self.edge_size)
+                })
+            }
+        }
+        if !geometry.contains_point_exclusive(point) {
+            return None
+        }
+        Some(Hit {
+            view,
+            part: Part::Surface,
+            local: relative(point, geometry),
+            edge: edge_zone(geometry, point, self.edge_size)
+        })
+    }
+}
+
+/// Views visible on an output, from the top down.
+fn visible_views(output: WlcOutput) -> Vec<WlcView> {
+    let mask = output.get_mask();
+    output.get_views().into_iter().rev()
+        .filter(|view| view.get_mask() & mask != 0)
+        .collect()
+}
+
+/// Where a view's surface and its subsurfaces are drawn, the view's own
+/// surface first.
+#[cfg(all(feature = "wlc-wayland", not(feature = "dummy")))]
+fn surface_layout(view: WlcView, geometry: Geometry) -> Vec<(uintptr_t, Geometry)> {
+    SurfaceTree::of_view(view).map(|tree| tree.layout(geometry)).unwrap_or_default()
+}
+
+/// Surfaces can only be read with the `wlc-wayland` feature, and dummy
+/// views have none, so nothing is laid out.
+#[cfg(any(not(feature = "wlc-wayland"), feature = "dummy"))]
+fn surface_layout(_view: WlcView, _geometry: Geometry) -> Vec<(uintptr_t, Geometry)> {
+    Vec::new()
+}
+
+/// The last surface of a layout containing a point.
+pub fn topmost(layout: &[(uintptr_t, Geometry)], point: Point) -> Option<&(uintptr_t, Geometry)> {
+    layout.iter().rev().find(|&&(_, geometry)| geometry.contains_point_exclusive(point))
+}
+
+/// Edges of a geometry within `size` pixels of a point inside it.
+///
+/// Returns `EDGE_NONE` for points outside the geometry. When the geometry
+/// is too small for the zones, the nearer edge wins.
+pub fn edge_zone(geometry: Geometry, point: Point, size: u32) -> ResizeEdge {
+    if size == 0 || !geometry.contains_point_exclusive(point) {
+        return EDGE_NONE
+    }
+    let size = size as i64;
+    let left = point.x as i64 - geometry.origin.x as i64;
+    let top = point.y as i64 - geometry.origin.y as i64;
+    let right = geometry.size.w as i64 - 1 - left;
+    let bottom = geometry.size.h as i64 - 1 - top;
+    let mut edges = EDGE_NONE;
+    if left < size && left <= right {
+        edges |= RESIZE_LEFT;
+    } else if right < size {
+        edges |= RESIZE_RIGHT;
+    }
+    if top < size && top <= bottom {
+        edges |= RESIZE_TOP;
+    } else if bottom < size {
+        edges |= RESIZE_BOTTOM;
+    }
+    edges
+}
+
+fn relative(point: Point, geometry: Geometry) -> Point {
+    Point::new(point.x - geometry.origin.x, point.y - geometry.origin.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Size, RESIZE_BOTTOMRIGHT, RESIZE_TOPLEFT};
+
+    #[test]
+    fn edge_zones() {
+        let view = Geometry::new(Point::new(100, 100), Size::new(200, 100));
+        assert_eq!(edge_zone(view, Point::new(150, 150), 8), EDGE_NONE);
+        assert_eq!(edge_zone(view, Point::new(100, 100), 8), RESIZE_TOPLEFT);
+        assert_eq!(edge_zone(view, Point::new(299, 199), 8), RESIZE_BOTTOMRIGHT);
+        assert_eq!(edge_zone(view, Point::new(107, 150), 8), RESIZE_LEFT);
+        assert_eq!(edge_zone(view, Point::new(108, 150), 8), EDGE_NONE);
+        assert_eq!(edge_zone(view, Point::new(150, 192), 8), RESIZE_BOTTOM);
+        assert_eq!(edge_zone(view, Point::new(300, 150), 8), EDGE_NONE);
+        assert_eq!(edge_zone(view, Point::new(99, 150), 8), EDGE_NONE);
+        assert_eq!(edge_zone(view, Point::new(100, 100), 0), EDGE_NONE);
+        // Zones overlap on small views
+        let small = Geometry::new(Point::new(0, 0), Size::new(10, 10));
+        assert_eq!(edge_zone(small, Point::new(4, 5), 8), RESIZE_LEFT | RESIZE_BOTTOM);
+    }
+
+    #[test]
+    fn topmost_surface() {
+        let layout = [(1, Geometry::new(Point::new(0, 0), Size::new(100, 100))),
+                      (2, Geometry::new(Point::new(50, 50), Size::new(100, 100))),
+                      (3, Geometry::new(Point::new(-20, 0), Size::new(30, 30)))];
+        assert_eq!(topmost(&layout, Point::new(60, 60)).map(|hit| hit.0), Some(2));
+        assert_eq!(topmost(&layout, Point::new(5, 10)).map(|hit| hit.0), Some(3));
+        assert_eq!(topmost(&layout, Point::new(10, 10)).map(|hit| hit.0), Some(1));
+        assert_eq!(topmost(&layout, Point::new(-10, 10)).map(|hit| hit.0), Some(3));
+        assert_eq!(topmost(&layout, Point::new(20, 80)).map(|hit| hit.0), Some(1));
+        assert_eq!(topmost(&layout, Point::new(100, 10)), None);
+        assert_eq!(topmost(&layout, Point::new(-21, 10)), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Pointer enter, leave and output-crossing events.
+//!
+//! The pointer motion callbacks only give the pointer's position. A
+//! `PointerTracker` remembers which output and view were under the pointer
+//! last, and turns each motion into the transitions it caused: leaving a
+//! view, crossing from one output to another, entering a view, and the
+//! motion itself in view-local coordinates. This is what tooltips,
+//! focus-follows-mouse and hover highlighting need.
+//!
+//! The view under the pointer is found with `hit_test`, so it is the
+//! topmost visible view, not necessarily the one wlc gives to the motion
+//! callback.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::WlcView;
+//! use rustwlc::hover::{PointerEvent, PointerTracker};
+//!
+//! lazy_static! {
+//!     static ref POINTER: Mutex<PointerTracker> = Mutex::new(PointerTracker::new());
+//! }
+//!
+//! extern fn pointer_motion(_view: WlcView, _time: u32, x: f64, y: f64) -> bool {
+//!     for event in POINTER.lock().unwrap().pointer_motion(x, y) {
+//!         if let PointerEvent::Enter { view, .. } = event {
+//!             // Focus follows the mouse
+//!             view.focus();
+//!         }
+//!     }
+//!     false
+//! }
+//!
+//! extern fn view_destroyed(view: WlcView) {
+//!     POINTER.lock().unwrap().view_destroyed(view);
+//! }
+//! # fn main() {}
+//! ```
+
+use super::{WlcOutput, WlcView};
+use super::hit_test::HitTest;
+use super::types::Point;
+
+/// Something the pointer did.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PointerEvent {
+    /// The pointer moved onto an output
+    OutputEnter {
+        /// The output
+        output: WlcOutput,
+        /// Output-local x coordinate
+        x: f64,
+        /// Output-local y coordinate
+        y: f64
+    },
+    /// The pointer left an output
+    OutputLeave {
+        /// The output
+        output: WlcOutput
+    },
+    /// The pointer moved onto a view
+    Enter {
+        /// The view
+        view: WlcView,
+        /// View-local x coordinate
+        x: f64,
+        /// View-local y coordinate
+        y: f64
+    },
+    /// The pointer left a view
+    Leave {
+        /// The view
+        view: WlcView
+    },
+    /// The pointer moved over a view
+    Motion {
+        /// The view
+        view: WlcView,
+        /// View-local x coordinate
+        x: f64,
+        /// View-local y coordinate
+        y: f64
+    }
+}
+
+/// Tracks the output and view under the pointer.
+#[derive(Debug, Clone, Default)]
+pub struct PointerTracker {
+    /// How the view under the pointer is found
+    pub hit_test: HitTest,
+    output: Option<WlcOutput>,
+    view: Option<WlcView>,
+    position: (f64, f64)
+}
+
+impl PointerTracker {
+    /// Makes a tracker with nothing under the pointer yet.
+    pub fn new() -> PointerTracker {
+        PointerTracker::default()
+    }
+
+    /// The output the pointer was last on.
+    pub fn output(&self) -> Option<WlcOutput> {
+        self.output
+    }
+
+    /// The view the pointer was last over.
+    pub fn view(&self) -> Option<WlcView> {
+        self.view
+    }
+
+    /// The last output-local position of the pointer.
+    pub fn position(&self) -> (f64, f64) {
+        self.position
+    }
+
+    /// Handles motion on the focused output, in output-local coordinates.
+    /// Call from the `pointer_motion_v2` callback.
+    pub fn pointer_motion(&mut self, x: f64, y: f64) -> Vec<PointerEvent> {
+        self.motion_on(WlcOutput::focused(), x, y)
+    }
+
+    /// Handles motion to a position on an output.
+    pub fn motion_on(&mut self, output: WlcOutput, x: f64, y: f64) -> Vec<PointerEvent> {
+        let point = Point::new(x.floor() as i32, y.floor() as i32);
+        let hit = self.hit_test.at(output, point).map(|hit| {
+            let geometry = hit.view.get_visible_geometry();
+            (hit.view, (geometry.origin.x as f64, geometry.origin.y as f64))
+        });
+        self.update(output, hit, x, y)
+    }
+
+    /// Finds the view under the pointer again without it moving, such as
+    /// after views were moved or restacked.
+    pub fn refresh(&mut self) -> Vec<PointerEvent> {
+        match self.output {
+            Some(output) => {
+                let (x, y) = self.position;
+                let mut events = self.motion_on(output, x, y);
+                events.retain(|event| !matches!(*event, PointerEvent::Motion { .. }));
+                events
+            },
+            None => Vec::new()
+        }
+    }
+
+    /// Forgets a destroyed view. Call from the `view_destroyed` callback.
+    ///
+    /// No `Leave` is sent for it, the view is gone.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        if self.view == Some(view) {
+            self.view = None;
+        }
+    }
+
+    /// Forgets a destroyed output. Call from the `output_destroyed`
+    /// callback.
+    pub fn output_destroyed(&mut self, output: WlcOutput) {
+        if self.output == Some(output) {
+            self.output = None;
+            self.view = None;
+        }
+    }
+
+    /// Works out the events for the pointer being at `(x, y)` on an output,
+    /// over `hit`: a view and the origin of its visible geometry.
+    fn update(&mut self, output: WlcOutput, hit: Option<(WlcView, (f64, f64))>, x: f64, y: f64)
+              -> Vec<PointerEvent> {
+        let mut events = Vec::new();
+        let view = hit.map(|(view, _)| view);
+        if self.view != view || self.output != Some(output) {
+            if let Some(old) = self.view.take() {
+                events.push(PointerEvent::Leave { view: old });
+            }
+        }
+        if self.output != Some(output) {
+            if let Some(old) = self.output {
+                events.push(PointerEvent::OutputLeave { output: old });
+            }
+            events.push(PointerEvent::OutputEnter { output, x, y });
+            self.output = Some(output);
+        }
+        if let Some((view, (origin_x, origin_y))) = hit {
+            let (local_x, local_y) = (x - origin_x, y - origin_y);
+            if self.view != Some(view) {
+                events.push(PointerEvent::Enter { view, x: local_x, y: local_y });
+                self.view = Some(view);
+            }
+            events.push(PointerEvent::Motion { view, x: local_x, y: local_y });
+        }
+        self.position = (x, y);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let mut tracker = PointerTracker::new();
+        let (first, second) = (WlcOutput(1), WlcOutput(2));
+        let (view, other) = (WlcView(10), WlcView(11));
+
+        assert_eq!(tracker.update(first, None, 5.0, 5.0),
+                   vec![PointerEvent::OutputEnter { output: first, x: 5.0, y: 5.0 }]);
+        assert!(tracker.update(first, None, 6.0, 5.0).is_empty());
+
+        assert_eq!(tracker.update(first, Some((view, (100.0, 50.0))), 110.5, 60.0), vec![
+            PointerEvent::Enter { view, x: 10.5, y: 10.0 },
+            PointerEvent::Motion { view, x: 10.5, y: 10.0 }
+        ]);
+        assert_eq!(tracker.update(first, Some((view, (100.0, 50.0))), 111.5, 60.0),
+                   vec![PointerEvent::Motion { view, x: 11.5, y: 10.0 }]);
+        assert_eq!(tracker.view(), Some(view));
+
+        assert_eq!(tracker.update(first, Some((other, (0.0, 0.0))), 20.0, 20.0), vec![
+            PointerEvent::Leave { view },
+            PointerEvent::Enter { view: other, x: 20.0, y: 20.0 },
+            PointerEvent::Motion { view: other, x: 20.0, y: 20.0 }
+        ]);
+
+        // Crossing to another output leaves the view first
+        assert_eq!(tracker.update(second, None, 0.0, 20.0), vec![
+            PointerEvent::Leave { view: other },
+            PointerEvent::OutputLeave { output: first },
+            PointerEvent::OutputEnter { output: second, x: 0.0, y: 20.0 }
+        ]);
+        assert_eq!(tracker.view(), None);
+        assert_eq!(tracker.output(), Some(second));
+        assert_eq!(tracker.position(), (0.0, 20.0));
+
+        // Destroyed views get no leave
+        tracker.update(second, Some((view, (0.0, 0.0))), 1.0, 1.0);
+        tracker.view_destroyed(view);
+        assert!(tracker.update(second, None, 2.0, 2.0).is_empty());
+        tracker.output_destroyed(second);
+        assert_eq!(tracker.output(), None);
+    }
+}
@@ -0,0 +1,711 @@
+//! Owned 32-bit pixel buffers and image file encoders.
+//!
+//! An `Image` is what the `screenshot` module captures from an output. It
+//! can be converted between RGBA and BGRA channel orders, flipped, and
+//! encoded as PPM, PNG or QOI without any extra dependencies. PPM files can
+//! also be decoded, such as for wallpapers, and PNG files with the
+//! `image-png` feature, which pulls in the `png` crate.
+//!
+//! # Example
+//! ```rust
+//! use rustwlc::Size;
+//! use rustwlc::image::{Image, PixelFormat};
+//!
+//! let mut image = Image::new(Size::new(2, 2), PixelFormat::Rgba8888);
+//! image.set_pixel(0, 0, [255, 0, 0, 255]);
+//! let png = image.encode_png();
+//! assert_eq!(&png[1..4], b"PNG");
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use super::types::Size;
+
+/// Channel order of a 32 bits per pixel image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// Red, green, blue, alpha. This is `wlc_pixel_format::WLC_RGBA8888`.
+    Rgba8888,
+    /// Blue, green, red, alpha. Common for cairo and other 2D libraries.
+    Bgra8888
+}
+
+/// File formats an `Image` can be encoded as.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Binary portable pixmap. Alpha is dropped.
+    Ppm,
+    /// Uncompressed PNG with alpha
+    Png,
+    /// "Quite OK Image" format with alpha
+    Qoi
+}
+
+impl Encoding {
+    /// Guesses the encoding from a file extension.
+    pub fn from_extension(extension: &str) -> Option<Encoding> {
+        match extension.to_lowercase().as_str() {
+            "ppm" => Some(Encoding::Ppm),
+            "png" => Some(Encoding::Png),
+            "qoi" => Some(Encoding::Qoi),
+            _ => None
+        }
+    }
+}
+
+/// An owned image with 4 bytes per pixel.
+///
+/// Rows are stored top to bottom, each `stride` bytes long. The stride may
+/// be larger than `size.w * 4` if rows are padded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+    /// Width and height in pixels
+    pub size: Size,
+    /// Bytes per row
+    pub stride: u32,
+    /// Order of the channels in each pixel
+    pub format: PixelFormat,
+    /// Pixel data, `stride * size.h` bytes
+    pub data: Vec<u8>
+}
+
+impl Image {
+    /// Creates a transparent black image.
+    pub fn new(size: Size, format: PixelFormat) -> Image {
+        let stride = size.w * 4;
+        Image {
+            size,
+            stride,
+            format,
+            data: vec![0; stride as usize * size.h as usize]
+        }
+    }
+
+    /// Creates an image from existing pixel data.
+    ///
+    /// Returns `None` if the stride is too small for the width or the data
+    /// is too short for the stride and height.
+    pub fn from_raw(size: Size, stride: u32, format: PixelFormat, data: Vec<u8>)
+                    -> Option<Image> {
+        if (stride as u64) < size.w as u64 * 4
+            || (data.len() as u64) < stride as u64 * size.h as u64 {
+            return None
+        }
+        Some(Image { size, stride, format, data })
+    }
+
+    /// Gets the bytes of one row, without any padding.
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.stride as usize;
+        &self.data[start..start + self.size.w as usize * 4]
+    }
+
+    /// Gets a pixel in the image's channel order.
+    ///
+    /// # Panics
+    /// Panics if the coordinates are outside the image.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.size.w && y < self.size.h, "Pixel out of bounds");
+        let index = y as usize * self.stride as usize + x as usize * 4;
+        [self.data[index], self.data[index + 1], self.data[index + 2], self.data[index + 3]]
+    }
+
+    /// Sets a pixel, given in the image's channel order.
+    ///
+    /// # Panics
+    /// Panics if the coordinates are outside the image.
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        assert!(x < self.size.w && y < self.size.h, "Pixel out of bounds");
+        let index = y as usize * self.stride as usize + x as usize * 4;
+        self.data[index..index + 4].copy_from_slice(&pixel);
+    }
+
+    /// Gets a pixel as red, green, blue, alpha regardless of format.
+    pub fn rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        let pixel = self.pixel(x, y);
+        match self.format {
+            PixelFormat::Rgba8888 => pixel,
+            PixelFormat::Bgra8888 => [pixel[2], pixel[1], pixel[0], pixel[3]]
+        }
+    }
+
+    /// Reverses the order of the rows.
+    ///
+    /// OpenGL reads framebuffers bottom to top, so pixels read with
+    /// `render::read_pixels_v2` need flipping.
+    pub fn flip_rows(&mut self) {
+        let stride = self.stride as usize;
+        let height = self.size.h as usize;
+        for y in 0..height / 2 {
+            let (top, bottom) = self.data.split_at_mut((height - 1 - y) * stride);
+            top[y * stride..(y + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+    }
+
+    /// Converts the image to another channel order in place.
+    pub fn convert(&mut self, format: PixelFormat) {
+        if self.format == format {
+            return
+        }
+        // Swapping red and blue goes both ways
+        for y in 0..self.size.h {
+            let start = y as usize * self.stride as usize;
+            let row = &mut self.data[start..start + self.size.w as usize * 4];
+            for pixel in row.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        self.format = format;
+    }
+
+    /// Returns a copy of the image in another channel order with no row
+    /// padding.
+    pub fn to_format(&self, format: PixelFormat) -> Image {
+        let mut data = Vec::with_capacity(self.size.w as usize * self.size.h as usize * 4);
+        for y in 0..self.size.h {
+            data.extend_from_slice(self.row(y));
+        }
+        let mut image = Image { size: self.size, stride: self.size.w * 4, format: self.format, data };
+        image.convert(format);
+        image
+    }
+
+    /// Encodes the image in the given file format.
+    pub fn encode(&self, encoding: Encoding) -> Vec<u8> {
+        match encoding {
+            Encoding::Ppm => self.encode_ppm(),
+            Encoding::Png => self.encode_png(),
+            Encoding::Qoi => self.encode_qoi()
+        }
+    }
+
+    /// Encodes the image and writes it to a file.
+    ///
+    /// If `encoding` is `None` it is guessed from the file extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P, encoding: Option<Encoding>) -> io::Result<()> {
+        let path = path.as_ref();
+        let encoding = encoding
+            .or_else(|| path.extension()
+                     .and_then(|extension| extension.to_str())
+                     .and_then(Encoding::from_extension))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                          "Unknown image file extension"))?;
+        File::create(path)?.write_all(&self.encode(encoding))
+    }
+
+    /// Encodes the image as a binary (P6) PPM. Alpha is dropped.
+    pub fn encode_ppm(&self) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", self.size.w, self.size.h).into_bytes();
+        out.reserve(self.size.w as usize * self.size.h as usize * 3);
+        for y in 0..self.size.h {
+            for x in 0..self.size.w {
+                out.extend_from_slice(&self.rgba(x, y)[..3]);
+            }
+        }
+        out
+    }
+
+    /// Encodes the image as an 8-bit RGBA PNG.
+    ///
+    /// The image data is stored without compression, which keeps the
+    /// encoder fast and small at the cost of file size.
+    pub fn encode_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.size.w as usize * 4 + 1) * self.size.h as usize);
+        for y in 0..self.size.h {
+            // Filter type 0, no filtering
+            raw.push(0);
+            for x in 0..self.size.w {
+                raw.extend_from_slice(&self.rgba(x, y));
+            }
+        }
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&be32(self.size.w));
+        header.extend_from_slice(&be32(self.size.h));
+        // 8 bits per channel, RGBA, default compression, filter and interlace
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut out = PNG_SIGNATURE.to_vec();
+        png_chunk(&mut out, b"IHDR", &header);
+        png_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    /// Encodes the image in the QOI format.
+    ///
+    /// See <https://qoiformat.org> for the specification.
+    pub fn encode_qoi(&self) -> Vec<u8> {
+        let pixel_count = self.size.w as usize * self.size.h as usize;
+        let mut out = b"qoif".to_vec();
+        out.extend_from_slice(&be32(self.size.w));
+        out.extend_from_slice(&be32(self.size.h));
+        // 4 channels, sRGB with linear alpha
+        out.extend_from_slice(&[4, 0]);
+
+        let mut index = [[0u8; 4]; 64];
+        let mut previous = [0u8, 0, 0, 255];
+        let mut run = 0u8;
+        let mut count = 0;
+        for y in 0..self.size.h {
+            for x in 0..self.size.w {
+                let pixel = self.rgba(x, y);
+                count += 1;
+                if pixel == previous {
+                    run += 1;
+                    if run == 62 || count == pixel_count {
+                        out.push(0xc0 | (run - 1));
+                        run = 0;
+                    }
+                    continue
+                }
+                if run > 0 {
+                    out.push(0xc0 | (run - 1));
+                    run = 0;
+                }
+                let hash = qoi_hash(pixel);
+                if index[hash] == pixel {
+                    out.push(hash as u8);
+                } else {
+                    index[hash] = pixel;
+                    if pixel[3] == previous[3] {
+                        let dr = pixel[0].wrapping_sub(previous[0]) as i8;
+                        let dg = pixel[1].wrapping_sub(previous[1]) as i8;
+                        let db = pixel[2].wrapping_sub(previous[2]) as i8;
+                        let dr_dg = dr.wrapping_sub(dg);
+                        let db_dg = db.wrapping_sub(dg);
+                        if (-2..2).contains(&dr) && (-2..2).contains(&dg) && (-2..2).contains(&db) {
+                            out.push(0x40 | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2
+                                     | (db + 2) as u8);
+                        } else if (-32..32).contains(&dg) && (-8..8).contains(&dr_dg)
+                            && (-8..8).contains(&db_dg) {
+                            out.push(0x80 | (dg + 32) as u8);
+                            out.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                        } else {
+                            out.extend_from_slice(&[0xfe, pixel[0], pixel[1], pixel[2]]);
+                        }
+                    } else {
+                        out.extend_from_slice(&[0xff, pixel[0], pixel[1], pixel[2], pixel[3]]);
+                    }
+                }
+                previous = pixel;
+            }
+        }
+        out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        out
+    }
+
+    /// Reads an image file, guessing its format from its contents.
+    ///
+    /// PPM files, and PNG files with the `image-png` feature, can be read,
+    /// see `decode`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Image::decode(&data).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Decodes a PPM or PNG file into an RGBA image.
+    ///
+    /// PNG files need the `image-png` feature.
+    pub fn decode(data: &[u8]) -> Result<Image, &'static str> {
+        if data.starts_with(PNG_SIGNATURE) {
+            #[cfg(feature = "image-png")]
+            return Image::decode_png(data);
+            #[cfg(not(feature = "image-png"))]
+            return Err("PNG files need the image-png feature");
+        } else if data.starts_with(b"P6") || data.starts_with(b"P3") {
+            Image::decode_ppm(data)
+        } else {
+            Err("Unknown image format")
+        }
+    }
+
+    /// Decodes a binary (P6) or plain text (P3) PPM file.
+    ///
+    /// Samples larger than 8 bits are scaled down to 8 bits.
+    pub fn decode_ppm(data: &[u8]) -> Result<Image, &'static str> {
+        let mut position = 2;
+        let mut header = [0u32; 3];
+        for value in &mut header {
+            *value = ppm_number(data, &mut position)?;
+        }
+        let [width, height, max] = header;
+        if max == 0 || max > 65535 {
+            return Err("Invalid PPM maximum value")
+        }
+        if width as u64 * height as u64 > MAX_PIXELS {
+            return Err("PPM image too large")
+        }
+        let size = Size::new(width, height);
+        let samples = (width as usize).checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or("PPM image too large")?;
+        // Every sample takes at least a byte, so check before allocating
+        if samples > data.len() - position {
+            return Err("PPM data too short")
+        }
+        let mut values = Vec::with_capacity(samples);
+        if data.starts_with(b"P6") {
+            // A single whitespace character separates the header and data
+            position += 1;
+            let width = if max > 255 { 2 } else { 1 };
+            let raw = data.get(position..position + samples * width)
+                .ok_or("PPM data too short")?;
+            if width == 1 {
+                values.extend(raw.iter().map(|&value| value as u32));
+            } else {
+                values.extend(raw.chunks(2).map(|pair| (pair[0] as u32) << 8 | pair[1] as u32));
+            }
+        } else {
+            for _ in 0..samples {
+                values.push(ppm_number(data, &mut position)?);
+            }
+        }
+        let mut image = Image::new(size, PixelFormat::Rgba8888);
+        for (pixel, rgb) in image.data.chunks_mut(4).zip(values.chunks(3)) {
+            for (channel, &value) in rgb.iter().enumerate() {
+                pixel[channel] = ((value.min(max) * 255 + max / 2) / max) as u8;
+            }
+            pixel[3] = 255;
+        }
+        Ok(image)
+    }
+
+    /// Decodes a PNG file with the `png` crate.
+    ///
+    /// Every color type and bit depth is supported, along with transparency
+    /// from `tRNS` chunks. 16-bit samples are reduced to 8 bits.
+    #[cfg(feature = "image-png")]
+    pub fn decode_png(data: &[u8]) -> Result<Image, &'static str> {
+        use png::{ColorType, Decoder, Limits, Transformations};
+
+        let limits = Limits { bytes: MAX_PIXELS as usize * 4 };
+        let mut decoder = Decoder::new_with_limits(data, limits);
+        decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(png_error)?;
+        if reader.info().width as u64 * reader.info().height as u64 > MAX_PIXELS {
+            return Err("PNG image too large")
+        }
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).map_err(png_error)?;
+        let size = Size::new(frame.width, frame.height);
+        let mut image = Image::new(size, PixelFormat::Rgba8888);
+        let rows = pixels.chunks(frame.line_size).take(frame.height as usize);
+        for (y, row) in rows.enumerate() {
+            for x in 0..size.w as usize {
+                let pixel = match frame.color_type {
+                    ColorType::Grayscale => [row[x], row[x], row[x], 255],
+                    ColorType::GrayscaleAlpha => {
+                        [row[x * 2], row[x * 2], row[x * 2], row[x * 2 + 1]]
+                    },
+                    ColorType::Rgb => [row[x * 3], row[x * 3 + 1], row[x * 3 + 2], 255],
+                    ColorType::Rgba => [row[x * 4], row[x * 4 + 1], row[x * 4 + 2], row[x * 4 + 3]],
+                    // Palettes are expanded to RGB
+                    ColorType::Indexed => return Err("Unsupported PNG format")
+                };
+                image.set_pixel(x as u32, y as u32, pixel);
+            }
+        }
+        Ok(image)
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Most pixels a decoded image may have
+const MAX_PIXELS: u64 = 1 << 28;
+
+/// Reads a decimal number from a PPM header, skipping whitespace and
+/// comments before it.
+fn ppm_number(data: &[u8], position: &mut usize) -> Result<u32, &'static str> {
+    loop {
+        match data.get(*position) {
+            Some(b'#') => {
+                while matches!(data.get(*position), Some(&byte) if byte != b'\n') {
+                    *position += 1;
+                }
+            },
+            Some(byte) if byte.is_ascii_whitespace() => *position += 1,
+            Some(_) => break,
+            None => return Err("PPM file truncated")
+        }
+    }
+    let start = *position;
+    while matches!(data.get(*position), Some(byte) if byte.is_ascii_digit()) {
+        *position += 1;
+    }
+    ::std::str::from_utf8(&data[start..*position]).ok()
+        .and_then(|number| number.parse().ok())
+        .ok_or("Invalid number in PPM file")
+}
+
+#[cfg(feature = "image-png")]
+fn png_error(error: png::DecodingError) -> &'static str {
+    match error {
+        png::DecodingError::LimitsExceeded => "PNG image too large",
+        _ => "Invalid PNG file"
+    }
+}
+
+fn qoi_hash(pixel: [u8; 4]) -> usize {
+    (pixel[0] as usize * 3 + pixel[1] as usize * 5
+     + pixel[2] as usize * 7 + pixel[3] as usize * 11) % 64
+}
+
+fn be32(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&be32(data.len() as u32));
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&be32(crc));
+}
+
+/// Wraps data in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xffff;
+    let blocks = data.len().div_ceil(MAX_BLOCK);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 11);
+    // Deflate with a 32K window, no preset dictionary, fastest compression
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(MAX_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(if last { 1 } else { 0 });
+        out.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&be32(adler32(data)));
+    out
+}
+
+/// The CRC-32 used by PNG and zlib.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// The Adler-32 checksum used by zlib.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before b may overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::Size;
+
+    fn sample() -> Image {
+        let mut image = Image::new(Size::new(2, 2), PixelFormat::Rgba8888);
+        image.set_pixel(0, 0, [1, 2, 3, 4]);
+        image.set_pixel(1, 1, [5, 6, 7, 8]);
+        image
+    }
+
+    #[test]
+    fn raw_validation() {
+        assert!(Image::from_raw(Size::new(2, 2), 8, PixelFormat::Rgba8888, vec![0; 16]).is_some());
+        assert!(Image::from_raw(Size::new(2, 2), 12, PixelFormat::Rgba8888, vec![0; 24]).is_some());
+        assert!(Image::from_raw(Size::new(2, 2), 4, PixelFormat::Rgba8888, vec![0; 16]).is_none());
+        assert!(Image::from_raw(Size::new(2, 2), 8, PixelFormat::Rgba8888, vec![0; 15]).is_none());
+    }
+
+    #[test]
+    fn flip_and_convert() {
+        let mut image = sample();
+        image.flip_rows();
+        assert_eq!(image.pixel(0, 1), [1, 2, 3, 4]);
+        assert_eq!(image.pixel(1, 0), [5, 6, 7, 8]);
+        image.convert(PixelFormat::Bgra8888);
+        assert_eq!(image.pixel(0, 1), [3, 2, 1, 4]);
+        assert_eq!(image.rgba(0, 1), [1, 2, 3, 4]);
+
+        let padded = Image::from_raw(Size::new(1, 3), 8, PixelFormat::Bgra8888,
+                                     (0..24).collect()).unwrap();
+        let mut flipped = padded.clone();
+        flipped.flip_rows();
+        assert_eq!(flipped.pixel(0, 0), [16, 17, 18, 19]);
+        let packed = padded.to_format(PixelFormat::Rgba8888);
+        assert_eq!(packed.stride, 4);
+        assert_eq!(packed.data, vec![2, 1, 0, 3, 10, 9, 8, 11, 18, 17, 16, 19]);
+    }
+
+    #[test]
+    fn ppm() {
+        let ppm = sample().encode_ppm();
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[1, 2, 3, 0, 0, 0, 0, 0, 0, 5, 6, 7]);
+        assert_eq!(ppm, expected);
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&vec![0xff; 100_000]), 0x149a_302c);
+    }
+
+    #[test]
+    fn png() {
+        let png = sample().encode_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+        // 2 rows of a filter byte and 2 pixels, in one stored block
+        let idat = &png[33..png.len() - 12];
+        assert_eq!(&idat[4..8], b"IDAT");
+        assert_eq!(&idat[8..15], &[0x78, 0x01, 1, 18, 0, !18, 0xff]);
+    }
+
+    #[test]
+    fn qoi() {
+        let mut image = Image::new(Size::new(2, 1), PixelFormat::Rgba8888);
+        image.set_pixel(0, 0, [255, 0, 0, 255]);
+        image.set_pixel(1, 0, [255, 0, 0, 255]);
+        let qoi = image.encode_qoi();
+        assert_eq!(&qoi[..14], b"qoif\0\0\0\x02\0\0\0\x01\x04\0");
+        // A small difference from the starting pixel, then a run of one
+        assert_eq!(&qoi[14..], &[0x5a, 0xc0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[cfg(feature = "image-png")]
+    #[test]
+    fn decode_png() {
+        // Every pixel of the encoder's output is read back
+        let mut image = sample();
+        image.convert(PixelFormat::Bgra8888);
+        assert_eq!(Image::decode(&image.encode_png()).unwrap(), sample());
+
+        // 3x2 RGB, compressed, with the Sub and Paeth filters
+        let rgb = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x08, 0x02, 0x00, 0x00,
+            0x00, 0x12, 0x16, 0xf1, 0x4d, 0x00, 0x00, 0x00, 0x14, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0xe4, 0x12, 0x91, 0x83, 0x00, 0x16, 0x56, 0x30, 0x68, 0x12, 0x59, 0x06,
+            0x00, 0x11, 0x09, 0x02, 0x50, 0xce, 0x0c, 0x20, 0x92, 0x00, 0x00, 0x00, 0x00, 0x49,
+            0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82];
+        let image = Image::decode_png(&rgb).unwrap();
+        assert_eq!(image.size, Size::new(3, 2));
+        assert_eq!(image.rgba(2, 0), [70, 80, 90, 255]);
+        assert_eq!(image.rgba(1, 1), [45, 55, 65, 255]);
+        assert_eq!(image.rgba(2, 1), [200, 100, 0, 255]);
+
+        // 3x1 with a 2-bit palette and transparency for the first two entries
+        let palette = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x00, 0x00,
+            0x00, 0x66, 0x8e, 0xfc, 0x27, 0x00, 0x00, 0x00, 0x09, 0x50, 0x4c, 0x54, 0x45, 0xff,
+            0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0x2d, 0x4a, 0xcd, 0x8a, 0x00, 0x00,
+            0x00, 0x02, 0x74, 0x52, 0x4e, 0x53, 0xff, 0x80, 0x08, 0x0f, 0xb3, 0x6a, 0x00, 0x00,
+            0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x90, 0x00, 0x00, 0x00, 0x1a,
+            0x00, 0x19, 0x80, 0x00, 0x8e, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+            0xae, 0x42, 0x60, 0x82];
+        let image = Image::decode_png(&palette).unwrap();
+        assert_eq!(image.rgba(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.rgba(1, 0), [0, 255, 0, 128]);
+        assert_eq!(image.rgba(2, 0), [0, 0, 255, 255]);
+
+        // 1x1 16-bit grey and alpha
+        let grey_alpha = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x10, 0x04, 0x00, 0x00,
+            0x00, 0xe5, 0x8c, 0xd0, 0x41, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0x10, 0x32, 0x69, 0x60, 0x00, 0x00, 0x01, 0xe9, 0x00, 0xc7, 0xa5, 0x49,
+            0x89, 0x37, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82];
+        assert_eq!(Image::decode_png(&grey_alpha).unwrap().rgba(0, 0), [0x12, 0x12, 0x12, 0x80]);
+
+        let mut corrupt = rgb;
+        corrupt[30] ^= 1;
+        assert!(Image::decode_png(&corrupt).is_err());
+    }
+
+    #[cfg(feature = "image-png")]
+    #[test]
+    fn decode_bad_png() {
+        // Rewrites the IHDR chunk of an encoded 2x2 image, keeping its CRC valid
+        fn with_size(width: u32, height: u32) -> Vec<u8> {
+            let mut png = sample().encode_png();
+            png[16..20].copy_from_slice(&be32(width));
+            png[20..24].copy_from_slice(&be32(height));
+            let crc = crc32(&png[12..29]);
+            png[29..33].copy_from_slice(&be32(crc));
+            png
+        }
+        assert!(Image::decode_png(&with_size(2, 2)).is_ok());
+        // IDAT holds less data than IHDR asks for
+        assert!(Image::decode_png(&with_size(2, 3)).is_err());
+        assert_eq!(Image::decode_png(&with_size(1 << 20, 1 << 20)).err(),
+                   Some("PNG image too large"));
+
+        let png = sample().encode_png();
+        let idat = png.windows(4).position(|kind| kind == b"IDAT").unwrap();
+        let length = u32::from_be_bytes([png[idat - 4], png[idat - 3], png[idat - 2],
+                                         png[idat - 1]]) as usize;
+        // Only IEND may be missing once the image data is complete
+        for end in 0..idat + 8 + length {
+            assert!(Image::decode_png(&png[..end]).is_err());
+        }
+        // Corrupt image data behind a valid chunk checksum: the zlib header,
+        // the stored block's length check and the first row's filter type
+        for &(index, value) in &[(0, 0x00), (5, 0x00), (7, 5)] {
+            let mut corrupt = png.clone();
+            corrupt[idat + 4 + index] = value;
+            let crc = crc32(&corrupt[idat..idat + 4 + length]);
+            corrupt[idat + 4 + length..idat + 8 + length].copy_from_slice(&be32(crc));
+            assert!(Image::decode_png(&corrupt).is_err());
+        }
+    }
+
+    #[cfg(not(feature = "image-png"))]
+    #[test]
+    fn png_needs_feature() {
+        assert_eq!(Image::decode(&sample().encode_png()),
+                   Err("PNG files need the image-png feature"));
+    }
+
+    #[test]
+    fn decode_ppm() {
+        let mut opaque = sample();
+        for y in 0..2 {
+            for x in 0..2 {
+                let mut pixel = opaque.pixel(x, y);
+                pixel[3] = 255;
+                opaque.set_pixel(x, y, pixel);
+            }
+        }
+        assert_eq!(Image::decode(&sample().encode_ppm()).unwrap(), opaque);
+        let plain = Image::decode(b"P3 # comment\n1 1 15\n15 0 5\n").unwrap();
+        assert_eq!(plain.rgba(0, 0), [255, 0, 85, 255]);
+        assert!(Image::decode(b"P6 2 2 255 abc").is_err());
+        // Huge sizes are rejected before anything is allocated
+        assert_eq!(Image::decode(b"P6 4000000000 4000000000 255 "), Err("PPM image too large"));
+        assert_eq!(Image::decode(b"P6 10000 10000 255 abc"), Err("PPM data too short"));
+    }
+}
@@ -76,8 +76,9 @@ pub mod keyboard {
     use super::super::xkb::Keysym;
 
     /// Get currently held keys.
-    /// # Panics
-    /// All the time, this function hasn't been implemented yet
+    ///
+    /// The slice is wlc's own, and only valid until the next key event.
+    #[deprecated(since="0.7.1", note="Use current_keys()->Vec<u32> instead")]
     pub fn get_current_keys<'a>() -> Option<&'a[u32]> {
         let mut size: size_t = 0;
         unsafe {
@@ -91,6 +92,21 @@ pub mod keyboard {
         }
     }
 
+    /// Get currently held keys.
+    pub fn current_keys() -> Vec<u32> {
+        let mut size: size_t = 0;
+        let size_ptr: *mut size_t = &mut size;
+        unsafe {
+            let out_ptr = super::wlc_keyboard_get_current_keys(size_ptr);
+            if size == 0 || out_ptr.is_null() {
+                Vec::new()
+            }
+            else {
+                slice::from_raw_parts(out_ptr, size as usize).to_vec()
+            }
+        }
+    }
+
     /// Gets a keysym given a key and modifiers.
     ///
     /// In order to delay breaking backwards compatibility this method is _not_
@@ -0,0 +1,373 @@
+//! Keeping track of the keyboard, and repeating keys for bindings.
+//!
+//! wlc only tells about keys as they are pressed and released. A
+//! `KeyboardState` fed from the `keyboard_key` callback keeps which keys
+//! are held, when each was pressed, and the modifiers and LEDs of the last
+//! event.
+//!
+//! Keys the compositor handles itself are never sent to clients, so clients
+//! can not repeat them either. `KeyRepeat` repeats them on the compositor
+//! side: once a binding is run, `start` arms an event loop `Timer` which
+//! runs the callback again after the repeat delay, then at the repeat rate
+//! until the key is released or another one is pressed.
+//!
+//! Like [`Timer`](../event_loop/struct.Timer.html), `KeyRepeat` is not `Send`.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::cell::RefCell;
+//! use std::sync::Mutex;
+//! use rustwlc::*;
+//! use rustwlc::keyboard_state::{KeyboardState, KeyRepeat};
+//!
+//! const KEY_LEFT: u32 = 105;
+//!
+//! lazy_static! {
+//!     static ref KEYBOARD: Mutex<KeyboardState> = Mutex::new(KeyboardState::new());
+//! }
+//!
+//! thread_local! {
+//!     static REPEAT: RefCell<KeyRepeat> = RefCell::new(KeyRepeat::new(run_binding));
+//! }
+//!
+//! /// Runs a binding, returning whether there was one.
+//! fn run_binding(key: u32, mods: KeyboardModifiers) -> bool {
+//!     if key == KEY_LEFT && mods.mods.contains(MOD_MOD4) {
+//!         println!("Focus the view to the left");
+//!         return true
+//!     }
+//!     false
+//! }
+//!
+//! extern fn keyboard_key(_view: WlcView, time: u32, mods: &KeyboardModifiers,
+//!                        key: u32, state: KeyState) -> bool {
+//!     KEYBOARD.lock().unwrap().keyboard_key(time, mods, key, state);
+//!     REPEAT.with(|repeat| {
+//!         let mut repeat = repeat.borrow_mut();
+//!         repeat.keyboard_key(key, state);
+//!         if state == KeyState::Pressed && run_binding(key, *mods) {
+//!             repeat.start(key, *mods);
+//!             return true
+//!         }
+//!         false
+//!     })
+//! }
+//! # fn main() {}
+//! ```
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+#[cfg(not(feature = "dummy"))]
+use super::event_loop::Timer;
+#[cfg(feature = "dummy")]
+use super::dummy_event_loop::Timer;
+#[cfg(not(feature = "dummy"))]
+use super::input;
+#[cfg(feature = "dummy")]
+use super::dummy_input as input;
+use super::types::{KeyboardLed, KeyboardModifiers, KeyMod, KeyState};
+
+/// A held key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PressedKey {
+    /// The key code
+    pub key: u32,
+    /// When the key was pressed
+    pub time: u32
+}
+
+/// Held keys, modifiers and LEDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardState {
+    keys: BTreeMap<u32, u32>,
+    modifiers: KeyboardModifiers
+}
+
+impl Default for KeyboardState {
+    fn default() -> KeyboardState {
+        KeyboardState::new()
+    }
+}
+
+impl KeyboardState {
+    /// Makes a state with no keys held.
+    pub fn new() -> KeyboardState {
+        KeyboardState {
+            keys: BTreeMap::new(),
+            modifiers: KeyboardModifiers { leds: KeyboardLed::empty(), mods: KeyMod::empty() }
+        }
+    }
+
+    /// Handles a key event. Call from the `keyboard_key` callback.
+    ///
+    /// Returns whether the held keys changed, which they do not for a
+    /// press of a key already held or a release of one which was not.
+    pub fn keyboard_key(&mut self, time: u32, mods: &KeyboardModifiers, key: u32,
+                        state: KeyState) -> bool {
+        self.modifiers = *mods;
+        match state {
+            KeyState::Pressed if !self.keys.contains_key(&key) => {
+                self.keys.insert(key, time);
+                true
+            },
+            KeyState::Pressed => false,
+            KeyState::Released => self.keys.remove(&key).is_some()
+        }
+    }
+
+    /// Replaces the held keys with the ones wlc has, pressed at `time`.
+    ///
+    /// Keys held since before the state was made are otherwise missed.
+    pub fn sync(&mut self, time: u32) {
+        let keys = input::keyboard::current_keys();
+        self.keys.retain(|key, _| keys.contains(key));
+        for key in keys {
+            self.keys.entry(key).or_insert(time);
+        }
+    }
+
+    /// Forgets every held key, such as when the compositor loses the
+    /// keyboard.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// Whether a key is held.
+    pub fn is_pressed(&self, key: u32) -> bool {
+        self.keys.contains_key(&key)
+    }
+
+    /// When a held key was pressed.
+    pub fn pressed_at(&self, key: u32) -> Option<u32> {
+        self.keys.get(&key).cloned()
+    }
+
+    /// How long a key has been held at `time`, in milliseconds.
+    pub fn held_for(&self, key: u32, time: u32) -> Option<u32> {
+        self.pressed_at(key).map(|pressed| time.wrapping_sub(pressed))
+    }
+
+    /// Held keys, in the order they were pressed.
+    pub fn pressed(&self) -> Vec<PressedKey> {
+        let mut pressed: Vec<_> = self.keys.iter()
+            .map(|(&key, &time)| PressedKey { key, time })
+            .collect();
+        pressed.sort_by_key(|pressed| pressed.time);
+        pressed
+    }
+
+    /// Held key codes, in the order they were pressed.
+    pub fn keys(&self) -> Vec<u32> {
+        self.pressed().into_iter().map(|pressed| pressed.key).collect()
+    }
+
+    /// The key held which was pressed last.
+    pub fn last_pressed(&self) -> Option<PressedKey> {
+        self.pressed().pop()
+    }
+
+    /// The modifiers and LEDs of the last key event.
+    pub fn modifiers(&self) -> KeyboardModifiers {
+        self.modifiers
+    }
+
+    /// The modifiers held at the last key event.
+    pub fn mods(&self) -> KeyMod {
+        self.modifiers.mods
+    }
+
+    /// The LEDs lit at the last key event.
+    pub fn leds(&self) -> KeyboardLed {
+        self.modifiers.leds
+    }
+}
+
+/// How keys are repeated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RepeatSettings {
+    /// How long a key is held before it repeats, in milliseconds
+    pub delay: u32,
+    /// Repeats per second. 0 turns repeating off.
+    pub rate: u32
+}
+
+impl Default for RepeatSettings {
+    fn default() -> RepeatSettings {
+        RepeatSettings { delay: 600, rate: 25 }
+    }
+}
+
+impl RepeatSettings {
+    /// Time between repeats in milliseconds, or `None` when repeating is
+    /// off.
+    pub fn interval(&self) -> Option<u32> {
+        1000u32.checked_div(self.rate).map(|ms| ms.max(1))
+    }
+}
+
+/// The key being repeated, shared with the timer.
+#[derive(Debug)]
+struct Repeating {
+    settings: RepeatSettings,
+    key: Option<(u32, KeyboardModifiers)>
+}
+
+type RepeatCallback = Rc<RefCell<Box<dyn FnMut(u32, KeyboardModifiers) -> bool>>>;
+
+/// Repeats keys handled by the compositor.
+pub struct KeyRepeat {
+    repeating: Rc<RefCell<Repeating>>,
+    callback: RepeatCallback,
+    timer: Option<Timer>
+}
+
+impl KeyRepeat {
+    /// Makes a key repeater which calls `callback` with the key and
+    /// modifiers on each repeat. Repeating stops when it returns `false`.
+    ///
+    /// The timer is created when a key is first repeated, so a repeater
+    /// can be made before wlc is initialized.
+    pub fn new<F>(callback: F) -> KeyRepeat
+        where F: FnMut(u32, KeyboardModifiers) -> bool + 'static {
+        KeyRepeat {
+            repeating: Rc::new(RefCell::new(Repeating {
+                settings: RepeatSettings::default(),
+                key: None
+            })),
+            callback: Rc::new(RefCell::new(Box::new(callback))),
+            timer: None
+        }
+    }
+
+    /// The repeat delay and rate.
+    pub fn settings(&self) -> RepeatSettings {
+        self.repeating.borrow().settings
+    }
+
+    /// Sets the repeat delay and rate, from the next repeat on.
+    pub fn set_settings(&mut self, settings: RepeatSettings) {
+        self.repeating.borrow_mut().settings = settings;
+        if settings.interval().is_none() {
+            self.stop();
+        }
+    }
+
+    /// The key being repeated.
+    pub fn repeating(&self) -> Option<u32> {
+        self.repeating.borrow().key.map(|(key, _)| key)
+    }
+
+    /// Starts repeating a key which was just pressed, replacing any key
+    /// being repeated.
+    pub fn start(&mut self, key: u32, mods: KeyboardModifiers) {
+        let settings = self.settings();
+        if settings.interval().is_none() {
+            return
+        }
+        self.repeating.borrow_mut().key = Some((key, mods));
+        if self.timer.is_none() {
+            let repeating = self.repeating.clone();
+            let callback = self.callback.clone();
+            self.timer = Timer::new(move || repeat(&repeating, &callback));
+        }
+        match self.timer {
+            Some(ref timer) => { timer.update(settings.delay.max(1)); },
+            // Without a timer nothing repeats
+            None => { self.repeating.borrow_mut().key = None; }
+        }
+    }
+
+    /// Stops repeating.
+    pub fn stop(&mut self) {
+        self.repeating.borrow_mut().key = None;
+        if let Some(ref timer) = self.timer {
+            timer.stop();
+        }
+    }
+
+    /// Stops repeating when the repeated key is released or another key is
+    /// pressed. Call from the `keyboard_key` callback, before `start`.
+    pub fn keyboard_key(&mut self, key: u32, state: KeyState) {
+        let stops = match (self.repeating(), state) {
+            (Some(_), KeyState::Pressed) => true,
+            (Some(repeated), KeyState::Released) => repeated == key,
+            (None, _) => false
+        };
+        if stops {
+            self.stop();
+        }
+    }
+}
+
+/// Runs the callback for the repeated key. Returns when to repeat next.
+fn repeat(repeating: &Rc<RefCell<Repeating>>, callback: &RepeatCallback) -> Option<u32> {
+    let (key, mods) = repeating.borrow().key?;
+    // Called without the state borrowed, so the callback can stop repeating
+    let handled = (callback.borrow_mut())(key, mods);
+    let mut repeating = repeating.borrow_mut();
+    if !handled {
+        repeating.key = None;
+    }
+    match repeating.key {
+        Some(_) => repeating.settings.interval(),
+        None => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{MOD_CTRL, NUM_LOCK};
+
+    #[test]
+    fn key_tracking() {
+        let mut state = KeyboardState::new();
+        let mods = KeyboardModifiers { leds: NUM_LOCK, mods: MOD_CTRL };
+        assert!(state.keyboard_key(100, &mods, 30, KeyState::Pressed));
+        assert!(state.keyboard_key(150, &mods, 20, KeyState::Pressed));
+        assert!(!state.keyboard_key(200, &mods, 30, KeyState::Pressed));
+        assert_eq!(state.keys(), vec![30, 20]);
+        assert_eq!(state.pressed_at(30), Some(100));
+        assert_eq!(state.held_for(20, 400), Some(250));
+        assert_eq!(state.last_pressed(), Some(PressedKey { key: 20, time: 150 }));
+        assert_eq!(state.mods(), MOD_CTRL);
+        assert_eq!(state.leds(), NUM_LOCK);
+
+        let none = KeyboardModifiers { leds: KeyboardLed::empty(), mods: KeyMod::empty() };
+        assert!(state.keyboard_key(300, &none, 30, KeyState::Released));
+        assert!(!state.keyboard_key(310, &none, 30, KeyState::Released));
+        assert!(!state.is_pressed(30) && state.is_pressed(20));
+        assert_eq!(state.held_for(30, 400), None);
+        assert_eq!(state.modifiers(), none);
+        state.clear();
+        assert!(state.pressed().is_empty());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn syncing() {
+        let mut state = KeyboardState::new();
+        let none = KeyboardModifiers { leds: KeyboardLed::empty(), mods: KeyMod::empty() };
+        state.keyboard_key(100, &none, 30, KeyState::Pressed);
+        state.keyboard_key(150, &none, 20, KeyState::Pressed);
+        input::keyboard::set_dummy_keys(&[30, 40]);
+        state.sync(500);
+        assert_eq!(state.pressed_at(30), Some(100));
+        assert_eq!(state.pressed_at(40), Some(500));
+        assert!(!state.is_pressed(20));
+        input::keyboard::set_dummy_keys(&[]);
+        state.sync(600);
+        assert!(state.pressed().is_empty());
+    }
+
+    #[test]
+    fn repeat_settings() {
+        assert_eq!(RepeatSettings::default().interval(), Some(40));
+        assert_eq!(RepeatSettings { delay: 0, rate: 0 }.interval(), None);
+        assert_eq!(RepeatSettings { delay: 0, rate: 5000 }.interval(), Some(1));
+    }
+}
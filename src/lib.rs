@@ -59,6 +59,10 @@
 #![warn(missing_docs)]
 
 extern crate libc;
+#[cfg(feature = "window-rules-regex")]
+extern crate regex;
+#[cfg(feature = "image-png")]
+extern crate png;
 
 #[macro_use]
 extern crate bitflags;
@@ -99,6 +103,39 @@ pub mod wayland;
 #[deprecated]
 pub mod xkb;
 pub mod render;
+pub mod image;
+pub mod font;
+pub mod canvas;
+
+pub mod checked;
+#[cfg(not(feature = "dummy"))]
+pub mod event_loop;
+#[cfg(feature = "dummy")]
+pub mod dummy_event_loop;
+pub mod gamma;
+pub mod screenshot;
+pub mod recorder;
+pub mod decorations;
+pub mod wallpaper;
+#[cfg(not(feature = "dummy"))]
+pub mod surface_render;
+pub mod render_scheduler;
+pub mod animation;
+
+pub mod workspace;
+pub mod output_layout;
+pub mod output_profile;
+pub mod window_rules;
+pub mod properties;
+pub mod registry;
+pub mod hierarchy;
+pub mod stacking;
+pub mod hit_test;
+pub mod hover;
+pub mod pointer_gestures;
+pub mod touch;
+pub mod scroll;
+pub mod keyboard_state;
 
 pub use types::*;
 
@@ -0,0 +1,357 @@
+//! Placement of outputs in a single global coordinate space.
+//!
+//! wlc gives each `WlcOutput` its own coordinate space starting at (0, 0),
+//! and has no idea how the monitors are physically arranged. An
+//! `OutputLayout` places every output at a position in a global space so
+//! coordinates can be translated between outputs and the pointer can move
+//! from one monitor to the next.
+//!
+//! Output-local coordinates are the ones wlc uses for views and the pointer,
+//! bounded by `get_virtual_resolution()`. Physical coordinates are pixels in
+//! the output's framebuffer, which is larger when `get_scale()` is above 1.
+//! The global space is made of output-local units.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::{Point, WlcOutput};
+//! use rustwlc::output_layout::{OutputLayout, Placement};
+//!
+//! let mut layout = OutputLayout::new();
+//! // Outputs are placed left to right by default
+//! for output in WlcOutput::list() {
+//!     layout.add(output);
+//! }
+//! // Put the focused output above the others instead
+//! layout.set_placement(WlcOutput::focused(), Placement::Manual(Point::new(0, -1080)))
+//!       .unwrap();
+//!
+//! if let Some((output, local)) = layout.global_to_local(Point::new(2000, 10)) {
+//!     println!("{:?} is at {:?} on {:?}", Point::new(2000, 10), local, output);
+//! }
+//! ```
+
+use std::cmp;
+
+use super::{WlcOutput};
+use super::types::{Geometry, Point, Size};
+#[cfg(not(feature = "dummy"))]
+use super::input::pointer;
+#[cfg(feature = "dummy")]
+use super::dummy_input::pointer;
+
+/// Where an output sits in the global space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Placement {
+    /// Placed to the right of all outputs before it in the layout
+    Auto,
+    /// Placed with its top left corner at a fixed global position
+    Manual(Point)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Entry {
+    output: WlcOutput,
+    placement: Placement,
+    size: Size,
+    scale: u32,
+    origin: Point
+}
+
+/// A set of outputs placed in a global coordinate space.
+#[derive(Debug, Clone, Default)]
+pub struct OutputLayout {
+    entries: Vec<Entry>
+}
+
+impl OutputLayout {
+    /// Creates an empty layout.
+    pub fn new() -> OutputLayout {
+        OutputLayout { entries: Vec::new() }
+    }
+
+    /// Adds an output, placed to the right of the other outputs.
+    ///
+    /// The size and scale are read from the output. Call this from the
+    /// `output_created` callback.
+    pub fn add(&mut self, output: WlcOutput) {
+        self.add_placed(output, Placement::Auto);
+    }
+
+    /// Adds an output at a specific placement.
+    ///
+    /// If the output is already in the layout its placement is updated.
+    pub fn add_placed(&mut self, output: WlcOutput, placement: Placement) {
+        let size = output.get_virtual_resolution().unwrap_or_else(Size::zero);
+        let scale = output.get_scale();
+        self.insert(output, placement, size, scale);
+    }
+
+    /// Adds an output with a known size and scale without asking wlc.
+    #[cfg(test)]
+    fn add_with_size(&mut self, output: WlcOutput, size: Size, scale: u32) {
+        self.insert(output, Placement::Auto, size, scale);
+    }
+
+    /// Removes an output, rearranging the automatically placed ones.
+    ///
+    /// Call this from the `output_destroyed` callback.
+    pub fn remove(&mut self, output: WlcOutput) {
+        self.entries.retain(|entry| entry.output != output);
+        self.arrange();
+    }
+
+    /// Changes how an output is placed.
+    ///
+    /// Returns an error if the output is not in the layout.
+    pub fn set_placement(&mut self, output: WlcOutput, placement: Placement)
+                         -> Result<(), &'static str> {
+        match self.entries.iter_mut().find(|entry| entry.output == output) {
+            Some(entry) => entry.placement = placement,
+            None => return Err("Output is not in the layout")
+        }
+        self.arrange();
+        Ok(())
+    }
+
+    /// Re-reads the resolution and scale of every output.
+    ///
+    /// Call this from the `output_resolution` callback.
+    pub fn refresh(&mut self) {
+        for entry in &mut self.entries {
+            if let Some(size) = entry.output.get_virtual_resolution() {
+                entry.size = size;
+            }
+            entry.scale = entry.output.get_scale();
+        }
+        self.arrange();
+    }
+
+    /// The outputs in the layout, in the order they were added.
+    pub fn outputs(&self) -> Vec<WlcOutput> {
+        self.entries.iter().map(|entry| entry.output).collect()
+    }
+
+    /// Gets the global position of an output's top left corner.
+    pub fn position_of(&self, output: WlcOutput) -> Option<Point> {
+        self.entry(output).map(|entry| entry.origin)
+    }
+
+    /// Gets the area an output covers in the global space.
+    pub fn geometry_of(&self, output: WlcOutput) -> Option<Geometry> {
+        self.entry(output).map(|entry| Geometry::new(entry.origin, entry.size))
+    }
+
+    /// Gets the smallest geometry containing every output.
+    pub fn bounds(&self) -> Geometry {
+        let mut iter = self.entries.iter();
+        let first = match iter.next() {
+            Some(entry) => entry,
+            None => return Geometry::zero()
+        };
+        let (mut min, mut max) = (first.origin, far_corner(first));
+        for entry in iter {
+            min = Point::from_min_coords(min, entry.origin);
+            max = Point::from_max_coords(max, far_corner(entry));
+        }
+        Geometry::new(min, Size::new((max.x - min.x) as u32,
+                                     (max.y - min.y) as u32))
+    }
+
+    /// Finds the output under a global point.
+    ///
+    /// If outputs overlap, the one added first wins.
+    pub fn output_at(&self, global: Point) -> Option<WlcOutput> {
+        self.entry_at(global.x as f64, global.y as f64)
+            .map(|entry| entry.output)
+    }
+
+    /// Translates a global point into a point local to the output under it.
+    pub fn global_to_local(&self, global: Point) -> Option<(WlcOutput, Point)> {
+        self.entry_at(global.x as f64, global.y as f64).map(|entry| {
+            (entry.output, Point::new(global.x - entry.origin.x,
+                                      global.y - entry.origin.y))
+        })
+    }
+
+    /// Translates a point local to an output into the global space.
+    pub fn local_to_global(&self, output: WlcOutput, local: Point) -> Option<Point> {
+        self.entry(output).map(|entry| {
+            Point::new(local.x + entry.origin.x, local.y + entry.origin.y)
+        })
+    }
+
+    /// Translates output-local coordinates into framebuffer pixels.
+    pub fn local_to_physical(&self, output: WlcOutput, local: Point) -> Option<Point> {
+        self.entry(output).map(|entry| {
+            let scale = cmp::max(entry.scale, 1) as i32;
+            Point::new(local.x * scale, local.y * scale)
+        })
+    }
+
+    /// Translates framebuffer pixels into output-local coordinates.
+    pub fn physical_to_local(&self, output: WlcOutput, physical: Point) -> Option<Point> {
+        self.entry(output).map(|entry| {
+            let scale = cmp::max(entry.scale, 1) as i32;
+            Point::new(physical.x / scale, physical.y / scale)
+        })
+    }
+
+    /// Works out where the pointer should go after moving to `(x, y)` in
+    /// the coordinates of `output`.
+    ///
+    /// If the point is on another output, that output and the coordinates
+    /// local to it are returned. If it is in a gap between outputs or past
+    /// the edge of the layout it is clamped to `output`.
+    pub fn pointer_destination(&self, output: WlcOutput, x: f64, y: f64)
+                               -> Option<(WlcOutput, f64, f64)> {
+        let current = self.entry(output)?;
+        let (gx, gy) = (x + current.origin.x as f64, y + current.origin.y as f64);
+        let target = self.entry_at(gx, gy).unwrap_or(current);
+        let max_x = (target.size.w as f64 - 1.0).max(0.0);
+        let max_y = (target.size.h as f64 - 1.0).max(0.0);
+        let local_x = (gx - target.origin.x as f64).max(0.0).min(max_x);
+        let local_y = (gy - target.origin.y as f64).max(0.0).min(max_y);
+        Some((target.output, local_x, local_y))
+    }
+
+    /// Moves the pointer, crossing into neighbouring outputs at the edges.
+    ///
+    /// Call this from the `pointer_motion_v2` callback in place of
+    /// `input::pointer::set_position_v2`. `output` should be the output the
+    /// pointer is currently on, usually `WlcOutput::focused()`. When the
+    /// pointer crosses an edge the new output is focused.
+    ///
+    /// Returns the output the pointer ended up on.
+    pub fn move_pointer(&self, output: WlcOutput, x: f64, y: f64) -> WlcOutput {
+        match self.pointer_destination(output, x, y) {
+            Some((target, local_x, local_y)) => {
+                if target != output {
+                    WlcOutput::focus(Some(target));
+                }
+                pointer::set_position_v2(local_x, local_y);
+                target
+            },
+            None => {
+                pointer::set_position_v2(x, y);
+                output
+            }
+        }
+    }
+
+    fn insert(&mut self, output: WlcOutput, placement: Placement, size: Size, scale: u32) {
+        let entry = Entry {
+            output,
+            placement,
+            size,
+            scale,
+            origin: Point::origin()
+        };
+        match self.entries.iter().position(|entry| entry.output == output) {
+            Some(index) => self.entries[index] = entry,
+            None => self.entries.push(entry)
+        }
+        self.arrange();
+    }
+
+    /// Recalculates the origin of every automatically placed output.
+    fn arrange(&mut self) {
+        let mut next_x = 0;
+        for entry in &mut self.entries {
+            entry.origin = match entry.placement {
+                Placement::Manual(point) => point,
+                Placement::Auto => Point::new(next_x, 0)
+            };
+            next_x = cmp::max(next_x, entry.origin.x + entry.size.w as i32);
+        }
+    }
+
+    fn entry(&self, output: WlcOutput) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.output == output)
+    }
+
+    fn entry_at(&self, x: f64, y: f64) -> Option<&Entry> {
+        self.entries.iter().find(|entry| {
+            x >= entry.origin.x as f64
+                && y >= entry.origin.y as f64
+                && x < (entry.origin.x + entry.size.w as i32) as f64
+                && y < (entry.origin.y + entry.size.h as i32) as f64
+        })
+    }
+}
+
+fn far_corner(entry: &Entry) -> Point {
+    Point::new(entry.origin.x + entry.size.w as i32,
+               entry.origin.y + entry.size.h as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::WlcOutput;
+    use super::super::types::{Geometry, Point, Size};
+
+    fn outputs() -> (WlcOutput, WlcOutput, WlcOutput) {
+        unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2), WlcOutput::dummy(3)) }
+    }
+
+    #[test]
+    fn auto_arrangement() {
+        let (a, b, c) = outputs();
+        let mut layout = OutputLayout::new();
+        layout.add_with_size(a, Size::new(100, 50), 1);
+        layout.add_with_size(b, Size::new(200, 80), 2);
+        layout.add_with_size(c, Size::new(10, 10), 1);
+        assert_eq!(layout.position_of(a), Some(Point::new(0, 0)));
+        assert_eq!(layout.position_of(b), Some(Point::new(100, 0)));
+        assert_eq!(layout.position_of(c), Some(Point::new(300, 0)));
+        assert_eq!(layout.bounds(), Geometry::new(Point::origin(), Size::new(310, 80)));
+
+        layout.remove(b);
+        assert_eq!(layout.position_of(c), Some(Point::new(100, 0)));
+        assert_eq!(layout.position_of(b), None);
+    }
+
+    #[test]
+    fn manual_placement() {
+        let (a, b, _) = outputs();
+        let mut layout = OutputLayout::new();
+        layout.add_with_size(a, Size::new(100, 50), 1);
+        layout.add_with_size(b, Size::new(100, 50), 1);
+        layout.set_placement(b, Placement::Manual(Point::new(0, -50))).unwrap();
+        assert_eq!(layout.output_at(Point::new(10, -10)), Some(b));
+        assert_eq!(layout.output_at(Point::new(10, 10)), Some(a));
+        assert_eq!(layout.output_at(Point::new(10, 60)), None);
+        assert_eq!(layout.global_to_local(Point::new(10, -10)),
+                   Some((b, Point::new(10, 40))));
+        assert_eq!(layout.local_to_global(b, Point::new(10, 40)),
+                   Some(Point::new(10, -10)));
+        assert_eq!(layout.bounds(), Geometry::new(Point::new(0, -50), Size::new(100, 100)));
+    }
+
+    #[test]
+    fn scale_translation() {
+        let (a, b, _) = outputs();
+        let mut layout = OutputLayout::new();
+        layout.add_with_size(a, Size::new(100, 50), 2);
+        assert_eq!(layout.local_to_physical(a, Point::new(10, 20)),
+                   Some(Point::new(20, 40)));
+        assert_eq!(layout.physical_to_local(a, Point::new(20, 40)),
+                   Some(Point::new(10, 20)));
+        assert_eq!(layout.local_to_physical(b, Point::new(10, 20)), None);
+    }
+
+    #[test]
+    fn pointer_crossing() {
+        let (a, b, c) = outputs();
+        let mut layout = OutputLayout::new();
+        layout.add_with_size(a, Size::new(100, 100), 1);
+        layout.add_with_size(b, Size::new(100, 50), 1);
+        // Crossing the right edge of a lands on b
+        assert_eq!(layout.pointer_destination(a, 105.0, 10.0), Some((b, 5.0, 10.0)));
+        // Crossing into the gap below b is clamped to a
+        assert_eq!(layout.pointer_destination(a, 150.0, 80.0), Some((a, 99.0, 80.0)));
+        // Past the edge of the layout stays on the current output
+        assert_eq!(layout.pointer_destination(b, -150.0, 10.0), Some((b, 0.0, 10.0)));
+        assert_eq!(layout.pointer_destination(c, 0.0, 0.0), None);
+    }
+}
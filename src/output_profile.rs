@@ -0,0 +1,531 @@
+//! Automatic output configuration when monitors are plugged in.
+//!
+//! A `Profiles` set holds a list of `Profile`s, each describing one
+//! arrangement of monitors (for example "laptop only" or "docked"). Whenever
+//! the set of connected outputs changes, the first profile matching it
+//! exactly is applied: resolutions and scale are set with
+//! `WlcOutput::set_resolution`, disabled outputs are put to sleep, the
+//! others are woken, and positions are written into an `OutputLayout`.
+//!
+//! A profile matches when every connected output is claimed by exactly one
+//! of its `output` entries and every entry claims an output. Entries naming
+//! an output are matched first, then `*` entries take the remaining outputs
+//! in order.
+//!
+//! # Config format
+//! Profiles can be loaded from a small config format similar to kanshi's:
+//!
+//! ```text
+//! # Lines starting with a # are comments
+//! profile laptop {
+//!     output eDP-1 resolution 1920x1080 scale 1
+//! }
+//!
+//! profile docked {
+//!     output eDP-1 disable
+//!     output DP-1 resolution 2560x1440 position 0,0
+//!     output * position 2560,0
+//! }
+//! ```
+//!
+//! Each `output` line takes the output name (as from `get_name()`) or `*`,
+//! followed by any of `enable`, `disable`, `resolution WxH` (or `mode WxH`),
+//! `scale N` and `position X,Y`. Outputs without a position are arranged
+//! automatically.
+//!
+//! # Example
+//! ```rust
+//! use rustwlc::output_profile::Profiles;
+//!
+//! let profiles = Profiles::parse("
+//!     profile docked {
+//!         output eDP-1 disable
+//!         output DP-1 resolution 2560x1440 scale 1 position 0,0
+//!     }
+//! ").unwrap();
+//! let connected = vec!["eDP-1".to_string(), "DP-1".to_string()];
+//! let (profile, _) = profiles.find_match(&connected).unwrap();
+//! assert_eq!(profile.name, Some("docked".to_string()));
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::WlcOutput;
+use super::types::{Point, Size};
+use super::output_layout::{OutputLayout, Placement};
+
+/// Which outputs an `OutputConfig` applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Criteria {
+    /// The output with this name, as returned by `WlcOutput::get_name`
+    Name(String),
+    /// Any output not claimed by a named entry
+    Any
+}
+
+impl Criteria {
+    fn matches(&self, name: &str) -> bool {
+        match *self {
+            Criteria::Name(ref other) => other == name,
+            Criteria::Any => true
+        }
+    }
+}
+
+/// Settings for one output in a profile.
+///
+/// Fields left as `None` are not changed when the profile is applied,
+/// except `enabled`: outputs are woken unless it is `Some(false)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputConfig {
+    /// Which output the settings apply to
+    pub criteria: Criteria,
+    /// Whether the output is awake (`true`, the default) or sleeping (`false`)
+    pub enabled: Option<bool>,
+    /// Resolution to set
+    pub resolution: Option<Size>,
+    /// Scale to set
+    pub scale: Option<u32>,
+    /// Position in the output layout
+    pub position: Option<Point>
+}
+
+impl OutputConfig {
+    /// Creates an empty config for outputs matching the criteria.
+    pub fn new(criteria: Criteria) -> OutputConfig {
+        OutputConfig {
+            criteria,
+            enabled: None,
+            resolution: None,
+            scale: None,
+            position: None
+        }
+    }
+
+    /// Applies the config to an output and places it in the layout.
+    pub fn apply(&self, output: WlcOutput, layout: &mut OutputLayout) {
+        if self.enabled == Some(false) {
+            output.set_sleep(true);
+            layout.remove(output);
+            return
+        }
+        // Like kanshi, listed outputs are enabled unless they say otherwise
+        output.set_sleep(false);
+        if self.resolution.is_some() || self.scale.is_some() {
+            // A scale alone needs the current mode, which a new output may lack
+            if let Some(size) = self.resolution.or_else(|| output.get_resolution()) {
+                let scale = self.scale.unwrap_or_else(|| output.get_scale());
+                output.set_resolution(size, scale);
+            }
+        }
+        let placement = match self.position {
+            Some(point) => Placement::Manual(point),
+            None => Placement::Auto
+        };
+        layout.add_placed(output, placement);
+    }
+}
+
+/// A named arrangement of outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Profile {
+    /// Name of the profile, if it was given one
+    pub name: Option<String>,
+    /// Settings for each output the profile expects
+    pub outputs: Vec<OutputConfig>
+}
+
+impl Profile {
+    /// Matches the profile against connected output names.
+    ///
+    /// Returns the index of the `OutputConfig` used for each name, in the
+    /// same order as `names`, or `None` if the profile does not match.
+    pub fn match_outputs<S: AsRef<str>>(&self, names: &[S]) -> Option<Vec<usize>> {
+        if names.len() != self.outputs.len() {
+            return None
+        }
+        let mut assigned: Vec<Option<usize>> = vec![None; names.len()];
+        // Named entries first so that * does not steal their outputs
+        let named = self.outputs.iter().enumerate()
+            .filter(|&(_, config)| config.criteria != Criteria::Any);
+        let wildcards = self.outputs.iter().enumerate()
+            .filter(|&(_, config)| config.criteria == Criteria::Any);
+        for (config_index, config) in named.chain(wildcards) {
+            let found = names.iter().enumerate().position(|(index, name)| {
+                assigned[index].is_none() && config.criteria.matches(name.as_ref())
+            });
+            match found {
+                Some(index) => assigned[index] = Some(config_index),
+                None => return None
+            }
+        }
+        assigned.into_iter().collect()
+    }
+}
+
+/// An error from parsing a profile config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line the error is on, starting from 1
+    pub line: usize,
+    /// What went wrong
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// An error from loading a profile config file.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file contents were invalid
+    Parse(ParseError)
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref err) => write!(f, "Could not read profiles: {}", err),
+            LoadError::Parse(ref err) => write!(f, "Invalid profiles: {}", err)
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn description(&self) -> &str {
+        "Could not load output profiles"
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> LoadError {
+        LoadError::Io(err)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(err: ParseError) -> LoadError {
+        LoadError::Parse(err)
+    }
+}
+
+/// A list of profiles, tried in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profiles {
+    /// The profiles, the first match is used
+    pub profiles: Vec<Profile>,
+    active: Option<usize>
+}
+
+impl Profiles {
+    /// Creates a set of profiles.
+    pub fn new(profiles: Vec<Profile>) -> Profiles {
+        Profiles { profiles, active: None }
+    }
+
+    /// Reads profiles from a config file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Profiles, LoadError> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        Ok(Profiles::parse(&contents)?)
+    }
+
+    /// Parses profiles from the config format described in the module docs.
+    pub fn parse(config: &str) -> Result<Profiles, ParseError> {
+        let mut profiles = Vec::new();
+        let mut current: Option<Profile> = None;
+        for (index, line) in config.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| ParseError { line: line_number, message };
+            let line = line.split('#').next().unwrap_or("");
+            let spaced = line.replace('{', " { ").replace('}', " } ");
+            let mut words = spaced.split_whitespace().peekable();
+            while let Some(word) = words.next() {
+                match word {
+                    "profile" => {
+                        if current.is_some() {
+                            return Err(error("Profiles can not be nested".into()))
+                        }
+                        let name = match words.next() {
+                            Some("{") => None,
+                            Some(name) => {
+                                if words.next() != Some("{") {
+                                    return Err(error("Expected { after profile name".into()))
+                                }
+                                Some(name.to_string())
+                            },
+                            None => return Err(error("Expected { after profile".into()))
+                        };
+                        current = Some(Profile { name, outputs: Vec::new() });
+                    },
+                    "}" => {
+                        match current.take() {
+                            Some(profile) => profiles.push(profile),
+                            None => return Err(error("Unexpected }".into()))
+                        }
+                    },
+                    "output" => {
+                        let profile = match current.as_mut() {
+                            Some(profile) => profile,
+                            None => return Err(error("output must be inside a profile".into()))
+                        };
+                        let criteria = match words.next() {
+                            Some("*") => Criteria::Any,
+                            Some("}") | None => {
+                                return Err(error("Expected an output name".into()))
+                            },
+                            Some(name) => Criteria::Name(name.to_string())
+                        };
+                        let mut config = OutputConfig::new(criteria);
+                        while let Some(&option) = words.peek() {
+                            if option == "}" {
+                                break
+                            }
+                            words.next();
+                            match option {
+                                "enable" => config.enabled = Some(true),
+                                "disable" => config.enabled = Some(false),
+                                "resolution" | "mode" => {
+                                    let value = words.next().unwrap_or("");
+                                    config.resolution = Some(parse_size(value).map_err(&error)?);
+                                },
+                                "scale" => {
+                                    let value = words.next().unwrap_or("");
+                                    let scale = value.parse::<u32>().ok()
+                                        .filter(|&scale| scale > 0)
+                                        .ok_or_else(|| error(format!("Invalid scale '{}'", value)))?;
+                                    config.scale = Some(scale);
+                                },
+                                "position" => {
+                                    let value = words.next().unwrap_or("");
+                                    config.position = Some(parse_point(value).map_err(&error)?);
+                                },
+                                other => {
+                                    return Err(error(format!("Unknown output option '{}'", other)))
+                                }
+                            }
+                        }
+                        profile.outputs.push(config);
+                    },
+                    other => return Err(error(format!("Unexpected '{}'", other)))
+                }
+            }
+        }
+        if current.is_some() {
+            let line = config.lines().count();
+            return Err(ParseError { line, message: "Missing } at end of profile".into() })
+        }
+        Ok(Profiles::new(profiles))
+    }
+
+    /// Finds the first profile matching the connected output names.
+    ///
+    /// Returns the profile and the index of the `OutputConfig` used for
+    /// each name.
+    pub fn find_match<S: AsRef<str>>(&self, names: &[S]) -> Option<(&Profile, Vec<usize>)> {
+        self.find_match_index(names)
+            .map(|(index, configs)| (&self.profiles[index], configs))
+    }
+
+    /// The profile applied by the last call to `evaluate`, if any matched.
+    pub fn active(&self) -> Option<&Profile> {
+        self.active.map(|index| &self.profiles[index])
+    }
+
+    /// Applies the profile matching the given outputs.
+    ///
+    /// Returns the profile that was applied. If no profile matches, nothing
+    /// is changed and `None` is returned.
+    pub fn evaluate(&mut self, outputs: &[WlcOutput], layout: &mut OutputLayout)
+                    -> Option<&Profile> {
+        let names: Vec<String> = outputs.iter().map(|output| output.get_name()).collect();
+        self.active = None;
+        let (index, configs) = self.find_match_index(&names)?;
+        for (&output, config_index) in outputs.iter().zip(configs) {
+            self.profiles[index].outputs[config_index].apply(output, layout);
+        }
+        self.active = Some(index);
+        Some(&self.profiles[index])
+    }
+
+    /// Re-evaluates the profiles after an output was added.
+    ///
+    /// Call this from the `output_created` callback.
+    pub fn output_created(&mut self, output: WlcOutput, layout: &mut OutputLayout)
+                          -> Option<&Profile> {
+        let mut outputs = WlcOutput::list();
+        if !outputs.contains(&output) {
+            outputs.push(output);
+        }
+        self.evaluate(&outputs, layout)
+    }
+
+    /// Re-evaluates the profiles after an output was removed.
+    ///
+    /// Call this from the `output_destroyed` callback.
+    pub fn output_destroyed(&mut self, output: WlcOutput, layout: &mut OutputLayout)
+                            -> Option<&Profile> {
+        layout.remove(output);
+        let outputs: Vec<WlcOutput> = WlcOutput::list().into_iter()
+            .filter(|&other| other != output)
+            .collect();
+        self.evaluate(&outputs, layout)
+    }
+
+    fn find_match_index<S: AsRef<str>>(&self, names: &[S]) -> Option<(usize, Vec<usize>)> {
+        self.profiles.iter().enumerate()
+            .filter_map(|(index, profile)| {
+                profile.match_outputs(names).map(|configs| (index, configs))
+            })
+            .next()
+    }
+}
+
+fn parse_size(value: &str) -> Result<Size, String> {
+    // Refresh rates are accepted for kanshi compatibility but ignored
+    let value = value.split('@').next().unwrap_or("");
+    let mut parts = value.splitn(2, 'x');
+    match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+        (Some(Ok(w)), Some(Ok(h))) => Ok(Size::new(w, h)),
+        _ => Err(format!("Invalid resolution '{}', expected WxH", value))
+    }
+}
+
+fn parse_point(value: &str) -> Result<Point, String> {
+    let mut parts = value.splitn(2, ',');
+    match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+        (Some(Ok(x)), Some(Ok(y))) => Ok(Point::new(x, y)),
+        _ => Err(format!("Invalid position '{}', expected X,Y", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::{Point, Size};
+
+    const CONFIG: &str = "
+        # Just the laptop
+        profile laptop {
+            output eDP-1 resolution 1920x1080@60 scale 2
+        }
+
+        profile docked {
+            output eDP-1 disable
+            output DP-1 mode 2560x1440 position 0,0
+            output * position 2560,-10
+        }
+
+        profile{
+            output * enable }
+    ";
+
+    #[test]
+    fn parse_config() {
+        let profiles = Profiles::parse(CONFIG).unwrap();
+        assert_eq!(profiles.profiles.len(), 3);
+        let laptop = &profiles.profiles[0];
+        assert_eq!(laptop.name, Some("laptop".to_string()));
+        assert_eq!(laptop.outputs[0].criteria, Criteria::Name("eDP-1".into()));
+        assert_eq!(laptop.outputs[0].resolution, Some(Size::new(1920, 1080)));
+        assert_eq!(laptop.outputs[0].scale, Some(2));
+        let docked = &profiles.profiles[1];
+        assert_eq!(docked.outputs[0].enabled, Some(false));
+        assert_eq!(docked.outputs[2].criteria, Criteria::Any);
+        assert_eq!(docked.outputs[2].position, Some(Point::new(2560, -10)));
+        assert_eq!(profiles.profiles[2].name, None);
+        assert_eq!(profiles.profiles[2].outputs[0].enabled, Some(true));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let line = |config: &str| Profiles::parse(config).unwrap_err().line;
+        assert_eq!(line("output eDP-1"), 1);
+        assert_eq!(line("profile {\n output eDP-1 scale 0\n}"), 2);
+        assert_eq!(line("profile {\n output eDP-1 resolution 10\n}"), 2);
+        assert_eq!(line("profile {\n output eDP-1 position 1;2\n}"), 2);
+        assert_eq!(line("profile {\n output eDP-1 frobnicate\n}"), 2);
+        assert_eq!(line("profile {\n profile {"), 2);
+        assert_eq!(line("}"), 1);
+        assert_eq!(line("profile a {\n output a\n"), 2);
+        assert!(Profiles::parse("").unwrap().profiles.is_empty());
+    }
+
+    #[test]
+    fn matching() {
+        let profiles = Profiles::parse(CONFIG).unwrap();
+        let name = |names: &[&str]| {
+            profiles.find_match(names).map(|(profile, configs)| (profile.name.clone(), configs))
+        };
+        assert_eq!(name(&["eDP-1"]), Some((Some("laptop".into()), vec![0])));
+        // The wildcard gets whatever is left over, whatever the order
+        assert_eq!(name(&["HDMI-1", "eDP-1", "DP-1"]), Some((Some("docked".into()), vec![2, 0, 1])));
+        // Named entries must all be present
+        assert_eq!(name(&["HDMI-1", "eDP-1", "DP-2"]), None);
+        assert_eq!(name(&["HDMI-1"]), Some((None, vec![0])));
+        assert_eq!(name(&[]), None);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn switching_profiles() {
+        let mut profiles = Profiles::parse("
+            profile docked {
+                output eDP-1 disable
+                output DP-1 position 0,0
+            }
+            profile laptop {
+                output eDP-1 position 0,0
+            }
+        ").unwrap();
+        let mut layout = OutputLayout::new();
+        let (laptop, monitor) = unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2)) };
+        laptop.set_dummy_name("eDP-1");
+        monitor.set_dummy_name("DP-1");
+
+        profiles.evaluate(&[laptop, monitor], &mut layout).unwrap();
+        assert!(laptop.get_sleep());
+        assert_eq!(layout.outputs(), vec![monitor]);
+
+        // Unplugging the monitor wakes the laptop screen without `enable`
+        let profile = profiles.evaluate(&[laptop], &mut layout).unwrap();
+        assert_eq!(profile.name, Some("laptop".into()));
+        assert!(!laptop.get_sleep());
+        assert_eq!(layout.position_of(laptop), Some(Point::new(0, 0)));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn scale_only() {
+        let profiles = Profiles::parse("profile { output * scale 2 }").unwrap();
+        let config = &profiles.profiles[0].outputs[0];
+        let mut layout = OutputLayout::new();
+        let output = unsafe { WlcOutput::dummy(1) };
+
+        // Without a mode there is nothing to scale
+        config.apply(output, &mut layout);
+        assert_eq!(output.get_resolution(), None);
+        assert_eq!(output.get_scale(), 1);
+
+        output.set_resolution(Size::new(2560, 1440), 1);
+        config.apply(output, &mut layout);
+        assert_eq!(output.get_resolution(), Some(Size::new(2560, 1440)));
+        assert_eq!(output.get_virtual_resolution(), Some(Size::new(1280, 720)));
+        assert_eq!(output.get_scale(), 2);
+    }
+}
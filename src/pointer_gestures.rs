@@ -0,0 +1,447 @@
+//! Clicks, drags and long-presses from pointer buttons.
+//!
+//! The `pointer_button` callback only reports presses and releases.
+//! `PointerGestures` turns them, with pointer motion, into higher-level
+//! gestures:
+//!
+//! * A release soon after its press, without moving far, is a `Click`.
+//!   Clicks in quick succession close to each other count up, so the
+//!   second is a double click and the third a triple click. Every click is
+//!   reported, so a double click comes after a single one.
+//! * Moving further than a threshold with a button held starts a drag,
+//!   reported as `DragStart`, `Drag` for each motion, and `DragEnd` on
+//!   release.
+//! * Holding a button still for long enough is a `LongPress`. The release
+//!   after it is not a click.
+//!
+//! Times are the `time` stamps of the callbacks, in milliseconds. A button
+//! held without the pointer moving gets no more events from wlc, so
+//! long-presses are found by calling `poll`, such as from an event loop
+//! `Timer` armed for `long_press_delay` milliseconds after a press.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::*;
+//! use rustwlc::decorations::{Decorations, DecorationStyle, Region};
+//! use rustwlc::pointer_gestures::{Gesture, PointerGestures};
+//!
+//! lazy_static! {
+//!     static ref GESTURES: Mutex<PointerGestures> = Mutex::new(PointerGestures::new());
+//!     static ref DECORATIONS: Mutex<Decorations> =
+//!         Mutex::new(Decorations::new(DecorationStyle::default()));
+//! }
+//!
+//! extern fn pointer_button(view: WlcView, time: u32, _mods: &KeyboardModifiers,
+//!                          button: u32, state: ButtonState, point: &Point) -> bool {
+//!     let gestures = GESTURES.lock().unwrap().pointer_button(view, time, button, state, *point);
+//!     for gesture in gestures {
+//!         // Double-clicking a title bar maximizes the view
+//!         if let Gesture::Click { view, point, count: 2, .. } = gesture {
+//!             let geometry = view.get_visible_geometry();
+//!             if DECORATIONS.lock().unwrap().hit_test(geometry, point) == Some(Region::Title) {
+//!                 let maximized = view.get_state().contains(VIEW_MAXIMIZED);
+//!                 view.set_state(VIEW_MAXIMIZED, !maximized);
+//!             }
+//!         }
+//!     }
+//!     false
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use super::WlcView;
+use super::types::{ButtonState, Point};
+
+/// Thresholds for recognizing gestures.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GestureSettings {
+    /// Longest time a button may be held for a click, and from one press
+    /// to the next for them to count as a double or triple click, in
+    /// milliseconds
+    pub click_time: u32,
+    /// Furthest one press may be from the previous one for them to count
+    /// as a double or triple click, in pixels
+    pub click_distance: u32,
+    /// How far the pointer moves with a button held before it is a drag,
+    /// in pixels
+    pub drag_threshold: u32,
+    /// How long a button is held still before it is a long-press, in
+    /// milliseconds. 0 turns long-presses off.
+    pub long_press_time: u32,
+    /// Most clicks counted in a row before counting starts at one again
+    pub max_clicks: u32
+}
+
+impl Default for GestureSettings {
+    fn default() -> GestureSettings {
+        GestureSettings {
+            click_time: 400,
+            click_distance: 4,
+            drag_threshold: 6,
+            long_press_time: 600,
+            max_clicks: 3
+        }
+    }
+}
+
+/// A recognized gesture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Gesture {
+    /// A button was pressed and released without dragging or holding it
+    Click {
+        /// The view pressed on
+        view: WlcView,
+        /// The button
+        button: u32,
+        /// Where it was pressed
+        point: Point,
+        /// Clicks in a row: 1 for a single click, 2 for a double click
+        count: u32
+    },
+    /// The pointer moved far enough with a button held to start a drag
+    DragStart {
+        /// The view pressed on
+        view: WlcView,
+        /// The button
+        button: u32,
+        /// Where it was pressed
+        start: Point,
+        /// Where the pointer is
+        point: Point
+    },
+    /// The pointer moved during a drag
+    Drag {
+        /// The view pressed on
+        view: WlcView,
+        /// The button
+        button: u32,
+        /// Where it was pressed
+        start: Point,
+        /// Where the pointer is
+        point: Point
+    },
+    /// The button of a drag was released
+    DragEnd {
+        /// The view pressed on
+        view: WlcView,
+        /// The button
+        button: u32,
+        /// Where it was pressed
+        start: Point,
+        /// Where it was released
+        point: Point
+    },
+    /// A button was held still for long enough
+    LongPress {
+        /// The view pressed on
+        view: WlcView,
+        /// The button
+        button: u32,
+        /// Where it was pressed
+        point: Point
+    }
+}
+
+/// A button being held.
+#[derive(Debug, Copy, Clone)]
+struct Press {
+    view: WlcView,
+    time: u32,
+    instant: Instant,
+    start: Point,
+    count: u32,
+    dragging: bool,
+    long_pressed: bool
+}
+
+/// The last press counted towards a run of clicks.
+#[derive(Debug, Copy, Clone)]
+struct LastClick {
+    button: u32,
+    time: u32,
+    point: Point,
+    count: u32
+}
+
+/// Recognizes gestures from pointer buttons and motion.
+#[derive(Debug, Clone, Default)]
+pub struct PointerGestures {
+    /// Thresholds for the gestures
+    pub settings: GestureSettings,
+    pressed: BTreeMap<u32, Press>,
+    last_click: Option<LastClick>
+}
+
+impl PointerGestures {
+    /// Makes a recognizer with the default settings.
+    pub fn new() -> PointerGestures {
+        PointerGestures::default()
+    }
+
+    /// Makes a recognizer with some settings.
+    pub fn with_settings(settings: GestureSettings) -> PointerGestures {
+        PointerGestures { settings, .. PointerGestures::default() }
+    }
+
+    /// Whether a button is held.
+    pub fn is_pressed(&self, button: u32) -> bool {
+        self.pressed.contains_key(&button)
+    }
+
+    /// Whether a button is being dragged.
+    pub fn is_dragging(&self, button: u32) -> bool {
+        matches!(self.pressed.get(&button), Some(press) if press.dragging)
+    }
+
+    /// Handles a press or release. Call from the `pointer_button` callback.
+    pub fn pointer_button(&mut self, view: WlcView, time: u32, button: u32, state: ButtonState,
+                          point: Point) -> Vec<Gesture> {
+        match state {
+            ButtonState::Pressed => {
+                self.press(view, time, button, point);
+                Vec::new()
+            },
+            ButtonState::Released => self.release(time, button, point)
+        }
+    }
+
+    fn press(&mut self, view: WlcView, time: u32, button: u32, point: Point) {
+        let settings = self.settings;
+        let count = match self.last_click {
+            Some(last) if last.button == button
+                && time.wrapping_sub(last.time) <= settings.click_time
+                && distance(last.point, point) <= settings.click_distance as f64
+                && last.count < settings.max_clicks => last.count + 1,
+            _ => 1
+        };
+        self.last_click = Some(LastClick { button, time, point, count });
+        self.pressed.insert(button, Press {
+            view,
+            time,
+            instant: Instant::now(),
+            start: point,
+            count,
+            dragging: false,
+            long_pressed: false
+        });
+    }
+
+    fn release(&mut self, time: u32, button: u32, point: Point) -> Vec<Gesture> {
+        let mut gestures = self.poll_at(time);
+        let press = match self.pressed.remove(&button) {
+            Some(press) => press,
+            None => return gestures
+        };
+        if !press.dragging && distance(press.start, point) > self.settings.drag_threshold as f64 {
+            // Released far away without motion in between
+            gestures.push(Gesture::DragStart {
+                view: press.view, button, start: press.start, point
+            });
+            gestures.push(Gesture::DragEnd { view: press.view, button, start: press.start, point });
+            self.last_click = None;
+        } else if press.dragging {
+            gestures.push(Gesture::DragEnd { view: press.view, button, start: press.start, point });
+        } else if !press.long_pressed {
+            if time.wrapping_sub(press.time) <= self.settings.click_time {
+                gestures.push(Gesture::Click {
+                    view: press.view, button, point: press.start, count: press.count
+                });
+            } else {
+                // Held too long for a click, without long-presses to catch it
+                self.last_click = None;
+            }
+        }
+        gestures
+    }
+
+    /// Handles motion in the same coordinates as the buttons. Call from the
+    /// `pointer_motion` callback.
+    pub fn pointer_motion(&mut self, time: u32, point: Point) -> Vec<Gesture> {
+        let mut gestures = self.poll_at(time);
+        let threshold = self.settings.drag_threshold as f64;
+        let mut started = false;
+        for (&button, press) in &mut self.pressed {
+            let (view, start) = (press.view, press.start);
+            if press.dragging {
+                gestures.push(Gesture::Drag { view, button, start, point });
+            } else if distance(start, point) > threshold {
+                press.dragging = true;
+                started = true;
+                gestures.push(Gesture::DragStart { view, button, start, point });
+            }
+        }
+        if started {
+            // A drag breaks a run of clicks
+            self.last_click = None;
+        }
+        gestures
+    }
+
+    /// Milliseconds until the next long-press is due, if a button is held.
+    pub fn long_press_delay(&self) -> Option<u32> {
+        let time = self.settings.long_press_time;
+        if time == 0 {
+            return None
+        }
+        self.pressed.values()
+            .filter(|press| !press.dragging && !press.long_pressed)
+            .map(|press| {
+                let held = press.instant.elapsed().as_millis().min(u32::MAX as u128) as u32;
+                time.saturating_sub(held)
+            })
+            .min()
+    }
+
+    /// Reports long-presses which are due, going by the time since each
+    /// press.
+    pub fn poll(&mut self) -> Vec<Gesture> {
+        let time = self.settings.long_press_time;
+        let now = self.pressed.values()
+            .map(|press| (press, press.instant.elapsed().as_millis().min(u32::MAX as u128) as u32))
+            .filter(|&(_, held)| held >= time)
+            .map(|(press, held)| press.time.wrapping_add(held))
+            .max();
+        match now {
+            Some(now) => self.poll_at(now),
+            None => Vec::new()
+        }
+    }
+
+    /// Reports long-presses which are due at a callback `time`.
+    pub fn poll_at(&mut self, time: u32) -> Vec<Gesture> {
+        let long_press_time = self.settings.long_press_time;
+        if long_press_time == 0 {
+            return Vec::new()
+        }
+        let mut gestures = Vec::new();
+        for (&button, press) in &mut self.pressed {
+            if !press.dragging && !press.long_pressed
+                && time.wrapping_sub(press.time) >= long_press_time {
+                press.long_pressed = true;
+                gestures.push(Gesture::LongPress { view: press.view, button, point: press.start });
+            }
+        }
+        if !gestures.is_empty() {
+            self.last_click = None;
+        }
+        gestures
+    }
+
+    /// Forgets presses on a destroyed view. Call from the `view_destroyed`
+    /// callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.pressed.retain(|_, press| press.view != view);
+    }
+}
+
+fn distance(from: Point, to: Point) -> f64 {
+    let dx = to.x as f64 - from.x as f64;
+    let dy = to.y as f64 - from.y as f64;
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: u32 = 0x110;
+    const VIEW: WlcView = WlcView(1);
+
+    fn click(gestures: &mut PointerGestures, time: u32, point: Point) -> Vec<Gesture> {
+        gestures.pointer_button(VIEW, time, LEFT, ButtonState::Pressed, point);
+        gestures.pointer_button(VIEW, time + 50, LEFT, ButtonState::Released, point)
+    }
+
+    #[test]
+    fn click_counting() {
+        let mut gestures = PointerGestures::new();
+        let point = Point::new(10, 10);
+        let counts: Vec<_> = [0, 200, 400, 600, 2000].iter()
+            .map(|&time| click(&mut gestures, time, point))
+            .map(|clicks| match clicks[..] {
+                [Gesture::Click { count, .. }] => count,
+                _ => panic!("not a click: {:?}", clicks)
+            })
+            .collect();
+        assert_eq!(counts, vec![1, 2, 3, 1, 1]);
+
+        // Too far from the last press
+        assert_eq!(click(&mut gestures, 2100, Point::new(20, 10)),
+                   vec![Gesture::Click { view: VIEW, button: LEFT, point: Point::new(20, 10),
+                                         count: 1 }]);
+        // Another button starts again
+        gestures.pointer_button(VIEW, 2200, 0x111, ButtonState::Pressed, Point::new(20, 10));
+        assert_eq!(gestures.pointer_button(VIEW, 2250, 0x111, ButtonState::Released,
+                                           Point::new(20, 10)),
+                   vec![Gesture::Click { view: VIEW, button: 0x111, point: Point::new(20, 10),
+                                         count: 1 }]);
+        // Releases without a press are ignored
+        assert!(gestures.pointer_button(VIEW, 2300, LEFT, ButtonState::Released, point)
+                .is_empty());
+    }
+
+    #[test]
+    fn dragging() {
+        let mut gestures = PointerGestures::new();
+        let start = Point::new(10, 10);
+        gestures.pointer_button(VIEW, 0, LEFT, ButtonState::Pressed, start);
+        assert!(gestures.pointer_motion(10, Point::new(14, 14)).is_empty());
+        assert!(!gestures.is_dragging(LEFT));
+        assert_eq!(gestures.pointer_motion(20, Point::new(20, 10)),
+                   vec![Gesture::DragStart { view: VIEW, button: LEFT, start,
+                                             point: Point::new(20, 10) }]);
+        // Coming back within the threshold is still a drag
+        assert_eq!(gestures.pointer_motion(30, start),
+                   vec![Gesture::Drag { view: VIEW, button: LEFT, start, point: start }]);
+        assert_eq!(gestures.pointer_button(VIEW, 40, LEFT, ButtonState::Released, start),
+                   vec![Gesture::DragEnd { view: VIEW, button: LEFT, start, point: start }]);
+        assert!(!gestures.is_pressed(LEFT));
+        // A drag breaks a run of clicks
+        match click(&mut gestures, 100, start)[..] {
+            [Gesture::Click { count: 1, .. }] => (),
+            ref other => panic!("not a single click: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn long_press() {
+        let mut gestures = PointerGestures::new();
+        let point = Point::new(10, 10);
+        gestures.pointer_button(VIEW, 1000, LEFT, ButtonState::Pressed, point);
+        assert!(gestures.long_press_delay().is_some());
+        assert!(gestures.poll_at(1599).is_empty());
+        assert_eq!(gestures.poll_at(1600),
+                   vec![Gesture::LongPress { view: VIEW, button: LEFT, point }]);
+        assert!(gestures.poll_at(1700).is_empty());
+        assert_eq!(gestures.long_press_delay(), None);
+        // No click after a long-press
+        assert!(gestures.pointer_button(VIEW, 1800, LEFT, ButtonState::Released, point)
+                .is_empty());
+
+        // Found on release too, and not while dragging
+        gestures.pointer_button(VIEW, 2000, LEFT, ButtonState::Pressed, point);
+        assert_eq!(gestures.pointer_button(VIEW, 3000, LEFT, ButtonState::Released, point),
+                   vec![Gesture::LongPress { view: VIEW, button: LEFT, point }]);
+        gestures.pointer_button(VIEW, 4000, LEFT, ButtonState::Pressed, point);
+        gestures.pointer_motion(4100, Point::new(30, 30));
+        assert!(gestures.poll_at(5000).is_empty());
+
+        gestures.settings.long_press_time = 0;
+        gestures.pointer_button(VIEW, 6000, LEFT, ButtonState::Pressed, point);
+        assert!(gestures.poll_at(9000).is_empty());
+        assert_eq!(gestures.long_press_delay(), None);
+        // Held past the click time is not a click either, nor starts a run
+        assert!(gestures.pointer_button(VIEW, 9000, LEFT, ButtonState::Released, point)
+                .is_empty());
+        match click(&mut gestures, 9100, point)[..] {
+            [Gesture::Click { count: 1, .. }] => (),
+            ref other => panic!("not a single click: {:?}", other)
+        }
+    }
+}
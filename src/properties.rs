@@ -0,0 +1,262 @@
+//! Tracking view properties and what they changed from.
+//!
+//! The `view_properties_changed` callback only says which properties of a
+//! view changed, not what they were before. A `PropertyTracker` caches the
+//! title, class, app id and pid of every view, and turns each change into a
+//! `PropertyChanged` event with the old and new values, so status bars and
+//! window rules can react to exact transitions such as a title changing
+//! from one document to another.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::{WlcView, ViewPropertyType};
+//! use rustwlc::properties::{Property, PropertyTracker};
+//!
+//! lazy_static! {
+//!     static ref PROPERTIES: Mutex<PropertyTracker> = Mutex::new(PropertyTracker::new());
+//! }
+//!
+//! extern fn view_created(view: WlcView) -> bool {
+//!     PROPERTIES.lock().unwrap().view_created(view);
+//!     true
+//! }
+//!
+//! extern fn view_properties_changed(view: WlcView, _mask: ViewPropertyType) {
+//!     for change in PROPERTIES.lock().unwrap().view_properties_changed(view) {
+//!         if change.property == Property::Title {
+//!             println!("Title changed from {} to {}", change.old, change.new);
+//!         }
+//!     }
+//! }
+//!
+//! extern fn view_destroyed(view: WlcView) {
+//!     PROPERTIES.lock().unwrap().view_destroyed(view);
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use libc::pid_t;
+
+use super::WlcView;
+use super::types::{ViewPropertyType, PROPERTY_APP_ID, PROPERTY_CLASS, PROPERTY_PID,
+                   PROPERTY_TITLE};
+
+/// A property of a view which can change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Property {
+    /// `get_title()`
+    Title,
+    /// `get_class()`
+    Class,
+    /// `get_app_id()`
+    AppId,
+    /// `get_pid()`
+    Pid
+}
+
+impl Property {
+    /// Every property, in the order changes are reported.
+    pub fn all() -> [Property; 4] {
+        [Property::Title, Property::Class, Property::AppId, Property::Pid]
+    }
+
+    /// The flag wlc uses for the property.
+    pub fn flag(self) -> ViewPropertyType {
+        match self {
+            Property::Title => PROPERTY_TITLE,
+            Property::Class => PROPERTY_CLASS,
+            Property::AppId => PROPERTY_APP_ID,
+            Property::Pid => PROPERTY_PID
+        }
+    }
+
+    /// The properties set in a mask.
+    pub fn in_mask(mask: ViewPropertyType) -> Vec<Property> {
+        Property::all().iter().cloned()
+            .filter(|property| mask.contains(property.flag()))
+            .collect()
+    }
+}
+
+/// The value of a property.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PropertyValue {
+    /// Value of a title, class or app id
+    Text(String),
+    /// Value of a pid
+    Pid(pid_t)
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PropertyValue::Text(ref text) => write!(f, "{}", text),
+            PropertyValue::Pid(pid) => write!(f, "{}", pid)
+        }
+    }
+}
+
+/// The properties of a view at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Properties {
+    /// Title of the view
+    pub title: String,
+    /// Class of the view
+    pub class: String,
+    /// App id of the view
+    pub app_id: String,
+    /// Pid of the view's client
+    pub pid: pid_t
+}
+
+impl Properties {
+    /// Reads the properties of a view.
+    pub fn of(view: WlcView) -> Properties {
+        Properties {
+            title: view.get_title(),
+            class: view.get_class(),
+            app_id: view.get_app_id(),
+            pid: view.get_pid()
+        }
+    }
+
+    /// The value of one property.
+    pub fn get(&self, property: Property) -> PropertyValue {
+        match property {
+            Property::Title => PropertyValue::Text(self.title.clone()),
+            Property::Class => PropertyValue::Text(self.class.clone()),
+            Property::AppId => PropertyValue::Text(self.app_id.clone()),
+            Property::Pid => PropertyValue::Pid(self.pid)
+        }
+    }
+
+    /// Events for each property which differs in `new`.
+    pub fn changes(&self, view: WlcView, new: &Properties) -> Vec<PropertyChanged> {
+        Property::all().iter()
+            .map(|&property| (property, self.get(property), new.get(property)))
+            .filter(|(_, old, new)| old != new)
+            .map(|(property, old, new)| PropertyChanged { view, property, old, new })
+            .collect()
+    }
+}
+
+/// A property of a view changed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PropertyChanged {
+    /// The view
+    pub view: WlcView,
+    /// Which property changed
+    pub property: Property,
+    /// The value before
+    pub old: PropertyValue,
+    /// The value now
+    pub new: PropertyValue
+}
+
+/// Caches the properties of views to report how they change.
+#[derive(Debug, Default)]
+pub struct PropertyTracker {
+    views: HashMap<WlcView, Properties>
+}
+
+impl PropertyTracker {
+    /// Makes a tracker with no views.
+    pub fn new() -> PropertyTracker {
+        PropertyTracker::default()
+    }
+
+    /// The cached properties of a view.
+    pub fn get(&self, view: WlcView) -> Option<&Properties> {
+        self.views.get(&view)
+    }
+
+    /// The cached title of a view.
+    pub fn title(&self, view: WlcView) -> Option<&str> {
+        self.views.get(&view).map(|properties| &*properties.title)
+    }
+
+    /// Caches the properties of a new view. Call from the `view_created`
+    /// callback.
+    pub fn view_created(&mut self, view: WlcView) {
+        self.views.insert(view, Properties::of(view));
+    }
+
+    /// Reads the properties of a view again and returns an event for each
+    /// one which changed. Call from the `view_properties_changed` callback.
+    ///
+    /// Every property is compared, not just those in the callback's mask.
+    /// A view which was not cached yet is cached without any events.
+    pub fn view_properties_changed(&mut self, view: WlcView) -> Vec<PropertyChanged> {
+        self.update(view, Properties::of(view))
+    }
+
+    /// Forgets a view. Call from the `view_destroyed` callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.views.remove(&view);
+    }
+
+    fn update(&mut self, view: WlcView, properties: Properties) -> Vec<PropertyChanged> {
+        match self.views.insert(view, properties) {
+            Some(old) => old.changes(view, &self.views[&view]),
+            None => Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(title: &str) -> Properties {
+        Properties {
+            title: title.into(),
+            class: "Editor".into(),
+            app_id: "editor".into(),
+            pid: 100
+        }
+    }
+
+    #[test]
+    fn property_flags() {
+        assert_eq!(Property::in_mask(PROPERTY_TITLE), vec![Property::Title]);
+        assert_eq!(Property::in_mask(PROPERTY_APP_ID | PROPERTY_PID),
+                   vec![Property::AppId, Property::Pid]);
+        assert!(Property::in_mask(ViewPropertyType::empty()).is_empty());
+        assert_eq!(Property::in_mask(ViewPropertyType::all()).len(), 4);
+    }
+
+    #[test]
+    fn tracking_changes() {
+        let view = WlcView(1);
+        let mut tracker = PropertyTracker::new();
+        assert!(tracker.update(view, editor("a.txt")).is_empty());
+        assert_eq!(tracker.title(view), Some("a.txt"));
+        assert!(tracker.update(view, editor("a.txt")).is_empty());
+
+        let changes = tracker.update(view, Properties { pid: 200, ..editor("b.txt") });
+        assert_eq!(changes, vec![
+            PropertyChanged {
+                view,
+                property: Property::Title,
+                old: PropertyValue::Text("a.txt".into()),
+                new: PropertyValue::Text("b.txt".into())
+            },
+            PropertyChanged {
+                view,
+                property: Property::Pid,
+                old: PropertyValue::Pid(100),
+                new: PropertyValue::Pid(200)
+            }
+        ]);
+        assert_eq!(format!("{} -> {}", changes[1].old, changes[1].new), "100 -> 200");
+
+        tracker.view_destroyed(view);
+        assert!(tracker.get(view).is_none());
+    }
+}
@@ -0,0 +1,468 @@
+//! Recording consecutive frames of an output.
+//!
+//! A `Recorder` reads the pixels of an output, or of a region of it, in the
+//! `output_render_post` callback at up to a target frame rate, and hands
+//! them to a writer thread which encodes them as a Y4M stream, raw RGBA
+//! video or numbered PNG files.
+//!
+//! Capturing must not slow down the compositor, so frames are passed to the
+//! writer through a bounded queue. When the writer falls behind, new frames
+//! are dropped rather than waited on, and counted in `RecordStats`.
+//!
+//! wlc only renders an output when something on it changes, so there may be
+//! long gaps between frames. To keep the video in real time, the last frame
+//! is repeated for every frame interval that passed without a render.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::WlcOutput;
+//! use rustwlc::screenshot::Screenshot;
+//! use rustwlc::recorder::{Format, Recorder, RecordSettings};
+//!
+//! lazy_static! {
+//!     static ref RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+//! }
+//!
+//! // Called from a key binding
+//! fn toggle_recording() {
+//!     let mut recorder = RECORDER.lock().unwrap();
+//!     match recorder.take() {
+//!         Some(recording) => {
+//!             let stats = recording.stop().expect("Could not write recording");
+//!             println!("{} frames dropped", stats.frames_dropped);
+//!         },
+//!         None => {
+//!             let target = Screenshot::output(WlcOutput::focused());
+//!             *recorder = Recorder::start(target, Format::Y4m, "/tmp/recording.y4m",
+//!                                         RecordSettings::default()).ok();
+//!         }
+//!     }
+//! }
+//!
+//! extern fn output_render_post(output: WlcOutput) {
+//!     if let Some(ref mut recorder) = *RECORDER.lock().unwrap() {
+//!         recorder.output_render_post(output);
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::WlcOutput;
+use super::types::Size;
+use super::render::{self, wlc_pixel_format};
+use super::image::{Encoding, Image, PixelFormat};
+use super::screenshot::Screenshot;
+
+/// How recorded frames are written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A YUV4MPEG2 stream with 4:2:0 chroma, playable by most video tools
+    Y4m,
+    /// Raw RGBA frames, one after another.
+    ///
+    /// The frame size is in `RecordStats::size`. To convert with ffmpeg:
+    /// `ffmpeg -f rawvideo -pix_fmt rgba -s WxH -r FPS -i file out.mkv`
+    Raw,
+    /// PNG files in a directory, named after their frame number.
+    ///
+    /// Repeated frames are not written, leaving gaps in the numbering.
+    Png
+}
+
+/// Settings for a recording.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RecordSettings {
+    /// Frames per second of the recording
+    pub fps: u32,
+    /// Number of frames that may wait for the writer before new ones are
+    /// dropped
+    pub queue_size: usize
+}
+
+impl Default for RecordSettings {
+    fn default() -> RecordSettings {
+        RecordSettings { fps: 30, queue_size: 8 }
+    }
+}
+
+/// Frame counts of a recording.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RecordStats {
+    /// Size of the recorded frames, in pixels
+    pub size: Size,
+    /// Frames read from the output
+    pub frames_captured: usize,
+    /// Frames thrown away because the writer was behind, or because the
+    /// captured size changed during the recording
+    pub frames_dropped: usize,
+    /// Frames repeated to fill intervals without a render
+    pub frames_repeated: usize,
+    /// Frames written by the writer, including repeats
+    pub frames_written: usize
+}
+
+impl Default for RecordStats {
+    fn default() -> RecordStats {
+        RecordStats {
+            size: Size::zero(),
+            frames_captured: 0,
+            frames_dropped: 0,
+            frames_repeated: 0,
+            frames_written: 0
+        }
+    }
+}
+
+/// Most frames of the video a single render can cover.
+///
+/// When nothing is rendered for longer, such as while the outputs sleep,
+/// the rest of the pause is cut from the video rather than filled with
+/// repeats.
+pub const MAX_REPEAT: u32 = 300;
+
+/// Decides which renders become frames of a fixed rate video.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FramePacer {
+    interval: Duration,
+    next: Duration
+}
+
+impl FramePacer {
+    /// Creates a pacer for `fps` frames per second, starting at time 0.
+    pub fn new(fps: u32) -> FramePacer {
+        FramePacer {
+            interval: Duration::from_secs(1) / fps.max(1),
+            next: Duration::from_secs(0)
+        }
+    }
+
+    /// Gets the time between two frames.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Reports a render at `time` since the start of the recording.
+    ///
+    /// Returns how many frames of the video the render covers: 0 if it
+    /// came too soon after the last frame, or 1 plus the number of frames
+    /// that passed without a render, at most `MAX_REPEAT`.
+    pub fn tick(&mut self, time: Duration) -> u32 {
+        if time < self.next {
+            return 0
+        }
+        let interval = self.interval.as_nanos();
+        let behind = (time - self.next).as_nanos();
+        let frames = (behind / interval).min(MAX_REPEAT as u128 - 1) as u32 + 1;
+        // The next frame is due on the first interval after this render,
+        // even when frames were cut
+        self.next = time + self.interval - Duration::from_nanos((behind % interval) as u64);
+        frames
+    }
+}
+
+struct Frame {
+    image: Image,
+    repeat: u32
+}
+
+struct Counters {
+    written: AtomicUsize,
+    mismatched: AtomicUsize
+}
+
+/// Records frames of an output on a writer thread.
+///
+/// Dropping the recorder ends the recording without waiting for the writer;
+/// use `stop` to wait for every frame to be written.
+pub struct Recorder {
+    target: Screenshot,
+    pacer: FramePacer,
+    start: Option<Instant>,
+    /// Frames covered by captures the writer had no room for
+    carried: u32,
+    stats: RecordStats,
+    counters: Arc<Counters>,
+    sender: Option<SyncSender<Frame>>,
+    writer: Option<JoinHandle<io::Result<()>>>
+}
+
+impl Recorder {
+    /// Starts recording a target to `path`.
+    ///
+    /// For `Format::Png` the path is a directory, which is created if
+    /// needed. Nothing is captured until `output_render_post` is called.
+    pub fn start<P: AsRef<Path>>(target: Screenshot, format: Format, path: P,
+                                 settings: RecordSettings) -> io::Result<Recorder> {
+        let path = path.as_ref().to_path_buf();
+        let mut sink = match format {
+            Format::Y4m => Sink::Y4m(BufWriter::new(File::create(&path)?),
+                                     settings.fps.max(1), false),
+            Format::Raw => Sink::Raw(BufWriter::new(File::create(&path)?)),
+            Format::Png => {
+                fs::create_dir_all(&path)?;
+                Sink::Png(path, 0)
+            }
+        };
+        let counters = Arc::new(Counters {
+            written: AtomicUsize::new(0),
+            mismatched: AtomicUsize::new(0)
+        });
+        let (sender, receiver) = mpsc::sync_channel::<Frame>(settings.queue_size.max(1));
+        let writer_counters = counters.clone();
+        let writer = thread::Builder::new()
+            .name("rustwlc-recorder".into())
+            .spawn(move || {
+                let mut size = None;
+                for mut frame in receiver {
+                    if *size.get_or_insert(frame.image.size) != frame.image.size {
+                        writer_counters.mismatched.fetch_add(1, Ordering::Relaxed);
+                        continue
+                    }
+                    // wlc reads rows bottom to top
+                    frame.image.flip_rows();
+                    sink.write(&frame.image, frame.repeat)?;
+                    writer_counters.written.fetch_add(frame.repeat as usize, Ordering::Relaxed);
+                }
+                sink.finish()
+            })?;
+        Ok(Recorder {
+            target,
+            pacer: FramePacer::new(settings.fps),
+            start: None,
+            carried: 0,
+            stats: RecordStats::default(),
+            counters,
+            sender: Some(sender),
+            writer: Some(writer)
+        })
+    }
+
+    /// Gets what is being recorded.
+    pub fn target(&self) -> Screenshot {
+        self.target
+    }
+
+    /// Whether the writer is still accepting frames.
+    ///
+    /// The writer stops early if it fails to write; `stop` returns the
+    /// error.
+    pub fn is_recording(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Gets the frame counts so far.
+    pub fn stats(&self) -> RecordStats {
+        let mut stats = self.stats;
+        stats.frames_dropped += self.counters.mismatched.load(Ordering::Relaxed);
+        stats.frames_written = self.counters.written.load(Ordering::Relaxed);
+        stats
+    }
+
+    /// Captures a frame if one is due.
+    ///
+    /// Call this from the `output_render_post` callback. Renders of other
+    /// outputs are ignored.
+    pub fn output_render_post(&mut self, output: WlcOutput) {
+        if output != self.target.output || self.sender.is_none() {
+            return
+        }
+        let now = Instant::now();
+        let start = *self.start.get_or_insert(now);
+        let frames = self.pacer.tick(now - start);
+        if frames == 0 {
+            return
+        }
+        let geometry = self.target.geometry();
+        if geometry.size.w == 0 || geometry.size.h == 0 {
+            return
+        }
+        let (read, data) = render::read_pixels_v2(wlc_pixel_format::WLC_RGBA8888, geometry);
+        let image = match Image::from_raw(read.size, read.size.w * 4,
+                                          PixelFormat::Rgba8888, data) {
+            Some(ref image) if image.size.w == 0 || image.size.h == 0 => return,
+            Some(image) => image,
+            None => return
+        };
+        if self.stats.size == Size::zero() {
+            self.stats.size = image.size;
+        }
+        self.stats.frames_captured += 1;
+        // Frames of captures which were dropped are covered by this one
+        let repeat = frames.saturating_add(self.carried).min(MAX_REPEAT);
+        let result = self.sender.as_ref()
+            .map(|sender| sender.try_send(Frame { image, repeat }));
+        match result {
+            Some(Ok(())) => {
+                self.stats.frames_repeated += repeat as usize - 1;
+                self.carried = 0;
+            },
+            Some(Err(TrySendError::Full(_))) => {
+                self.stats.frames_dropped += 1;
+                self.carried = repeat;
+            },
+            Some(Err(TrySendError::Disconnected(_))) => self.sender = None,
+            None => {}
+        }
+    }
+
+    /// Stops recording and waits for the queued frames to be written.
+    ///
+    /// This blocks the calling thread until the writer is done.
+    pub fn stop(mut self) -> io::Result<RecordStats> {
+        self.sender = None;
+        let result = match self.writer.take().map(|writer| writer.join()) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("recorder thread panicked")),
+            None => Ok(())
+        };
+        result.map(|_| self.stats())
+    }
+}
+
+enum Sink {
+    Y4m(BufWriter<File>, u32, bool),
+    Raw(BufWriter<File>),
+    Png(PathBuf, usize)
+}
+
+impl Sink {
+    fn write(&mut self, image: &Image, repeat: u32) -> io::Result<()> {
+        match *self {
+            Sink::Y4m(ref mut file, fps, ref mut started) => {
+                if !*started {
+                    file.write_all(y4m_header(image.size, fps).as_bytes())?;
+                    *started = true;
+                }
+                let frame = y4m_frame(image);
+                for _ in 0..repeat {
+                    file.write_all(&frame)?;
+                }
+            },
+            Sink::Raw(ref mut file) => {
+                let image = image.to_format(PixelFormat::Rgba8888);
+                for _ in 0..repeat {
+                    for y in 0..image.size.h {
+                        file.write_all(image.row(y))?;
+                    }
+                }
+            },
+            Sink::Png(ref dir, ref mut number) => {
+                let path = dir.join(format!("{:06}.png", number));
+                image.save(path, Some(Encoding::Png))?;
+                *number += repeat as usize;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Y4m(mut file, ..) | Sink::Raw(mut file) => file.flush(),
+            Sink::Png(..) => Ok(())
+        }
+    }
+}
+
+/// Gets the stream header of a Y4M video with 4:2:0 chroma.
+pub fn y4m_header(size: Size, fps: u32) -> String {
+    format!("YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg\n", size.w, size.h, fps)
+}
+
+/// Converts an image to a Y4M frame with 4:2:0 chroma, including the
+/// `FRAME` marker.
+///
+/// Uses BT.601 limited range. Each chroma sample is the average of a 2x2
+/// block of pixels.
+pub fn y4m_frame(image: &Image) -> Vec<u8> {
+    let (w, h) = (image.size.w, image.size.h);
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+    let marker = b"FRAME\n";
+    let mut frame = Vec::with_capacity(marker.len() + (w * h + 2 * cw * ch) as usize);
+    frame.extend_from_slice(marker);
+    for y in 0..h {
+        for x in 0..w {
+            let [r, g, b, _] = image.rgba(x, y);
+            let (r, g, b) = (r as i32, g as i32, b as i32);
+            frame.push((((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8);
+        }
+    }
+    let mut u_plane = Vec::with_capacity((cw * ch) as usize);
+    let mut v_plane = Vec::with_capacity((cw * ch) as usize);
+    for cy in 0..ch {
+        for cx in 0..cw {
+            let (mut r, mut g, mut b, mut count) = (0, 0, 0, 0);
+            for y in cy * 2..(cy * 2 + 2).min(h) {
+                for x in cx * 2..(cx * 2 + 2).min(w) {
+                    let [pr, pg, pb, _] = image.rgba(x, y);
+                    r += pr as i32;
+                    g += pg as i32;
+                    b += pb as i32;
+                    count += 1;
+                }
+            }
+            let (r, g, b) = (r / count, g / count, b / count);
+            u_plane.push((((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8);
+            v_plane.push((((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8);
+        }
+    }
+    frame.extend(u_plane);
+    frame.extend(v_plane);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pacing() {
+        let mut pacer = FramePacer::new(10);
+        let ms = Duration::from_millis;
+        assert_eq!(pacer.interval(), ms(100));
+        assert_eq!(pacer.tick(ms(0)), 1);
+        // Too soon after the first frame
+        assert_eq!(pacer.tick(ms(50)), 0);
+        assert_eq!(pacer.tick(ms(100)), 1);
+        // Nothing rendered for 300ms, the frames at 200 and 300 are repeats
+        assert_eq!(pacer.tick(ms(420)), 3);
+        assert_eq!(pacer.tick(ms(480)), 0);
+        assert_eq!(pacer.tick(ms(500)), 1);
+        // Long pauses are cut short, and frames stay on the same intervals
+        assert_eq!(pacer.tick(ms(3_600_050)), MAX_REPEAT);
+        assert_eq!(pacer.tick(ms(3_600_090)), 0);
+        assert_eq!(pacer.tick(ms(3_600_100)), 1);
+    }
+
+    #[test]
+    fn y4m() {
+        assert_eq!(y4m_header(Size::new(3, 3), 30),
+                   "YUV4MPEG2 W3 H3 F30:1 Ip A1:1 C420jpeg\n");
+        let mut image = Image::new(Size::new(3, 3), PixelFormat::Rgba8888);
+        for y in 0..3 {
+            for x in 0..3 {
+                image.set_pixel(x, y, [255, 255, 255, 255]);
+            }
+        }
+        image.set_pixel(2, 2, [0, 0, 0, 255]);
+        let frame = y4m_frame(&image);
+        // 9 luma samples and 2x2 samples for each chroma plane
+        assert_eq!(frame.len(), 6 + 9 + 4 + 4);
+        assert_eq!(&frame[..6], b"FRAME\n");
+        assert_eq!(frame[6], 235);
+        assert_eq!(frame[6 + 8], 16);
+        // Grey has no chroma
+        assert!(frame[15..].iter().all(|&c| c == 128));
+    }
+}
@@ -0,0 +1,333 @@
+//! A global registry of live views and outputs.
+//!
+//! Finding a view otherwise means going through `WlcOutput::list()` and
+//! `get_views()` of every output. After `enable` is called, the registry
+//! keeps every live output and view, indexed by pid, app id, class and
+//! title, up to date through the crate's own `output_created`,
+//! `output_destroyed`, `view_created`, `view_destroyed` and
+//! `view_properties_changed` hooks. Callbacks registered in `callback` are
+//! still called as usual, and can query the registry: new views are in it
+//! before the user's `view_created` runs, and destroyed ones are removed
+//! after `view_destroyed` returns.
+//!
+//! Lookups return views sorted by handle.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::WlcView;
+//! use rustwlc::registry;
+//!
+//! // Once, before running wlc
+//! registry::enable();
+//!
+//! fn close_all_terminals() {
+//!     for view in registry::views_by_class("URxvt") {
+//!         view.close();
+//!     }
+//! }
+//!
+//! fn close_client(view: WlcView) {
+//!     for view in registry::client_views(view) {
+//!         view.close();
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use libc::pid_t;
+
+use super::{WlcOutput, WlcView};
+#[cfg(not(feature = "dummy"))]
+use super::callback;
+#[cfg(feature = "dummy")]
+use super::dummy_callback as callback;
+use super::hit_test::HitTest;
+use super::properties::Properties;
+use super::types::Point;
+
+/// Views indexed by one of their properties.
+#[derive(Debug)]
+struct Index<K: Hash + Eq> {
+    views: HashMap<K, BTreeSet<WlcView>>
+}
+
+impl<K: Hash + Eq> Index<K> {
+    fn new() -> Index<K> {
+        Index { views: HashMap::new() }
+    }
+
+    fn insert(&mut self, key: K, view: WlcView) {
+        self.views.entry(key).or_default().insert(view);
+    }
+
+    fn remove(&mut self, key: &K, view: WlcView) {
+        let empty = match self.views.get_mut(key) {
+            Some(views) => { views.remove(&view); views.is_empty() },
+            None => false
+        };
+        if empty {
+            self.views.remove(key);
+        }
+    }
+
+    fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Vec<WlcView> where K: Borrow<Q> {
+        self.views.get(key).map(|views| views.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+/// Live outputs and views, and the indices of the views.
+#[derive(Debug)]
+struct Registry {
+    outputs: BTreeSet<WlcOutput>,
+    views: HashMap<WlcView, Properties>,
+    by_pid: Index<pid_t>,
+    by_app_id: Index<String>,
+    by_class: Index<String>,
+    by_title: Index<String>
+}
+
+impl Registry {
+    fn new() -> Registry {
+        Registry {
+            outputs: BTreeSet::new(),
+            views: HashMap::new(),
+            by_pid: Index::new(),
+            by_app_id: Index::new(),
+            by_class: Index::new(),
+            by_title: Index::new()
+        }
+    }
+
+    fn add_output(&mut self, output: WlcOutput) {
+        self.outputs.insert(output);
+    }
+
+    fn remove_output(&mut self, output: WlcOutput) {
+        self.outputs.remove(&output);
+    }
+
+    /// Adds a view, or updates the indices of one already added.
+    fn set_view(&mut self, view: WlcView, properties: Properties) {
+        self.remove_view(view);
+        self.by_pid.insert(properties.pid, view);
+        self.by_app_id.insert(properties.app_id.clone(), view);
+        self.by_class.insert(properties.class.clone(), view);
+        self.by_title.insert(properties.title.clone(), view);
+        self.views.insert(view, properties);
+    }
+
+    fn remove_view(&mut self, view: WlcView) {
+        if let Some(old) = self.views.remove(&view) {
+            self.by_pid.remove(&old.pid, view);
+            self.by_app_id.remove(&old.app_id, view);
+            self.by_class.remove(&old.class, view);
+            self.by_title.remove(&old.title, view);
+        }
+    }
+
+    fn views(&self) -> Vec<WlcView> {
+        let mut views: Vec<_> = self.views.keys().cloned().collect();
+        views.sort();
+        views
+    }
+}
+
+/// `None` while the registry is off.
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn with<F, R>(f: F) -> Option<R> where F: FnOnce(&mut Registry) -> R {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+    registry.as_mut().map(f)
+}
+
+/// Turns on the registry.
+///
+/// Outputs and views which already exist are added. The crate's hooks are
+/// registered with wlc; callbacks set through `callback` before or after this
+/// are called from those hooks.
+pub fn enable() {
+    let mut registry = Registry::new();
+    for output in WlcOutput::list() {
+        registry.add_output(output);
+        for view in output.get_views() {
+            registry.set_view(view, Properties::of(view));
+        }
+    }
+    *REGISTRY.lock().unwrap_or_else(|err| err.into_inner()) = Some(registry);
+    callback::install_hooks();
+}
+
+/// Turns off the registry and forgets its contents.
+pub fn disable() {
+    *REGISTRY.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Whether the registry is on.
+pub fn is_enabled() -> bool {
+    with(|_| ()).is_some()
+}
+
+// Called from the callback hooks, which dummy callbacks do not have
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn output_created(output: WlcOutput) {
+    with(|registry| registry.add_output(output));
+}
+
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn output_destroyed(output: WlcOutput) {
+    with(|registry| registry.remove_output(output));
+}
+
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn view_created(view: WlcView) {
+    if is_enabled() {
+        // Read outside the lock, so hooks never wait on wlc
+        let properties = Properties::of(view);
+        with(|registry| registry.set_view(view, properties));
+    }
+}
+
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn view_properties_changed(view: WlcView) {
+    view_created(view);
+}
+
+#[cfg_attr(feature = "dummy", allow(dead_code))]
+pub(crate) fn view_destroyed(view: WlcView) {
+    with(|registry| registry.remove_view(view));
+}
+
+/// Live outputs, sorted by handle.
+pub fn outputs() -> Vec<WlcOutput> {
+    with(|registry| registry.outputs.iter().cloned().collect()).unwrap_or_default()
+}
+
+/// Live views.
+pub fn views() -> Vec<WlcView> {
+    with(|registry| registry.views()).unwrap_or_default()
+}
+
+/// Whether a view is in the registry.
+pub fn contains(view: WlcView) -> bool {
+    with(|registry| registry.views.contains_key(&view)).unwrap_or(false)
+}
+
+/// The indexed properties of a view.
+pub fn properties(view: WlcView) -> Option<Properties> {
+    with(|registry| registry.views.get(&view).cloned()).flatten()
+}
+
+/// Views of clients with a pid.
+pub fn views_by_pid(pid: pid_t) -> Vec<WlcView> {
+    with(|registry| registry.by_pid.get(&pid)).unwrap_or_default()
+}
+
+/// Views with an app id.
+pub fn views_by_app_id(app_id: &str) -> Vec<WlcView> {
+    with(|registry| registry.by_app_id.get(app_id)).unwrap_or_default()
+}
+
+/// Views with a class.
+pub fn views_by_class(class: &str) -> Vec<WlcView> {
+    with(|registry| registry.by_class.get(class)).unwrap_or_default()
+}
+
+/// Views with a title.
+pub fn views_by_title(title: &str) -> Vec<WlcView> {
+    with(|registry| registry.by_title.get(title)).unwrap_or_default()
+}
+
+/// Views whose properties match a predicate.
+pub fn find<F>(mut predicate: F) -> Vec<WlcView> where F: FnMut(&Properties) -> bool {
+    with(|registry| {
+        let mut views: Vec<_> = registry.views.iter()
+            .filter(|&(_, properties)| predicate(properties))
+            .map(|(&view, _)| view)
+            .collect();
+        views.sort();
+        views
+    }).unwrap_or_default()
+}
+
+/// Views of the same client as `view`, including itself.
+///
+/// Clients are told apart by pid, so all X11 views share one client.
+pub fn client_views(view: WlcView) -> Vec<WlcView> {
+    match properties(view) {
+        Some(properties) => views_by_pid(properties.pid),
+        None => Vec::new()
+    }
+}
+
+/// Views whose parent is `view`.
+pub fn children(view: WlcView) -> Vec<WlcView> {
+    views().into_iter().filter(|child| child.get_parent() == view).collect()
+}
+
+/// The topmost view on an output under a point in output-local
+/// coordinates, among views visible on the output.
+///
+/// This is the view `HitTest::default()` finds, subsurfaces included.
+pub fn view_at(output: WlcOutput, point: Point) -> Option<WlcView> {
+    HitTest::default().at(output, point).map(|hit| hit.view)
+}
+
+/// The topmost view under a point, searching every output.
+///
+/// `layout` gives where outputs are in global coordinates. Views which
+/// wlc knows as visible on the output under the point are searched.
+pub fn view_at_global(layout: &::output_layout::OutputLayout, global: Point)
+                      -> Option<(WlcOutput, WlcView)> {
+    let (output, local) = layout.global_to_local(global)?;
+    view_at(output, local).map(|view| (output, view))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indices() {
+        let mut registry = Registry::new();
+        registry.set_view(WlcView(2), Properties {
+            title: "bash".into(), class: "".into(), app_id: "term".into(), pid: 10
+        });
+        registry.set_view(WlcView(1), Properties {
+            title: "vim".into(), class: "".into(), app_id: "term".into(), pid: 10
+        });
+        registry.set_view(WlcView(3), Properties {
+            title: "news".into(), class: "".into(), app_id: "browser".into(), pid: 20
+        });
+        assert_eq!(registry.views(), vec![WlcView(1), WlcView(2), WlcView(3)]);
+        assert_eq!(registry.by_pid.get(&10), vec![WlcView(1), WlcView(2)]);
+        assert_eq!(registry.by_app_id.get("browser"), vec![WlcView(3)]);
+        assert_eq!(registry.by_class.get("").len(), 3);
+
+        // Property changes move views between index entries
+        registry.set_view(WlcView(2), Properties {
+            title: "vim".into(), class: "".into(), app_id: "term".into(), pid: 10
+        });
+        assert_eq!(registry.by_title.get("vim"), vec![WlcView(1), WlcView(2)]);
+        assert!(registry.by_title.get("bash").is_empty());
+        assert!(!registry.by_title.views.contains_key("bash"));
+
+        registry.remove_view(WlcView(1));
+        registry.remove_view(WlcView(1));
+        assert_eq!(registry.by_pid.get(&10), vec![WlcView(2)]);
+        assert_eq!(registry.views(), vec![WlcView(2), WlcView(3)]);
+    }
+
+    #[test]
+    fn outputs() {
+        let mut registry = Registry::new();
+        registry.add_output(WlcOutput(5));
+        registry.add_output(WlcOutput(4));
+        registry.remove_output(WlcOutput(5));
+        assert_eq!(registry.outputs.iter().cloned().collect::<Vec<_>>(), vec![WlcOutput(4)]);
+    }
+}
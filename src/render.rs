@@ -1,8 +1,8 @@
 //! Contains definitions for wlc render functions (wlc-render.h)
 
-use libc::{c_void, uint32_t, uintptr_t};
-use std::mem;
-use super::types::{Geometry};
+use libc::{c_void, uintptr_t};
+use std::cmp;
+use super::types::{Geometry, Size};
 
 /// Number of bits per pixel (RGBA8888)
 pub const BITS_PER_PIXEL: u32 = 32;
@@ -25,18 +25,97 @@ pub enum wlc_renderer {
     WLC_NO_RENDERER
 }
 
-#[allow(missing_docs)]
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+/// Formats of surface textures, see `SurfaceTextures`
 pub enum wlc_surface_format {
+    /// One RGB texture
     SURFACE_RGB,
+    /// One RGBA texture
     SURFACE_RGBA,
+    /// One texture backed by an EGL image
     SURFACE_EGL,
+    /// A luma texture and a texture with interleaved chroma (NV12)
     SURFACE_Y_UV,
+    /// Separate luma, blue and red chroma textures (YUV420)
     SURFACE_Y_U_V,
+    /// A luma texture and a texture with packed chroma (YUYV)
     SURFACE_Y_XUXV,
 }
 
+/// Most textures a surface can have, for `SURFACE_Y_U_V`.
+pub const MAX_SURFACE_TEXTURES: usize = 3;
+
+impl wlc_surface_format {
+    /// Gets the number of textures a surface of this format has.
+    pub fn planes(self) -> usize {
+        match self {
+            wlc_surface_format::SURFACE_RGB
+                | wlc_surface_format::SURFACE_RGBA
+                | wlc_surface_format::SURFACE_EGL => 1,
+            wlc_surface_format::SURFACE_Y_UV
+                | wlc_surface_format::SURFACE_Y_XUXV => 2,
+            wlc_surface_format::SURFACE_Y_U_V => 3
+        }
+    }
+
+    /// Whether the textures hold YUV data that has to be converted to RGB
+    /// when rendering.
+    pub fn is_yuv(self) -> bool {
+        self.planes() > 1
+    }
+
+    /// Whether the surface has an alpha channel. EGL surfaces may or may
+    /// not, so they are assumed to.
+    pub fn has_alpha(self) -> bool {
+        matches!(self, wlc_surface_format::SURFACE_RGBA | wlc_surface_format::SURFACE_EGL)
+    }
+}
+
+/// The textures of a surface, as used by wlc's renderer.
+///
+/// With the GLES2 renderer these are OpenGL texture names. They are only
+/// valid for the current frame, so they should be fetched again each
+/// frame instead of kept.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct SurfaceTextures {
+    format: wlc_surface_format,
+    textures: [u32; MAX_SURFACE_TEXTURES]
+}
+
+impl SurfaceTextures {
+    /// Gets the textures of a surface from its wlc handle.
+    ///
+    /// Returns `None` if the surface is invalid. With the `wlc-wayland`
+    /// feature, `WlcResource::get_textures` can be used instead.
+    pub fn for_surface(surface: uintptr_t) -> Option<SurfaceTextures> {
+        let mut textures = [0; MAX_SURFACE_TEXTURES];
+        let mut format = wlc_surface_format::SURFACE_RGBA;
+        let valid = unsafe {
+            wlc_surface_get_textures(surface, textures.as_mut_ptr(), &mut format)
+        };
+        if !valid {
+            return None
+        }
+        Some(SurfaceTextures { format, textures })
+    }
+
+    /// Gets the format of the textures.
+    pub fn format(&self) -> wlc_surface_format {
+        self.format
+    }
+
+    /// Gets the number of textures, as given by the format.
+    pub fn planes(&self) -> usize {
+        self.format.planes()
+    }
+
+    /// Gets the texture of each plane.
+    pub fn textures(&self) -> &[u32] {
+        &self.textures[..self.planes()]
+    }
+}
+
 #[cfg_attr(feature = "static-wlc", link(name = "wlc", kind = "static"))]
 #[cfg_attr(not(feature = "static-wlc"), link(name = "wlc"))]
 extern "C" {
@@ -44,6 +123,9 @@ extern "C" {
     /// If the geometry is out of bounds, it will be automatically clamped.
     pub fn wlc_pixels_write(format: wlc_pixel_format, geometry: *const Geometry, data: *const c_void);
 
+    /// Read pixel data from output's framebuffer.
+    /// If the geometry is out of bounds, it will be automatically clamped.
+    /// The geometry actually read is written to `out_geo`.
     pub fn wlc_pixels_read(format: wlc_pixel_format,
                            geometry: *const Geometry,
                            out_geo: *mut Geometry,
@@ -67,18 +149,23 @@ extern "C" {
 
     /// Fills out_textures[] with the textures of a surface. Returns false if surface is invalid.
     /// Array must have at least 3 elements and should be refreshed at each frame.
+    /// See `SurfaceTextures` for a safe version.
     /// Note that these are not only OpenGL textures but rather render-specific.
     /// For more info what they are check the renderer's source code */
     pub fn wlc_surface_get_textures(surface: uintptr_t,
-                                out_textures: *mut uint32_t,
+                                out_textures: *mut u32,
                                 out_format: *mut wlc_surface_format) -> bool;
 
+    /// Sets the gamma ramps of an output. Each array must have `size` entries.
+    /// See `WlcOutput::set_gamma` for a safe version.
     pub fn wlc_output_set_gamma(output: uintptr_t,
                                 size: u16,
                                 red: *mut u16,
                                 green: *mut u16,
                                 blue: *mut u16);
 
+    /// Gets the number of entries in an output's gamma ramps.
+    /// See `WlcOutput::get_gamma_size` for a safe version.
     pub fn wlc_output_get_gamma_size(output: uintptr_t) -> u16 ;
 }
 
@@ -97,34 +184,76 @@ pub fn write_pixels(format: wlc_pixel_format, geometry: Geometry, data: &[u8]) {
 }
 
 /// Reads the pixels at the specified geometry
-pub fn read_pixels(format: wlc_pixel_format, mut geometry: Geometry) -> ([u8; 9], Vec<u8>) {
-    let data_size = (geometry.size.w * geometry.size.h * 4) as usize;
-    // magic response header size
+///
+/// The first 9 bytes of the data are returned separately for compatibility.
+/// wlc does not write a header, so they are simply the first pixels.
+#[deprecated(since = "0.7.0", note = "please use `read_pixels_v2`")]
+pub fn read_pixels(format: wlc_pixel_format, geometry: Geometry) -> ([u8; 9], Vec<u8>) {
+    // Callers expect the data after the "header" to be as long as the
+    // requested geometry, so pad the end with zeroes
     let header_size = 9;
-    let mut in_buf: Vec<u8> = Vec::with_capacity(header_size + data_size);
-    let in_buf_ptr = in_buf.as_mut_ptr();
-    mem::forget(in_buf);
-    let mut out_buf = unsafe {
-        let mut out_geo = Geometry::zero();
-        wlc_pixels_read(format,
-                        &mut geometry as *mut _,
-                        &mut out_geo as *mut _,
-                        in_buf_ptr as *mut c_void);
-        // TODO read the header for this information!
-        let size = header_size +
-            out_geo.size.w as usize * out_geo.size.h as usize * 4;
-        Vec::from_raw_parts(in_buf_ptr, size, size)
-    };
+    let (_, mut out_buf) = read_pixels_v2(format, geometry);
+    let padded_size = cmp::max(out_buf.len(), header_size) + header_size;
+    out_buf.resize(padded_size, 0);
     let mut header_response = [0u8; 9];
-    let response: Vec<u8> = out_buf.drain(0..9).collect();
-    header_response.copy_from_slice(response.as_slice());
+    header_response.copy_from_slice(&out_buf[..header_size]);
+    out_buf.drain(..header_size);
     (header_response, out_buf)
 }
 
+/// Reads the pixels of the current output's framebuffer at the given
+/// geometry.
+///
+/// This should be called from the `output_render_post` callback, otherwise
+/// the framebuffer may not hold a complete frame. wlc clamps the geometry
+/// to the output; the geometry that was actually read is returned along
+/// with exactly `w * h * 4` bytes of pixel data.
+///
+/// Rows are returned bottom to top, as OpenGL reads them. See the
+/// `screenshot` module for a higher level API.
+pub fn read_pixels_v2(format: wlc_pixel_format, geometry: Geometry) -> (Geometry, Vec<u8>) {
+    let data_size = geometry.size.w as usize * geometry.size.h as usize * 4;
+    let mut buffer = vec![0u8; data_size];
+    let mut out_geo = Geometry::zero();
+    unsafe {
+        wlc_pixels_read(format,
+                        &geometry as *const _,
+                        &mut out_geo as *mut _,
+                        buffer.as_mut_ptr() as *mut c_void);
+    }
+    // wlc only ever shrinks the requested geometry when clamping it to the
+    // output. Clamp it here as well, so the returned geometry always matches
+    // the data
+    out_geo.size = Size::new(cmp::min(out_geo.size.w, geometry.size.w),
+                             cmp::min(out_geo.size.h, geometry.size.h));
+    buffer.truncate(out_geo.size.w as usize * out_geo.size.h as usize * 4);
+    (out_geo, buffer)
+}
+
 /// Calculates the stride for ARGB32 encoded buffers
 pub fn calculate_stride(width: u32) -> u32 {
     // function stolen from CAIRO_STRIDE_FOR_WIDTH macro in carioint.h
     // can be found in the most recent version of the cairo source
     let stride_alignment = ::std::mem::size_of::<u32>() as u32;
-    ((BITS_PER_PIXEL * width + 7 ) / 8 + (stride_alignment - 1))  & (stride_alignment.overflowing_neg().0)
+    ((BITS_PER_PIXEL * width).div_ceil(8) + (stride_alignment - 1))  & (stride_alignment.overflowing_neg().0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surface_formats() {
+        assert_eq!(wlc_surface_format::SURFACE_RGBA.planes(), 1);
+        assert_eq!(wlc_surface_format::SURFACE_Y_UV.planes(), 2);
+        assert_eq!(wlc_surface_format::SURFACE_Y_U_V.planes(), MAX_SURFACE_TEXTURES);
+        assert_eq!(wlc_surface_format::SURFACE_Y_XUXV.planes(), 2);
+        assert!(wlc_surface_format::SURFACE_Y_XUXV.is_yuv());
+        assert!(!wlc_surface_format::SURFACE_EGL.is_yuv());
+        let textures = SurfaceTextures {
+            format: wlc_surface_format::SURFACE_Y_UV,
+            textures: [4, 5, 0]
+        };
+        assert_eq!(textures.textures(), &[4, 5]);
+    }
 }
@@ -0,0 +1,379 @@
+//! Scheduling output renders and measuring frame times.
+//!
+//! `WlcOutput::schedule_render` asks wlc for a new frame, but does not say
+//! whether one is already on its way, so callers end up asking for frames
+//! defensively. A `RenderScheduler` remembers which outputs have a frame
+//! pending and only calls `schedule_render` once per frame, however often
+//! a render is requested.
+//!
+//! The scheduler also times the frames of each output between
+//! `output_render_pre` and `output_render_post`, and can cap the frame rate
+//! so animations do not render faster than they need to. Frames held back
+//! by the cap are scheduled later with an event loop `Timer`.
+//!
+//! Like [`Timer`](../event_loop/struct.Timer.html), the scheduler is not `Send`.
+//!
+//! # Example
+//! ```no_run
+//! use std::cell::RefCell;
+//! use rustwlc::WlcOutput;
+//! use rustwlc::render_scheduler::RenderScheduler;
+//!
+//! thread_local! {
+//!     static SCHEDULER: RefCell<RenderScheduler> = RefCell::new(RenderScheduler::new());
+//! }
+//!
+//! extern fn output_render_pre(output: WlcOutput) {
+//!     SCHEDULER.with(|scheduler| scheduler.borrow_mut().output_render_pre(output));
+//! }
+//!
+//! extern fn output_render_post(output: WlcOutput) {
+//!     SCHEDULER.with(|scheduler| {
+//!         let mut scheduler = scheduler.borrow_mut();
+//!         scheduler.output_render_post(output);
+//!         if let Some(stats) = scheduler.stats(output) {
+//!             println!("{:.1} fps, {:?} per frame", stats.fps, stats.average);
+//!         }
+//!     });
+//! }
+//!
+//! // Anywhere something on the output changes
+//! fn damage(output: WlcOutput) {
+//!     SCHEDULER.with(|scheduler| scheduler.borrow_mut().schedule(output));
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::WlcOutput;
+#[cfg(not(feature = "dummy"))]
+use super::event_loop::Timer;
+#[cfg(feature = "dummy")]
+use super::dummy_event_loop::Timer;
+
+/// Number of frames kept for statistics
+const HISTORY: usize = 120;
+
+/// How long a frame which has not started counts as pending. wlc can drop
+/// render requests, so waiting for the frame forever would block the output.
+const PENDING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Frame statistics of an output.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameStats {
+    /// Frames rendered since the output was first seen
+    pub frames: u64,
+    /// Frames per second over the last second of rendering
+    pub fps: f64,
+    /// Render time of the last frame
+    pub last: Duration,
+    /// Average render time of recent frames
+    pub average: Duration,
+    /// Shortest render time of recent frames
+    pub min: Duration,
+    /// Longest render time of recent frames
+    pub max: Duration
+}
+
+/// Start and render time of recent frames.
+#[derive(Debug, Default)]
+struct FrameHistory {
+    frames: u64,
+    recent: VecDeque<(Instant, Duration)>
+}
+
+impl FrameHistory {
+    fn record(&mut self, start: Instant, end: Instant) {
+        if self.recent.len() == HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((start, end.saturating_duration_since(start)));
+        self.frames += 1;
+    }
+
+    fn last_start(&self) -> Option<Instant> {
+        self.recent.back().map(|&(start, _)| start)
+    }
+
+    fn stats(&self) -> Option<FrameStats> {
+        let &(newest, last) = self.recent.back()?;
+        let times = self.recent.iter().map(|&(_, time)| time);
+        let total: Duration = times.clone().sum();
+        // Frames which started within a second of the newest one
+        let second = Duration::from_secs(1);
+        let oldest = self.recent.iter()
+            .map(|&(start, _)| start)
+            .find(|&start| newest.duration_since(start) <= second)
+            .unwrap_or(newest);
+        let count = self.recent.iter().filter(|&&(start, _)| start >= oldest).count();
+        let span = newest.duration_since(oldest).as_secs_f64();
+        Some(FrameStats {
+            frames: self.frames,
+            fps: if span > 0.0 { (count - 1) as f64 / span } else { 0.0 },
+            last,
+            average: total / self.recent.len() as u32,
+            min: times.clone().min().unwrap_or(last),
+            max: times.max().unwrap_or(last)
+        })
+    }
+}
+
+#[derive(Default)]
+struct OutputFrames {
+    /// A frame was asked for and has not started yet, until this deadline
+    pending: Option<Instant>,
+    /// Start of the frame being rendered
+    start: Option<Instant>,
+    /// Timer for a frame held back by the frame rate cap
+    timer: Option<Timer>,
+    history: FrameHistory
+}
+
+impl OutputFrames {
+    fn is_pending(&self, output: WlcOutput, now: Instant) -> bool {
+        matches!(self.pending, Some(deadline) if now < deadline) && !output.get_sleep()
+    }
+}
+
+/// Tracks pending frames and frame times of outputs.
+#[derive(Default)]
+pub struct RenderScheduler {
+    max_fps: Option<u32>,
+    outputs: HashMap<WlcOutput, OutputFrames>
+}
+
+impl RenderScheduler {
+    /// Makes a scheduler without a frame rate cap.
+    pub fn new() -> RenderScheduler {
+        RenderScheduler::default()
+    }
+
+    /// Highest frame rate frames are scheduled at, if capped.
+    pub fn max_fps(&self) -> Option<u32> {
+        self.max_fps
+    }
+
+    /// Caps the frame rate frames are scheduled at, or removes the cap.
+    ///
+    /// Frames wlc renders on its own, such as when a client draws, are not
+    /// held back.
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.max_fps = max_fps.filter(|&fps| fps > 0);
+    }
+
+    /// Asks for a new frame of an output.
+    ///
+    /// `schedule_render` is called at most once until the frame starts. If
+    /// the frame rate is capped and the last frame was too recent, the
+    /// frame is scheduled when the interval is over. Nothing is scheduled
+    /// for a sleeping output.
+    pub fn schedule(&mut self, output: WlcOutput) {
+        let interval = self.max_fps.map(|fps| Duration::from_secs(1) / fps);
+        let frames = self.outputs.entry(output).or_default();
+        let now = Instant::now();
+        if output.get_sleep() {
+            // Sleeping outputs do not render, so there is no frame to wait for
+            frames.pending = None;
+            if let Some(ref timer) = frames.timer {
+                timer.stop();
+            }
+            return
+        }
+        if frames.is_pending(output, now) {
+            return
+        }
+        let delay = interval.and_then(|interval| {
+            frame_delay(frames.history.last_start(), now, interval)
+        });
+        frames.pending = Some(now + delay.unwrap_or_default() + PENDING_TIMEOUT);
+        if let Some(delay) = delay {
+            if frames.timer.is_none() {
+                frames.timer = Timer::new(move || {
+                    output.schedule_render();
+                    None
+                });
+            }
+            if let Some(ref timer) = frames.timer {
+                // Round up so the frame is never early
+                let ms = delay.as_micros().div_ceil(1000) as u32;
+                if timer.update(ms.max(1)) {
+                    return
+                }
+            }
+        }
+        output.schedule_render();
+    }
+
+    /// Asks for a new frame of every output.
+    pub fn schedule_all(&mut self) {
+        for output in WlcOutput::list() {
+            self.schedule(output);
+        }
+    }
+
+    /// Whether a frame of the output was asked for and has not started.
+    ///
+    /// A frame stops being pending if the output goes to sleep, or if it
+    /// has not started a second after it was due.
+    pub fn is_pending(&self, output: WlcOutput) -> bool {
+        let now = Instant::now();
+        matches!(self.outputs.get(&output), Some(frames) if frames.is_pending(output, now))
+    }
+
+    /// Outputs with a frame asked for which has not started.
+    pub fn pending_outputs(&self) -> Vec<WlcOutput> {
+        let now = Instant::now();
+        self.outputs.iter()
+            .filter(|&(&output, frames)| frames.is_pending(output, now))
+            .map(|(&output, _)| output)
+            .collect()
+    }
+
+    /// Frame statistics of an output, once it has rendered a frame.
+    pub fn stats(&self, output: WlcOutput) -> Option<FrameStats> {
+        self.outputs.get(&output)?.history.stats()
+    }
+
+    /// Starts timing a frame. Call from the `output_render_pre` callback.
+    ///
+    /// Requests made from here on are for the next frame.
+    pub fn output_render_pre(&mut self, output: WlcOutput) {
+        let frames = self.outputs.entry(output).or_default();
+        frames.pending = None;
+        frames.start = Some(Instant::now());
+        if let Some(ref timer) = frames.timer {
+            timer.stop();
+        }
+    }
+
+    /// Finishes timing a frame. Call from the `output_render_post`
+    /// callback.
+    pub fn output_render_post(&mut self, output: WlcOutput) {
+        if let Some(frames) = self.outputs.get_mut(&output) {
+            if let Some(start) = frames.start.take() {
+                frames.history.record(start, Instant::now());
+            }
+        }
+    }
+
+    /// Forgets an output. Call from the `output_destroyed` callback.
+    pub fn output_destroyed(&mut self, output: WlcOutput) {
+        self.outputs.remove(&output);
+    }
+}
+
+/// How long to wait before a frame so frames are at least `interval`
+/// apart, or `None` if it can be rendered now.
+fn frame_delay(last: Option<Instant>, now: Instant, interval: Duration) -> Option<Duration> {
+    let elapsed = now.saturating_duration_since(last?);
+    interval.checked_sub(elapsed).filter(|delay| *delay > Duration::from_millis(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn frame_stats() {
+        let mut history = FrameHistory::default();
+        assert!(history.stats().is_none());
+        let start = Instant::now();
+        // 61 frames 16ms apart, alternating 2 and 4ms to render
+        for frame in 0..61 {
+            let begin = start + ms(frame * 16);
+            history.record(begin, begin + ms(if frame % 2 == 0 { 2 } else { 4 }));
+        }
+        let stats = history.stats().unwrap();
+        assert_eq!(stats.frames, 61);
+        assert_eq!(stats.last, ms(2));
+        assert_eq!(stats.min, ms(2));
+        assert_eq!(stats.max, ms(4));
+        assert_eq!(stats.average, ms(2) + ms(2) * 30 / 61);
+        assert!((stats.fps - 62.5).abs() < 0.01, "{}", stats.fps);
+
+        // Old frames fall out of the history
+        for frame in 61..(61 + HISTORY as u64) {
+            let begin = start + ms(frame * 16);
+            history.record(begin, begin + ms(1));
+        }
+        let stats = history.stats().unwrap();
+        assert_eq!(stats.frames, 61 + HISTORY as u64);
+        assert_eq!(stats.max, ms(1));
+    }
+
+    #[test]
+    fn single_frame() {
+        let mut history = FrameHistory::default();
+        let start = Instant::now();
+        history.record(start, start + ms(5));
+        let stats = history.stats().unwrap();
+        assert_eq!(stats.fps, 0.0);
+        assert_eq!(stats.average, ms(5));
+    }
+
+    #[test]
+    fn frame_cap() {
+        let now = Instant::now();
+        assert_eq!(frame_delay(None, now, ms(16)), None);
+        assert_eq!(frame_delay(Some(now), now + ms(10), ms(16)), Some(ms(6)));
+        assert_eq!(frame_delay(Some(now), now + ms(16), ms(16)), None);
+        assert_eq!(frame_delay(Some(now), now + ms(40), ms(16)), None);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn requests_are_merged() {
+        let output = unsafe { WlcOutput::dummy(1) };
+        let mut scheduler = RenderScheduler::new();
+        scheduler.schedule(output);
+        scheduler.schedule(output);
+        assert!(scheduler.is_pending(output));
+        assert_eq!(scheduler.pending_outputs(), vec![output]);
+        assert_eq!(output.get_dummy_renders_scheduled(), 1);
+
+        scheduler.output_render_pre(output);
+        assert!(!scheduler.is_pending(output));
+        scheduler.output_render_post(output);
+        scheduler.schedule(output);
+        assert!(scheduler.is_pending(output));
+        assert_eq!(output.get_dummy_renders_scheduled(), 2);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn sleeping_outputs_are_not_pending() {
+        let output = unsafe { WlcOutput::dummy(1) };
+        let mut scheduler = RenderScheduler::new();
+        scheduler.schedule(output);
+        output.set_sleep(true);
+        assert!(!scheduler.is_pending(output));
+        scheduler.schedule(output);
+        assert_eq!(output.get_dummy_renders_scheduled(), 1);
+        output.set_sleep(false);
+        scheduler.schedule(output);
+        assert_eq!(output.get_dummy_renders_scheduled(), 2);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn capped_frames_wait_for_the_timer() {
+        use super::super::dummy_event_loop::advance;
+
+        let output = unsafe { WlcOutput::dummy(1) };
+        let mut scheduler = RenderScheduler::new();
+        scheduler.set_max_fps(Some(10));
+        scheduler.output_render_pre(output);
+        scheduler.output_render_post(output);
+        scheduler.schedule(output);
+        assert!(scheduler.is_pending(output));
+        assert_eq!(output.get_dummy_renders_scheduled(), 0);
+        assert_eq!(advance(100), 1);
+        assert_eq!(output.get_dummy_renders_scheduled(), 1);
+    }
+}
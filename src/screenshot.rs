@@ -0,0 +1,247 @@
+//! Capturing the contents of outputs and views.
+//!
+//! wlc can only read pixels from the framebuffer of the output currently
+//! being rendered, and the frame is only complete in the
+//! `output_render_post` callback. A `Screenshot` describes what to capture;
+//! `Screenshot::capture` must be called from that callback for the
+//! screenshot's output.
+//!
+//! `Screenshots` queues requests from anywhere in the compositor (such as a
+//! key binding) and fulfils them on the next frame of each output.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::WlcOutput;
+//! use rustwlc::screenshot::{Screenshot, Screenshots};
+//!
+//! lazy_static! {
+//!     static ref SCREENSHOTS: Mutex<Screenshots> = Mutex::new(Screenshots::new());
+//! }
+//!
+//! // Called from a key binding
+//! fn take_screenshot() {
+//!     let output = WlcOutput::focused();
+//!     SCREENSHOTS.lock().unwrap().request(Screenshot::output(output));
+//!     // Make sure there is a frame to capture
+//!     output.schedule_render();
+//! }
+//!
+//! extern fn output_render_post(output: WlcOutput) {
+//!     for (_, image) in SCREENSHOTS.lock().unwrap().output_render_post(output) {
+//!         if let Some(image) = image {
+//!             image.save("/tmp/screenshot.png", None).ok();
+//!         }
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use std::cmp;
+
+use super::{WlcOutput, WlcView};
+use super::types::{Geometry, Point, Size};
+use super::render::{self, wlc_pixel_format};
+use super::image::{Image, PixelFormat};
+
+/// What part of an output to capture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// The whole output
+    Output,
+    /// A region, in output-local coordinates
+    Region(Geometry),
+    /// The visible geometry of a view
+    View(WlcView)
+}
+
+/// A request to capture part of an output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Screenshot {
+    /// Output to read pixels from
+    pub output: WlcOutput,
+    /// What to read from the output
+    pub target: Target
+}
+
+impl Screenshot {
+    /// Captures a whole output.
+    pub fn output(output: WlcOutput) -> Screenshot {
+        Screenshot { output, target: Target::Output }
+    }
+
+    /// Captures a region of an output, in output-local coordinates.
+    pub fn region(output: WlcOutput, geometry: Geometry) -> Screenshot {
+        Screenshot { output, target: Target::Region(geometry) }
+    }
+
+    /// Captures the visible geometry of a view on its current output.
+    ///
+    /// Anything drawn over the view is captured too.
+    pub fn view(view: WlcView) -> Screenshot {
+        Screenshot { output: view.get_output(), target: Target::View(view) }
+    }
+
+    /// Gets the area to read, in framebuffer pixels.
+    ///
+    /// Output-local coordinates are multiplied by the output's scale.
+    pub fn geometry(&self) -> Geometry {
+        let scale = cmp::max(self.output.get_scale(), 1);
+        match self.target {
+            Target::Output => Geometry::new(Point::origin(),
+                                            self.output.get_resolution()
+                                            .unwrap_or_else(Size::zero)),
+            Target::Region(geometry) => scale_geometry(geometry, scale),
+            Target::View(view) => scale_geometry(view.get_visible_geometry(), scale)
+        }
+    }
+
+    /// Reads the pixels of the screenshot from the framebuffer.
+    ///
+    /// This must be called from the `output_render_post` callback for
+    /// `self.output`. The geometry is clamped to the output; `None` is
+    /// returned if nothing of it is on screen.
+    pub fn capture(&self) -> Option<Image> {
+        let geometry = self.geometry();
+        if geometry.size.w == 0 || geometry.size.h == 0 {
+            return None
+        }
+        let (read, data) = render::read_pixels_v2(wlc_pixel_format::WLC_RGBA8888, geometry);
+        let mut image = Image::from_raw(read.size, read.size.w * 4,
+                                        PixelFormat::Rgba8888, data)?;
+        if image.size.w == 0 || image.size.h == 0 {
+            return None
+        }
+        image.flip_rows();
+        Some(image)
+    }
+}
+
+/// Scales a geometry in logical coordinates to output pixels.
+pub(crate) fn scale_geometry(geometry: Geometry, scale: u32) -> Geometry {
+    Geometry::new(Point::new(geometry.origin.x * scale as i32,
+                             geometry.origin.y * scale as i32),
+                  Size::new(geometry.size.w * scale, geometry.size.h * scale))
+}
+
+/// A queue of screenshots waiting for their output to render.
+#[derive(Debug, Clone, Default)]
+pub struct Screenshots {
+    pending: Vec<Screenshot>
+}
+
+impl Screenshots {
+    /// Creates an empty queue.
+    pub fn new() -> Screenshots {
+        Screenshots { pending: Vec::new() }
+    }
+
+    /// Queues a screenshot for the next frame of its output.
+    ///
+    /// Call `schedule_render` on the output if nothing else will cause a
+    /// frame to be drawn.
+    pub fn request(&mut self, screenshot: Screenshot) {
+        self.pending.push(screenshot);
+    }
+
+    /// Whether any screenshots are waiting for an output.
+    pub fn is_pending(&self, output: WlcOutput) -> bool {
+        self.pending.iter().any(|shot| shot.output == output)
+    }
+
+    /// Captures every screenshot queued for this output.
+    ///
+    /// Call this from the `output_render_post` callback. Returns each
+    /// request with its image, or `None` if it could not be captured.
+    pub fn output_render_post(&mut self, output: WlcOutput)
+                              -> Vec<(Screenshot, Option<Image>)> {
+        let (ready, pending) = self.pending.drain(..)
+            .partition(|shot| shot.output == output);
+        self.pending = pending;
+        ready.into_iter()
+            .map(|shot: Screenshot| (shot, shot.capture()))
+            .collect()
+    }
+
+    /// Drops any screenshots queued for an output that went away.
+    ///
+    /// Call this from the `output_destroyed` callback.
+    pub fn output_destroyed(&mut self, output: WlcOutput) {
+        self.pending.retain(|shot| shot.output != output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "dummy")]
+    use super::super::types::EDGE_NONE;
+
+    #[test]
+    fn scaling() {
+        let geometry = Geometry::new(Point::new(-3, 4), Size::new(10, 20));
+        assert_eq!(scale_geometry(geometry, 1), geometry);
+        assert_eq!(scale_geometry(geometry, 3),
+                   Geometry::new(Point::new(-9, 12), Size::new(30, 60)));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn geometry_is_scaled() {
+        let output = unsafe { WlcOutput::dummy(1) };
+        let view = unsafe { WlcView::dummy(1) };
+        view.set_output(output);
+        view.set_geometry(EDGE_NONE, Geometry::new(Point::new(10, 20), Size::new(300, 200)));
+        let region = Screenshot::region(output, Geometry::new(Point::new(-5, 5), Size::new(50, 40)));
+
+        output.set_resolution(Size::new(1920, 1080), 1);
+        assert_eq!(Screenshot::output(output).geometry(),
+                   Geometry::new(Point::origin(), Size::new(1920, 1080)));
+        assert_eq!(region.geometry(), Geometry::new(Point::new(-5, 5), Size::new(50, 40)));
+        assert_eq!(Screenshot::view(view).geometry(),
+                   Geometry::new(Point::new(10, 20), Size::new(300, 200)));
+
+        output.set_resolution(Size::new(3840, 2160), 2);
+        assert_eq!(Screenshot::output(output).geometry(),
+                   Geometry::new(Point::origin(), Size::new(3840, 2160)));
+        assert_eq!(region.geometry(), Geometry::new(Point::new(-10, 10), Size::new(100, 80)));
+        assert_eq!(Screenshot::view(view).geometry(),
+                   Geometry::new(Point::new(20, 40), Size::new(600, 400)));
+    }
+
+    // The outputs have no resolution, so nothing is read from wlc
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn queue_is_split_per_output() {
+        let (first, second) = unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2)) };
+        let mut screenshots = Screenshots::new();
+        screenshots.request(Screenshot::output(first));
+        screenshots.request(Screenshot::output(second));
+        screenshots.request(Screenshot::region(first, Geometry::zero()));
+        assert!(screenshots.is_pending(first) && screenshots.is_pending(second));
+
+        let captured = screenshots.output_render_post(first);
+        assert_eq!(captured.iter().map(|&(shot, _)| shot).collect::<Vec<_>>(),
+                   vec![Screenshot::output(first), Screenshot::region(first, Geometry::zero())]);
+        assert!(captured.iter().all(|&(_, ref image)| image.is_none()));
+        assert!(!screenshots.is_pending(first));
+        assert!(screenshots.is_pending(second));
+        assert!(screenshots.output_render_post(first).is_empty());
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn destroyed_outputs_are_dropped() {
+        let (first, second) = unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2)) };
+        let mut screenshots = Screenshots::new();
+        screenshots.request(Screenshot::output(first));
+        screenshots.request(Screenshot::output(second));
+        screenshots.request(Screenshot::output(first));
+        screenshots.output_destroyed(first);
+        assert!(!screenshots.is_pending(first));
+        assert!(screenshots.is_pending(second));
+        assert_eq!(screenshots.output_render_post(second).len(), 1);
+    }
+}
@@ -0,0 +1,259 @@
+//! Turning scrolling into discrete steps.
+//!
+//! The `pointer_scroll` callback reports how far each axis scrolled:
+//! `amount[0]` is vertical and `amount[1]` horizontal, each only valid when
+//! `axis` has its bit set. A mouse wheel moves 10 per click, while
+//! touchpads scroll smoothly in small fractions.
+//!
+//! A `ScrollAccumulator` adds up the amounts of each axis and reports a
+//! `ScrollDirection` step each time an axis has scrolled past the step
+//! size, keeping the rest for later. Changing direction or pausing drops
+//! what was left over, so steps follow what the user is doing now. With
+//! natural scrolling on, directions are inverted, as on touchpads where
+//! content follows the fingers.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::*;
+//! use rustwlc::scroll::{ScrollAccumulator, ScrollDirection};
+//!
+//! lazy_static! {
+//!     static ref SCROLL: Mutex<ScrollAccumulator> = Mutex::new(ScrollAccumulator::new());
+//! }
+//!
+//! extern fn pointer_scroll(_view: WlcView, time: u32, mods: &KeyboardModifiers,
+//!                          axis: ScrollAxis, amount: [f64; 2]) -> bool {
+//!     if !mods.mods.contains(MOD_MOD4) {
+//!         return false
+//!     }
+//!     // Mod+scroll switches workspace
+//!     for direction in SCROLL.lock().unwrap().pointer_scroll(time, axis, amount) {
+//!         match direction {
+//!             ScrollDirection::Up => println!("Previous workspace"),
+//!             ScrollDirection::Down => println!("Next workspace"),
+//!             _ => {}
+//!         }
+//!     }
+//!     true
+//! }
+//! # fn main() {}
+//! ```
+
+use super::types::ScrollAxis;
+
+/// A direction of scrolling.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ScrollDirection {
+    /// Scrolling up, a wheel turned away from the user
+    Up,
+    /// Scrolling down, a wheel turned towards the user
+    Down,
+    /// Scrolling left
+    Left,
+    /// Scrolling right
+    Right
+}
+
+impl ScrollDirection {
+    /// The direction of an amount scrolled along an axis, or `None` when it
+    /// did not scroll. `axis` should be `Vertical` or `Horizontal`.
+    pub fn of(axis: ScrollAxis, amount: f64) -> Option<ScrollDirection> {
+        match axis {
+            _ if amount == 0.0 || amount.is_nan() => None,
+            ScrollAxis::Vertical if amount < 0.0 => Some(ScrollDirection::Up),
+            ScrollAxis::Vertical => Some(ScrollDirection::Down),
+            ScrollAxis::Horizontal if amount < 0.0 => Some(ScrollDirection::Left),
+            ScrollAxis::Horizontal => Some(ScrollDirection::Right),
+            _ => None
+        }
+    }
+
+    /// The axis scrolled along.
+    pub fn axis(self) -> ScrollAxis {
+        match self {
+            ScrollDirection::Up | ScrollDirection::Down => ScrollAxis::Vertical,
+            ScrollDirection::Left | ScrollDirection::Right => ScrollAxis::Horizontal
+        }
+    }
+
+    /// The opposite direction.
+    pub fn inverted(self) -> ScrollDirection {
+        match self {
+            ScrollDirection::Up => ScrollDirection::Down,
+            ScrollDirection::Down => ScrollDirection::Up,
+            ScrollDirection::Left => ScrollDirection::Right,
+            ScrollDirection::Right => ScrollDirection::Left
+        }
+    }
+}
+
+/// Whether an axis includes vertical scrolling.
+pub fn is_vertical(axis: ScrollAxis) -> bool {
+    axis as u32 & ScrollAxis::Vertical as u32 != 0
+}
+
+/// Whether an axis includes horizontal scrolling.
+pub fn is_horizontal(axis: ScrollAxis) -> bool {
+    axis as u32 & ScrollAxis::Horizontal as u32 != 0
+}
+
+/// How scrolling is turned into steps.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScrollSettings {
+    /// How far an axis scrolls for one step. A mouse wheel click is 10.
+    pub step: f64,
+    /// Whether to invert directions for natural scrolling
+    pub natural: bool,
+    /// How long scrolling can pause before what was left over is dropped,
+    /// in milliseconds. 0 keeps it however long the pause.
+    pub reset_time: u32
+}
+
+impl Default for ScrollSettings {
+    fn default() -> ScrollSettings {
+        ScrollSettings { step: 10.0, natural: false, reset_time: 500 }
+    }
+}
+
+/// Adds up scrolling per axis into discrete steps.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ScrollAccumulator {
+    /// How scrolling is turned into steps
+    pub settings: ScrollSettings,
+    /// Vertical and horizontal amounts not yet making up a step
+    pending: [f64; 2],
+    last_time: Option<u32>
+}
+
+impl ScrollAccumulator {
+    /// Makes an accumulator with the default settings.
+    pub fn new() -> ScrollAccumulator {
+        ScrollAccumulator::default()
+    }
+
+    /// Makes an accumulator with some settings.
+    pub fn with_settings(settings: ScrollSettings) -> ScrollAccumulator {
+        ScrollAccumulator { settings, .. ScrollAccumulator::default() }
+    }
+
+    /// Vertical and horizontal amounts scrolled which did not make up a
+    /// step yet, after natural scrolling is applied.
+    pub fn pending(&self) -> [f64; 2] {
+        self.pending
+    }
+
+    /// Drops what was left over.
+    pub fn reset(&mut self) {
+        self.pending = [0.0, 0.0];
+        self.last_time = None;
+    }
+
+    /// The vertical and horizontal amounts of a scroll event, after natural
+    /// scrolling is applied. Axes not in `axis` are 0.
+    pub fn delta(&self, axis: ScrollAxis, amount: [f64; 2]) -> [f64; 2] {
+        let sign = if self.settings.natural { -1.0 } else { 1.0 };
+        [if is_vertical(axis) { amount[0] * sign } else { 0.0 },
+         if is_horizontal(axis) { amount[1] * sign } else { 0.0 }]
+    }
+
+    /// Adds up a scroll event and returns the steps it completed, vertical
+    /// ones first. Call from the `pointer_scroll` callback.
+    pub fn pointer_scroll(&mut self, time: u32, axis: ScrollAxis, amount: [f64; 2])
+                          -> Vec<ScrollDirection> {
+        let reset_time = self.settings.reset_time;
+        let paused = matches!(self.last_time, Some(last) if time.wrapping_sub(last) > reset_time);
+        if reset_time != 0 && paused {
+            self.reset();
+        }
+        self.last_time = Some(time);
+        let delta = self.delta(axis, amount);
+        let step = self.settings.step;
+        let mut steps = Vec::new();
+        for (index, &axis) in [ScrollAxis::Vertical, ScrollAxis::Horizontal].iter().enumerate() {
+            let delta = delta[index];
+            if delta == 0.0 || !delta.is_finite() {
+                continue
+            }
+            let pending = &mut self.pending[index];
+            if *pending * delta < 0.0 {
+                // Changed direction
+                *pending = 0.0;
+            }
+            *pending += delta;
+            if step <= 0.0 {
+                steps.extend(ScrollDirection::of(axis, *pending));
+                *pending = 0.0;
+                continue
+            }
+            let count = (pending.abs() / step).floor();
+            if count >= 1.0 {
+                let direction = ScrollDirection::of(axis, *pending)
+                    .expect("a step was scrolled");
+                steps.extend((0..count as usize).map(|_| direction));
+                *pending -= pending.signum() * count * step;
+            }
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions() {
+        assert_eq!(ScrollDirection::of(ScrollAxis::Vertical, -10.0), Some(ScrollDirection::Up));
+        assert_eq!(ScrollDirection::of(ScrollAxis::Horizontal, 0.5),
+                   Some(ScrollDirection::Right));
+        assert_eq!(ScrollDirection::of(ScrollAxis::Vertical, 0.0), None);
+        assert_eq!(ScrollDirection::of(ScrollAxis::Both, 1.0), None);
+        assert_eq!(ScrollDirection::Left.inverted(), ScrollDirection::Right);
+        assert_eq!(ScrollDirection::Down.axis(), ScrollAxis::Vertical);
+        assert!(is_vertical(ScrollAxis::Both) && is_horizontal(ScrollAxis::Both));
+        assert!(!is_vertical(ScrollAxis::Horizontal) && !is_horizontal(ScrollAxis::None));
+    }
+
+    #[test]
+    fn accumulation() {
+        let mut scroll = ScrollAccumulator::new();
+        // Wheel clicks are a step each
+        assert_eq!(scroll.pointer_scroll(0, ScrollAxis::Vertical, [-10.0, 0.0]),
+                   vec![ScrollDirection::Up]);
+        assert_eq!(scroll.pointer_scroll(10, ScrollAxis::Vertical, [25.0, 3.0]),
+                   vec![ScrollDirection::Down, ScrollDirection::Down]);
+        assert_eq!(scroll.pending(), [5.0, 0.0]);
+
+        // Small amounts add up
+        assert!(scroll.pointer_scroll(20, ScrollAxis::Vertical, [4.0, 0.0]).is_empty());
+        assert_eq!(scroll.pointer_scroll(30, ScrollAxis::Both, [1.5, -12.0]),
+                   vec![ScrollDirection::Down, ScrollDirection::Left]);
+        assert_eq!(scroll.pending(), [0.5, -2.0]);
+
+        // Changing direction drops the rest
+        assert!(scroll.pointer_scroll(40, ScrollAxis::Vertical, [-9.0, 0.0]).is_empty());
+        assert_eq!(scroll.pending(), [-9.0, -2.0]);
+        // So does pausing
+        assert!(scroll.pointer_scroll(1000, ScrollAxis::Vertical, [-9.0, 0.0]).is_empty());
+        assert_eq!(scroll.pending(), [-9.0, 0.0]);
+    }
+
+    #[test]
+    fn natural_scrolling() {
+        let mut scroll = ScrollAccumulator::with_settings(ScrollSettings {
+            natural: true,
+            .. ScrollSettings::default()
+        });
+        assert_eq!(scroll.delta(ScrollAxis::Horizontal, [3.0, 4.0]), [0.0, -4.0]);
+        assert_eq!(scroll.pointer_scroll(0, ScrollAxis::Vertical, [10.0, 0.0]),
+                   vec![ScrollDirection::Up]);
+
+        // Without a step size every event is one step
+        scroll.settings.step = 0.0;
+        assert_eq!(scroll.pointer_scroll(10, ScrollAxis::Horizontal, [0.0, -0.1]),
+                   vec![ScrollDirection::Right]);
+    }
+}
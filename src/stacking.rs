@@ -0,0 +1,230 @@
+//! Stacking views in layers.
+//!
+//! wlc stacks the views of an output in one list, which `send_to_back`,
+//! `bring_to_front` and friends change one view at a time. `Stacking` keeps
+//! views in layers instead: every view belongs to a `Layer`, and views of a
+//! higher layer are always above views of a lower one, however the views
+//! inside each layer are raised and lowered.
+//!
+//! Views get a layer from their type and state unless one is set for them:
+//! popups, unmanaged and override-redirect views go in the overlay layer,
+//! splash screens above normal views, and fullscreen views in the
+//! fullscreen layer. Children are never in a lower layer than their parent,
+//! and modal children stay above their parent as in `hierarchy`.
+//!
+//! After each change the whole order is worked out again and written back
+//! with a single `set_views` call.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::WlcView;
+//! use rustwlc::stacking::{Layer, Stacking};
+//!
+//! lazy_static! {
+//!     static ref STACKING: Mutex<Stacking> = Mutex::new(Stacking::new());
+//! }
+//!
+//! extern fn view_created(view: WlcView) -> bool {
+//!     STACKING.lock().unwrap().raise(view).ok();
+//!     view.focus();
+//!     true
+//! }
+//!
+//! extern fn view_destroyed(view: WlcView) {
+//!     STACKING.lock().unwrap().view_destroyed(view);
+//! }
+//!
+//! // A panel which stays above windows
+//! fn dock(view: WlcView) {
+//!     STACKING.lock().unwrap().set_layer(view, Some(Layer::Above)).ok();
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::HashMap;
+
+use super::{WlcOutput, WlcView};
+use super::hierarchy::{self, ViewTree};
+use super::types::{ViewState, ViewType, VIEW_BIT_MODAL, VIEW_BIT_OVERRIDE_REDIRECT,
+                   VIEW_BIT_POPUP, VIEW_BIT_SPLASH, VIEW_BIT_UNMANAGED, VIEW_FULLSCREEN};
+
+/// A layer of views, from bottom to top.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// Desktop backgrounds and icons
+    Background,
+    /// Below normal views, such as desktop widgets
+    Bottom,
+    /// Normal views
+    Normal,
+    /// Above normal views, such as panels and splash screens
+    Above,
+    /// Fullscreen views
+    Fullscreen,
+    /// Above everything, such as menus, tooltips and notifications
+    Overlay
+}
+
+impl Layer {
+    /// Every layer, from bottom to top.
+    pub fn all() -> [Layer; 6] {
+        [Layer::Background, Layer::Bottom, Layer::Normal, Layer::Above,
+         Layer::Fullscreen, Layer::Overlay]
+    }
+
+    /// The layer for a view of a type and state, if none was set for it.
+    pub fn default_for(view_type: ViewType, state: ViewState) -> Layer {
+        if view_type.intersects(VIEW_BIT_POPUP | VIEW_BIT_UNMANAGED | VIEW_BIT_OVERRIDE_REDIRECT) {
+            Layer::Overlay
+        } else if state.contains(VIEW_FULLSCREEN) {
+            Layer::Fullscreen
+        } else if view_type.contains(VIEW_BIT_SPLASH) {
+            Layer::Above
+        } else {
+            Layer::Normal
+        }
+    }
+}
+
+/// Layers set for views, and the stacking of outputs by layer.
+#[derive(Debug, Default)]
+pub struct Stacking {
+    layers: HashMap<WlcView, Layer>
+}
+
+impl Stacking {
+    /// Makes a stacking with every view in its default layer.
+    pub fn new() -> Stacking {
+        Stacking::default()
+    }
+
+    /// The layer set for a view, if any.
+    pub fn layer_set(&self, view: WlcView) -> Option<Layer> {
+        self.layers.get(&view).cloned()
+    }
+
+    /// Sets the layer of a view, or with `None` puts it back in its
+    /// default layer, then restacks its output.
+    pub fn set_layer(&mut self, view: WlcView, layer: Option<Layer>) -> Result<(), &'static str> {
+        match layer {
+            Some(layer) => self.layers.insert(view, layer),
+            None => self.layers.remove(&view)
+        };
+        self.restack(view.get_output())
+    }
+
+    /// The layer a view is stacked in.
+    pub fn layer_of(&self, view: WlcView) -> Layer {
+        let mut views = view.get_output().get_views();
+        if !views.contains(&view) {
+            views.push(view);
+        }
+        self.layer_in(&ViewTree::new(&views), view)
+    }
+
+    /// The layer of a view: its own, or its parent's if that is higher.
+    fn layer_in(&self, tree: &ViewTree, view: WlcView) -> Layer {
+        let own = self.layer_set(view)
+            .unwrap_or_else(|| Layer::default_for(view.get_type(), view.get_state()));
+        match tree.parent(view) {
+            Some(parent) if !tree.ancestors(parent).contains(&view) =>
+                own.max(self.layer_in(tree, parent)),
+            _ => own
+        }
+    }
+
+    /// Views of an output in a layer, from bottom to top.
+    pub fn views_in(&self, output: WlcOutput, layer: Layer) -> Vec<WlcView> {
+        let tree = ViewTree::of_output(output);
+        tree.views().iter().cloned().filter(|&view| self.layer_in(&tree, view) == layer).collect()
+    }
+
+    /// Puts a view on top of the other views of its layer.
+    pub fn raise(&mut self, view: WlcView) -> Result<(), &'static str> {
+        let output = view.get_output();
+        let mut order = output.get_views();
+        order.retain(|&other| other != view);
+        order.push(view);
+        self.write(output, order)
+    }
+
+    /// Puts a view below the other views of its layer.
+    pub fn lower(&mut self, view: WlcView) -> Result<(), &'static str> {
+        let output = view.get_output();
+        let mut order = output.get_views();
+        order.retain(|&other| other != view);
+        order.insert(0, view);
+        self.write(output, order)
+    }
+
+    /// Sorts the views of an output by layer again, such as after a view
+    /// went fullscreen or got a parent.
+    pub fn restack(&mut self, output: WlcOutput) -> Result<(), &'static str> {
+        self.write(output, output.get_views())
+    }
+
+    /// Forgets a destroyed view. Call from the `view_destroyed` callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.layers.remove(&view);
+    }
+
+    /// Sorts an order by layer and writes it to the output if it differs.
+    fn write(&self, output: WlcOutput, order: Vec<WlcView>) -> Result<(), &'static str> {
+        let tree = ViewTree::new(&order);
+        let order = hierarchy::modals_above(&tree, |view| view.get_type().contains(VIEW_BIT_MODAL));
+        let layers: HashMap<_, _> = order.iter()
+            .map(|&view| (view, self.layer_in(&tree, view)))
+            .collect();
+        let order = sort_by_layer(&order, |view| layers[&view]);
+        if order != output.get_views() {
+            output.set_views(&order)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sorts views by layer, keeping the order of views within a layer.
+pub fn sort_by_layer<F>(order: &[WlcView], layer: F) -> Vec<WlcView>
+    where F: Fn(WlcView) -> Layer {
+    let mut sorted = order.to_vec();
+    sorted.sort_by_key(|&view| layer(view));
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{VIEW_ACTIVATED, VIEW_MAXIMIZED};
+
+    #[test]
+    fn default_layers() {
+        assert_eq!(Layer::default_for(ViewType::empty(), ViewState::empty()), Layer::Normal);
+        assert_eq!(Layer::default_for(ViewType::empty(), VIEW_MAXIMIZED | VIEW_ACTIVATED),
+                   Layer::Normal);
+        assert_eq!(Layer::default_for(VIEW_BIT_MODAL, ViewState::empty()), Layer::Normal);
+        assert_eq!(Layer::default_for(ViewType::empty(), VIEW_FULLSCREEN), Layer::Fullscreen);
+        assert_eq!(Layer::default_for(VIEW_BIT_SPLASH, ViewState::empty()), Layer::Above);
+        assert_eq!(Layer::default_for(VIEW_BIT_POPUP, VIEW_FULLSCREEN), Layer::Overlay);
+        assert_eq!(Layer::default_for(VIEW_BIT_UNMANAGED, ViewState::empty()), Layer::Overlay);
+        assert_eq!(Layer::default_for(VIEW_BIT_OVERRIDE_REDIRECT, ViewState::empty()),
+                   Layer::Overlay);
+        let all = Layer::all();
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn layer_sorting() {
+        let layers = |view: WlcView| match view.0 {
+            1 | 4 => Layer::Overlay,
+            2 => Layer::Background,
+            _ => Layer::Normal
+        };
+        let order: Vec<_> = (1..7).map(WlcView).collect();
+        assert_eq!(sort_by_layer(&order, layers),
+                   vec![WlcView(2), WlcView(3), WlcView(5), WlcView(6), WlcView(1), WlcView(4)]);
+        assert!(sort_by_layer(&[], layers).is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+//! Rendering views at any geometry, for thumbnails and overviews.
+//!
+//! wlc draws each view once, at its own geometry. A compositor can draw a
+//! view again elsewhere, such as scaled down in an exposé grid, alt-tab
+//! thumbnails or a picture-in-picture corner, by rendering its surface and
+//! subsurfaces with `wlc_surface_render` from a render callback.
+//!
+//! Clients only draw new frames when they get a frame callback. wlc sends
+//! them for the views it renders itself, so views which are hidden and only
+//! drawn here would freeze; `render_view` flushes their frame callbacks
+//! after rendering them.
+//!
+//! Reading a view's surfaces uses wlc's Wayland functions, so
+//! `SurfaceTree::of_view`, `render_view` and `render_view_fitted` require
+//! the `wlc-wayland` feature.
+//!
+//! # Example
+//! ```no_run
+//! use rustwlc::{Geometry, Point, Size, WlcOutput};
+//! use rustwlc::surface_render;
+//!
+//! // From an output_render_post callback: draw every view of the output in
+//! // a grid over the screen.
+//! # #[cfg(feature = "wlc-wayland")]
+//! fn draw_overview(output: WlcOutput) {
+//!     let area = Geometry::new(Point::origin(), output.get_resolution().unwrap());
+//!     let views = output.get_views();
+//!     for (view, cell) in views.iter().zip(surface_render::grid(views.len(), area, 16)) {
+//!         surface_render::render_view_fitted(*view, cell);
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use libc::uintptr_t;
+
+use super::types::{Geometry, Point, Size};
+use super::render::{wlc_surface_flush_frame_callbacks, wlc_surface_render};
+
+#[cfg(feature = "wlc-wayland")]
+use super::WlcView;
+#[cfg(feature = "wlc-wayland")]
+use super::wayland::{self, WlcResource};
+
+/// A surface and its subsurfaces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SurfaceTree {
+    /// wlc handle of the surface
+    pub surface: uintptr_t,
+    /// Geometry of the surface, relative to its parent's top left corner.
+    /// For the root surface the origin is always zero.
+    pub geometry: Geometry,
+    /// Subsurfaces, in the order wlc renders them
+    pub children: Vec<SurfaceTree>
+}
+
+impl SurfaceTree {
+    /// # Requires `wlc-wayland` feature
+    ///
+    /// Reads the subsurface tree of a view's surface.
+    ///
+    /// Returns `None` if the view has no surface.
+    #[cfg(feature = "wlc-wayland")]
+    pub fn of_view(view: WlcView) -> Option<SurfaceTree> {
+        let resource: WlcResource = view.into();
+        if resource.get_raw() == 0 {
+            return None
+        }
+        Some(SurfaceTree::of_resource(resource))
+    }
+
+    /// # Requires `wlc-wayland` feature
+    ///
+    /// Reads the subsurface tree of a surface.
+    #[cfg(feature = "wlc-wayland")]
+    pub fn of_resource(resource: WlcResource) -> SurfaceTree {
+        let size = unsafe {
+            let size = wayland::wlc_surface_get_size(resource.get_raw());
+            if size.is_null() { Size::zero() } else { *size }
+        };
+        let mut tree = SurfaceTree::read_children(resource);
+        tree.geometry = Geometry::new(Point::origin(), size);
+        tree
+    }
+
+    #[cfg(feature = "wlc-wayland")]
+    fn read_children(resource: WlcResource) -> SurfaceTree {
+        SurfaceTree {
+            surface: resource.get_raw(),
+            geometry: resource.get_subsurface_geometry(),
+            children: resource.get_subsurfaces().into_iter()
+                .map(SurfaceTree::read_children)
+                .collect()
+        }
+    }
+
+    /// Works out where each surface of the tree is drawn when the root is
+    /// scaled to `target`.
+    ///
+    /// Surfaces are listed parent first, in the order they should be
+    /// rendered. Subsurfaces are scaled and moved along with the root.
+    pub fn layout(&self, target: Geometry) -> Vec<(uintptr_t, Geometry)> {
+        let mut out = Vec::new();
+        if self.geometry.size.w == 0 || self.geometry.size.h == 0 {
+            return out
+        }
+        let scale_x = target.size.w as f64 / self.geometry.size.w as f64;
+        let scale_y = target.size.h as f64 / self.geometry.size.h as f64;
+        self.layout_into(target.origin, scale_x, scale_y, Some(target.size), &mut out);
+        out
+    }
+
+    fn layout_into(&self, parent: Point, scale_x: f64, scale_y: f64, size: Option<Size>,
+                   out: &mut Vec<(uintptr_t, Geometry)>) {
+        let origin = match size {
+            // The root is placed exactly at the target
+            Some(_) => parent,
+            None => Point::new(parent.x + (self.geometry.origin.x as f64 * scale_x).round() as i32,
+                               parent.y + (self.geometry.origin.y as f64 * scale_y).round() as i32)
+        };
+        let size = size.unwrap_or_else(|| {
+            Size::new((self.geometry.size.w as f64 * scale_x).round() as u32,
+                      (self.geometry.size.h as f64 * scale_y).round() as u32)
+        });
+        out.push((self.surface, Geometry::new(origin, size)));
+        for child in &self.children {
+            child.layout_into(origin, scale_x, scale_y, None, out);
+        }
+    }
+
+    /// Renders the tree scaled to `target`, in output-local coordinates.
+    ///
+    /// This must be called from a render callback of the output being
+    /// drawn to.
+    pub fn render(&self, target: Geometry) {
+        for (surface, geometry) in self.layout(target) {
+            unsafe { wlc_surface_render(surface, &geometry); }
+        }
+    }
+
+    /// Asks wlc to send frame callbacks to the surface and its subsurfaces
+    /// after the next frame, so their clients keep drawing.
+    pub fn flush_frame_callbacks(&self) {
+        unsafe { wlc_surface_flush_frame_callbacks(self.surface); }
+        for child in &self.children {
+            child.flush_frame_callbacks();
+        }
+    }
+}
+
+/// # Requires `wlc-wayland` feature
+///
+/// Renders a view and its subsurfaces stretched to `target`, then flushes
+/// their frame callbacks.
+///
+/// This must be called from a render callback of the output being drawn
+/// to, such as `output_render_post`. `target` is in output-local
+/// coordinates and may be anywhere, even if the view is on another output
+/// or hidden.
+#[cfg(feature = "wlc-wayland")]
+pub fn render_view(view: WlcView, target: Geometry) {
+    if let Some(tree) = SurfaceTree::of_view(view) {
+        tree.render(target);
+        tree.flush_frame_callbacks();
+    }
+}
+
+/// # Requires `wlc-wayland` feature
+///
+/// Renders a view as large as it fits in `area` while keeping its aspect
+/// ratio, centered. Returns the geometry it was drawn at.
+///
+/// See `render_view`.
+#[cfg(feature = "wlc-wayland")]
+pub fn render_view_fitted(view: WlcView, area: Geometry) -> Option<Geometry> {
+    let tree = SurfaceTree::of_view(view)?;
+    let target = fit(tree.geometry.size, area);
+    tree.render(target);
+    tree.flush_frame_callbacks();
+    Some(target)
+}
+
+/// Scales `size` to be as large as it fits in `area`, keeping its aspect
+/// ratio, and centers it.
+pub fn fit(size: Size, area: Geometry) -> Geometry {
+    if size.w == 0 || size.h == 0 {
+        return Geometry::new(area.origin, Size::zero())
+    }
+    let scale = (area.size.w as f64 / size.w as f64).min(area.size.h as f64 / size.h as f64);
+    let fitted = Size::new((size.w as f64 * scale).round() as u32,
+                           (size.h as f64 * scale).round() as u32);
+    Geometry::new(Point::new(area.origin.x + (area.size.w - fitted.w) as i32 / 2,
+                             area.origin.y + (area.size.h - fitted.h) as i32 / 2),
+                  fitted)
+}
+
+/// Splits an area into `count` cells of a grid with `gap` pixels between
+/// and around them, for an overview of views.
+///
+/// The grid has as many columns as rows, or one more. Cells are listed
+/// row by row.
+pub fn grid(count: usize, area: Geometry, gap: u32) -> Vec<Geometry> {
+    if count == 0 {
+        return Vec::new()
+    }
+    let columns = (count as f64).sqrt().ceil() as u32;
+    let rows = (count as u32).div_ceil(columns);
+    let width = area.size.w.saturating_sub(gap * (columns + 1)) / columns;
+    let height = area.size.h.saturating_sub(gap * (rows + 1)) / rows;
+    (0..count as u32).map(|index| {
+        let (column, row) = (index % columns, index / columns);
+        Geometry::new(Point::new(area.origin.x + (gap + column * (width + gap)) as i32,
+                                 area.origin.y + (gap + row * (height + gap)) as i32),
+                      Size::new(width, height))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_layout() {
+        let tree = SurfaceTree {
+            surface: 1,
+            geometry: Geometry::new(Point::new(0, 0), Size::new(400, 200)),
+            children: vec![SurfaceTree {
+                surface: 2,
+                geometry: Geometry::new(Point::new(100, 50), Size::new(200, 100)),
+                children: vec![SurfaceTree {
+                    surface: 3,
+                    geometry: Geometry::new(Point::new(-10, 10), Size::new(20, 20)),
+                    children: Vec::new()
+                }]
+            }]
+        };
+        let target = Geometry::new(Point::new(1000, 1000), Size::new(200, 100));
+        assert_eq!(tree.layout(target), vec![
+            (1, Geometry::new(Point::new(1000, 1000), Size::new(200, 100))),
+            (2, Geometry::new(Point::new(1050, 1025), Size::new(100, 50))),
+            (3, Geometry::new(Point::new(1045, 1030), Size::new(10, 10)))
+        ]);
+        let empty = SurfaceTree { surface: 1, geometry: Geometry::zero(), children: Vec::new() };
+        assert!(empty.layout(Geometry::new(Point::new(0, 0), Size::new(10, 10))).is_empty());
+    }
+
+    #[test]
+    fn fitting() {
+        let square = Geometry::new(Point::new(0, 0), Size::new(100, 100));
+        assert_eq!(fit(Size::new(400, 200), square),
+                   Geometry::new(Point::new(0, 25), Size::new(100, 50)));
+        let offset = Geometry::new(Point::new(10, 10), Size::new(100, 100));
+        assert_eq!(fit(Size::new(100, 200), offset),
+                   Geometry::new(Point::new(35, 10), Size::new(50, 100)));
+        assert_eq!(fit(Size::zero(), square).size, Size::zero());
+    }
+
+    #[test]
+    fn grids() {
+        assert!(grid(0, Geometry::new(Point::new(0, 0), Size::new(100, 100)), 10).is_empty());
+        let cells = grid(3, Geometry::new(Point::new(0, 0), Size::new(310, 210)), 10);
+        assert_eq!(cells, vec![Geometry::new(Point::new(10, 10), Size::new(140, 90)),
+                               Geometry::new(Point::new(160, 10), Size::new(140, 90)),
+                               Geometry::new(Point::new(10, 110), Size::new(140, 90))]);
+        assert_eq!(grid(5, Geometry::new(Point::new(0, 0), Size::new(100, 100)), 0).len(), 5);
+    }
+}
@@ -0,0 +1,636 @@
+//! Touch points and touch gestures.
+//!
+//! The `touch` callback reports each finger separately by slot. A
+//! `TouchTracker` keeps the state of every slot, and recognizes gestures
+//! from all fingers together:
+//!
+//! * Fingers put down and lifted again quickly without moving are a `Tap`,
+//!   counting how many fingers were down.
+//! * Fingers moving together are a `Swipe` in one direction.
+//! * Two or more fingers moving apart, together or around each other are a
+//!   `Pinch`, with the scale and rotation since they were put down.
+//! * A swipe which starts at a border of the output and moves inwards is an
+//!   `EdgeSwipe`, such as for opening panels and overviews.
+//!
+//! Swipes, pinches and edge swipes have a `Phase`: they begin once
+//! recognized, update on each `Frame`, and end when a finger is lifted or
+//! another one put down. The fingers still down after that start no new
+//! gesture until all of them are lifted.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::*;
+//! use rustwlc::touch::{Direction, Phase, TouchGesture, TouchTracker};
+//!
+//! lazy_static! {
+//!     static ref TOUCH: Mutex<TouchTracker> = Mutex::new(TouchTracker::new());
+//! }
+//!
+//! extern fn touch(view: WlcView, time: u32, _mods: &KeyboardModifiers,
+//!                 touch: TouchType, slot: i32, point: &Point) -> bool {
+//!     let gestures = TOUCH.lock().unwrap().touch(view, time, touch, slot, *point);
+//!     let mut handled = false;
+//!     for gesture in gestures {
+//!         // Three finger swipes switch workspace
+//!         if let TouchGesture::Swipe { phase: Phase::End, fingers: 3, direction, .. } = gesture {
+//!             match direction {
+//!                 Direction::Left => println!("Next workspace"),
+//!                 Direction::Right => println!("Previous workspace"),
+//!                 _ => {}
+//!             }
+//!             handled = true;
+//!         }
+//!     }
+//!     handled
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use super::{WlcOutput, WlcView};
+use super::types::{Point, Size, TouchType};
+
+/// Thresholds for recognizing touch gestures.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TouchSettings {
+    /// Longest time from the first finger down to the last one up for a
+    /// tap, in milliseconds
+    pub tap_time: u32,
+    /// Furthest a finger may move during a tap, in pixels
+    pub tap_distance: u32,
+    /// How far fingers move together before it is a swipe, in pixels
+    pub swipe_distance: u32,
+    /// How much the distance between fingers changes before it is a pinch,
+    /// as a fraction
+    pub pinch_scale: f64,
+    /// How far fingers turn around each other before it is a pinch, in
+    /// radians
+    pub pinch_rotation: f64,
+    /// How close to a border of the output a swipe starts to be an edge
+    /// swipe, in pixels
+    pub edge_size: u32
+}
+
+impl Default for TouchSettings {
+    fn default() -> TouchSettings {
+        TouchSettings {
+            tap_time: 300,
+            tap_distance: 10,
+            swipe_distance: 40,
+            pinch_scale: 0.15,
+            pinch_rotation: 0.3,
+            edge_size: 16
+        }
+    }
+}
+
+/// The state of one finger.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TouchPoint {
+    /// The slot of the finger
+    pub slot: i32,
+    /// The view touched, `WlcView::root()` if none
+    pub view: WlcView,
+    /// When the finger was put down
+    pub time: u32,
+    /// Where the finger was put down
+    pub start: Point,
+    /// Where the finger is
+    pub point: Point
+}
+
+/// A direction of a swipe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Towards the left
+    Left,
+    /// Towards the right
+    Right,
+    /// Upwards
+    Up,
+    /// Downwards
+    Down
+}
+
+impl Direction {
+    /// The direction of a motion, along whichever axis it moved more.
+    pub fn of(dx: f64, dy: f64) -> Direction {
+        if dx.abs() >= dy.abs() {
+            if dx < 0.0 { Direction::Left } else { Direction::Right }
+        } else if dy < 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+/// A border of an output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Edge {
+    /// The left border
+    Left,
+    /// The right border
+    Right,
+    /// The top border
+    Top,
+    /// The bottom border
+    Bottom
+}
+
+impl Edge {
+    /// The direction pointing from this border into the output.
+    pub fn inwards(self) -> Direction {
+        match self {
+            Edge::Left => Direction::Right,
+            Edge::Right => Direction::Left,
+            Edge::Top => Direction::Down,
+            Edge::Bottom => Direction::Up
+        }
+    }
+
+    /// The border of an area of `size` a point is within `distance` of.
+    /// Empty areas have no borders.
+    pub fn near(point: Point, size: Size, distance: u32) -> Option<Edge> {
+        if size.w == 0 || size.h == 0 {
+            return None
+        }
+        let distance = distance as i64;
+        let (x, y) = (point.x as i64, point.y as i64);
+        if x < distance {
+            Some(Edge::Left)
+        } else if x >= size.w as i64 - distance {
+            Some(Edge::Right)
+        } else if y < distance {
+            Some(Edge::Top)
+        } else if y >= size.h as i64 - distance {
+            Some(Edge::Bottom)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where a gesture is at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// The gesture was just recognized
+    Begin,
+    /// The fingers moved
+    Update,
+    /// The fingers were lifted or another was put down
+    End,
+    /// The touch sequence was cancelled
+    Cancel
+}
+
+/// A recognized touch gesture.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TouchGesture {
+    /// Fingers were put down and lifted without moving
+    Tap {
+        /// The view the first finger touched
+        view: WlcView,
+        /// How many fingers were down together
+        fingers: u32,
+        /// Where the first finger was put down
+        point: Point
+    },
+    /// Fingers moved together
+    Swipe {
+        /// Where the swipe is at
+        phase: Phase,
+        /// How many fingers are swiping
+        fingers: u32,
+        /// Which way the swipe started going
+        direction: Direction,
+        /// How far the fingers moved across on average
+        dx: f64,
+        /// How far the fingers moved down on average
+        dy: f64
+    },
+    /// Fingers moved apart, together or around each other
+    Pinch {
+        /// Where the pinch is at
+        phase: Phase,
+        /// How many fingers are pinching
+        fingers: u32,
+        /// How far apart the fingers are compared to when they were put
+        /// down: above 1 when spread, below 1 when pinched
+        scale: f64,
+        /// How far the fingers turned around each other, in radians,
+        /// clockwise
+        rotation: f64
+    },
+    /// Fingers moved inwards from a border of the output
+    EdgeSwipe {
+        /// Where the swipe is at
+        phase: Phase,
+        /// How many fingers are swiping
+        fingers: u32,
+        /// The border the swipe started at
+        edge: Edge,
+        /// How far inwards the fingers moved
+        distance: f64
+    }
+}
+
+/// A gesture being performed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Active {
+    Swipe(Direction),
+    Pinch,
+    Edge(Edge)
+}
+
+/// Fingers down since none were.
+#[derive(Debug, Copy, Clone)]
+struct Sequence {
+    view: WlcView,
+    time: u32,
+    start: Point,
+    area: Size,
+    fingers: u32,
+    moved: bool,
+    active: Option<Active>,
+    /// The gesture of this sequence ended, or it can no longer be a tap
+    spent: bool
+}
+
+/// Averages of how fingers moved since they were put down.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Motion {
+    dx: f64,
+    dy: f64,
+    scale: f64,
+    rotation: f64
+}
+
+/// Tracks touch points and recognizes gestures.
+#[derive(Debug, Clone, Default)]
+pub struct TouchTracker {
+    /// Thresholds for the gestures
+    pub settings: TouchSettings,
+    points: BTreeMap<i32, TouchPoint>,
+    sequence: Option<Sequence>
+}
+
+impl TouchTracker {
+    /// Makes a tracker with the default settings.
+    pub fn new() -> TouchTracker {
+        TouchTracker::default()
+    }
+
+    /// Makes a tracker with some settings.
+    pub fn with_settings(settings: TouchSettings) -> TouchTracker {
+        TouchTracker { settings, .. TouchTracker::default() }
+    }
+
+    /// Fingers which are down, by slot.
+    pub fn points(&self) -> Vec<TouchPoint> {
+        self.points.values().cloned().collect()
+    }
+
+    /// The finger in a slot, if it is down.
+    pub fn point(&self, slot: i32) -> Option<TouchPoint> {
+        self.points.get(&slot).cloned()
+    }
+
+    /// How many fingers are down.
+    pub fn fingers(&self) -> u32 {
+        self.points.len() as u32
+    }
+
+    /// Handles a touch event on the focused output. Call from the `touch`
+    /// callback.
+    pub fn touch(&mut self, view: WlcView, time: u32, touch: TouchType, slot: i32, point: Point)
+                 -> Vec<TouchGesture> {
+        let output = WlcOutput::focused();
+        let area = output.get_virtual_resolution()
+            .or_else(|| output.get_resolution())
+            .unwrap_or_else(Size::zero);
+        self.touch_in(area, view, time, touch, slot, point)
+    }
+
+    /// Handles a touch event on an output of size `area`, which edge
+    /// swipes start from.
+    pub fn touch_in(&mut self, area: Size, view: WlcView, time: u32, touch: TouchType,
+                    slot: i32, point: Point) -> Vec<TouchGesture> {
+        match touch {
+            TouchType::Down => self.down(area, view, time, slot, point),
+            TouchType::Up => self.up(time, slot),
+            TouchType::Motion => {
+                self.motion(slot, point);
+                Vec::new()
+            },
+            TouchType::Frame => self.frame(),
+            TouchType::Cancel => self.cancel()
+        }
+    }
+
+    fn down(&mut self, area: Size, view: WlcView, time: u32, slot: i32, point: Point)
+            -> Vec<TouchGesture> {
+        let gestures = self.end(Phase::End);
+        self.points.insert(slot, TouchPoint { slot, view, time, start: point, point });
+        let fingers = self.fingers();
+        let sequence = self.sequence.get_or_insert(Sequence {
+            view,
+            time,
+            start: point,
+            area,
+            fingers,
+            moved: false,
+            active: None,
+            spent: false
+        });
+        sequence.fingers = sequence.fingers.max(fingers);
+        gestures
+    }
+
+    fn motion(&mut self, slot: i32, point: Point) {
+        let tap_distance = self.settings.tap_distance as f64;
+        if let Some(touch) = self.points.get_mut(&slot) {
+            touch.point = point;
+            if distance(touch.start, point) > tap_distance {
+                if let Some(sequence) = self.sequence.as_mut() {
+                    sequence.moved = true;
+                }
+            }
+        }
+    }
+
+    fn up(&mut self, time: u32, slot: i32) -> Vec<TouchGesture> {
+        let mut gestures = self.end(Phase::End);
+        if self.points.remove(&slot).is_none() || !self.points.is_empty() {
+            return gestures
+        }
+        if let Some(sequence) = self.sequence.take() {
+            if !sequence.spent && !sequence.moved
+                && time.wrapping_sub(sequence.time) <= self.settings.tap_time {
+                gestures.push(TouchGesture::Tap {
+                    view: sequence.view,
+                    fingers: sequence.fingers,
+                    point: sequence.start
+                });
+            }
+        }
+        gestures
+    }
+
+    fn cancel(&mut self) -> Vec<TouchGesture> {
+        let gestures = self.end(Phase::Cancel);
+        self.points.clear();
+        self.sequence = None;
+        gestures
+    }
+
+    /// Ends the active gesture, if any. The fingers down start no other.
+    fn end(&mut self, phase: Phase) -> Vec<TouchGesture> {
+        let motion = self.measure();
+        let fingers = self.fingers();
+        match self.sequence.as_mut() {
+            Some(sequence) => {
+                sequence.spent |= sequence.active.is_some();
+                match sequence.active.take() {
+                    Some(active) => vec![gesture(active, phase, fingers, motion)],
+                    None => Vec::new()
+                }
+            },
+            None => Vec::new()
+        }
+    }
+
+    fn frame(&mut self) -> Vec<TouchGesture> {
+        let motion = self.measure();
+        let fingers = self.fingers();
+        let settings = self.settings;
+        let starts: Vec<Point> = self.points.values().map(|touch| touch.start).collect();
+        let sequence = match self.sequence.as_mut() {
+            Some(sequence) if !sequence.spent && fingers > 0 => sequence,
+            _ => return Vec::new()
+        };
+        if let Some(active) = sequence.active {
+            return vec![gesture(active, Phase::Update, fingers, motion)]
+        }
+        let moved = (motion.dx * motion.dx + motion.dy * motion.dy).sqrt();
+        let active = if fingers > 1 && ((motion.scale - 1.0).abs() >= settings.pinch_scale
+                                        || motion.rotation.abs() >= settings.pinch_rotation) {
+            Active::Pinch
+        } else if moved >= settings.swipe_distance as f64 {
+            let direction = Direction::of(motion.dx, motion.dy);
+            let edges: Vec<_> = starts.iter()
+                .map(|&start| Edge::near(start, sequence.area, settings.edge_size))
+                .collect();
+            match edges[0] {
+                Some(edge) if edge.inwards() == direction
+                    && edges.iter().all(|&other| other == Some(edge)) => Active::Edge(edge),
+                _ => Active::Swipe(direction)
+            }
+        } else {
+            return Vec::new()
+        };
+        sequence.active = Some(active);
+        sequence.moved = true;
+        vec![gesture(active, Phase::Begin, fingers, motion)]
+    }
+
+    /// How the fingers down moved since they were put down.
+    fn measure(&self) -> Motion {
+        let pairs: Vec<_> = self.points.values()
+            .map(|touch| (touch.start, touch.point))
+            .collect();
+        measure(&pairs)
+    }
+}
+
+fn gesture(active: Active, phase: Phase, fingers: u32, motion: Motion) -> TouchGesture {
+    match active {
+        Active::Swipe(direction) =>
+            TouchGesture::Swipe { phase, fingers, direction, dx: motion.dx, dy: motion.dy },
+        Active::Pinch =>
+            TouchGesture::Pinch { phase, fingers, scale: motion.scale, rotation: motion.rotation },
+        Active::Edge(edge) => {
+            let distance = match edge {
+                Edge::Left => motion.dx,
+                Edge::Right => -motion.dx,
+                Edge::Top => motion.dy,
+                Edge::Bottom => -motion.dy
+            };
+            TouchGesture::EdgeSwipe { phase, fingers, edge, distance }
+        }
+    }
+}
+
+/// How points moved from their start: the centroid's offset, and the
+/// average change in distance from and angle around the centroid.
+fn measure(pairs: &[(Point, Point)]) -> Motion {
+    let mut motion = Motion { dx: 0.0, dy: 0.0, scale: 1.0, rotation: 0.0 };
+    if pairs.is_empty() {
+        return motion
+    }
+    let from = centroid(pairs.iter().map(|pair| pair.0));
+    let to = centroid(pairs.iter().map(|pair| pair.1));
+    motion.dx = to.0 - from.0;
+    motion.dy = to.1 - from.1;
+    if pairs.len() < 2 {
+        return motion
+    }
+    let (mut spread_from, mut spread_to, mut turned) = (0.0, 0.0, 0.0);
+    for &(start, point) in pairs {
+        let before = (start.x as f64 - from.0, start.y as f64 - from.1);
+        let after = (point.x as f64 - to.0, point.y as f64 - to.1);
+        spread_from += before.0.hypot(before.1);
+        spread_to += after.0.hypot(after.1);
+        let mut angle = after.1.atan2(after.0) - before.1.atan2(before.0);
+        if angle > PI {
+            angle -= 2.0 * PI;
+        } else if angle < -PI {
+            angle += 2.0 * PI;
+        }
+        turned += angle;
+    }
+    if spread_from > 0.0 {
+        motion.scale = spread_to / spread_from;
+    }
+    motion.rotation = turned / pairs.len() as f64;
+    motion
+}
+
+fn centroid<I: ExactSizeIterator<Item = Point>>(points: I) -> (f64, f64) {
+    let count = points.len() as f64;
+    let (x, y) = points.fold((0.0, 0.0), |(x, y), point| (x + point.x as f64, y + point.y as f64));
+    (x / count, y / count)
+}
+
+fn distance(from: Point, to: Point) -> f64 {
+    (to.x as f64 - from.x as f64).hypot(to.y as f64 - from.y as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Size = Size { w: 1000, h: 800 };
+
+    fn event(tracker: &mut TouchTracker, time: u32, touch: TouchType, slot: i32, x: i32, y: i32)
+             -> Vec<TouchGesture> {
+        tracker.touch_in(AREA, WlcView(1), time, touch, slot, Point::new(x, y))
+    }
+
+    fn frame(tracker: &mut TouchTracker) -> Vec<TouchGesture> {
+        event(tracker, 0, TouchType::Frame, 0, 0, 0)
+    }
+
+    #[test]
+    fn taps() {
+        let mut tracker = TouchTracker::new();
+        event(&mut tracker, 0, TouchType::Down, 0, 100, 100);
+        event(&mut tracker, 10, TouchType::Down, 1, 200, 100);
+        assert_eq!(tracker.fingers(), 2);
+        event(&mut tracker, 20, TouchType::Motion, 1, 205, 100);
+        assert_eq!(tracker.point(1).map(|touch| touch.point), Some(Point::new(205, 100)));
+        assert!(frame(&mut tracker).is_empty());
+        assert!(event(&mut tracker, 100, TouchType::Up, 0, 0, 0).is_empty());
+        assert_eq!(event(&mut tracker, 120, TouchType::Up, 1, 0, 0), vec![
+            TouchGesture::Tap { view: WlcView(1), fingers: 2, point: Point::new(100, 100) }
+        ]);
+        assert!(tracker.points().is_empty());
+
+        // Too slow
+        event(&mut tracker, 1000, TouchType::Down, 0, 100, 100);
+        assert!(event(&mut tracker, 1400, TouchType::Up, 0, 0, 0).is_empty());
+        // Moved too far
+        event(&mut tracker, 2000, TouchType::Down, 0, 100, 100);
+        event(&mut tracker, 2010, TouchType::Motion, 0, 115, 100);
+        event(&mut tracker, 2010, TouchType::Motion, 0, 100, 100);
+        assert!(event(&mut tracker, 2020, TouchType::Up, 0, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn swipes() {
+        let mut tracker = TouchTracker::new();
+        for slot in 0..3 {
+            event(&mut tracker, 0, TouchType::Down, slot, 300 + slot * 50, 400);
+        }
+        for slot in 0..3 {
+            event(&mut tracker, 10, TouchType::Motion, slot, 280 + slot * 50, 395);
+        }
+        assert!(frame(&mut tracker).is_empty());
+        for slot in 0..3 {
+            event(&mut tracker, 20, TouchType::Motion, slot, 250 + slot * 50, 390);
+        }
+        assert_eq!(frame(&mut tracker), vec![TouchGesture::Swipe {
+            phase: Phase::Begin, fingers: 3, direction: Direction::Left, dx: -50.0, dy: -10.0
+        }]);
+        event(&mut tracker, 30, TouchType::Motion, 0, 240, 420);
+        assert!(matches!(frame(&mut tracker)[..],
+                         [TouchGesture::Swipe { phase: Phase::Update, fingers: 3,
+                                                direction: Direction::Left, .. }]));
+        assert!(matches!(event(&mut tracker, 40, TouchType::Up, 2, 0, 0)[..],
+                         [TouchGesture::Swipe { phase: Phase::End, fingers: 3, .. }]));
+        // The fingers left start nothing new
+        event(&mut tracker, 50, TouchType::Motion, 0, 0, 0);
+        assert!(frame(&mut tracker).is_empty());
+        assert!(event(&mut tracker, 60, TouchType::Up, 0, 0, 0).is_empty());
+        assert!(event(&mut tracker, 60, TouchType::Up, 1, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn edge_swipes() {
+        let mut tracker = TouchTracker::new();
+        event(&mut tracker, 0, TouchType::Down, 0, 990, 400);
+        event(&mut tracker, 10, TouchType::Motion, 0, 900, 410);
+        assert_eq!(frame(&mut tracker), vec![TouchGesture::EdgeSwipe {
+            phase: Phase::Begin, fingers: 1, edge: Edge::Right, distance: 90.0
+        }]);
+        assert_eq!(event(&mut tracker, 20, TouchType::Cancel, 0, 0, 0), vec![
+            TouchGesture::EdgeSwipe { phase: Phase::Cancel, fingers: 1, edge: Edge::Right,
+                                      distance: 90.0 }
+        ]);
+        assert_eq!(tracker.fingers(), 0);
+
+        // Along the edge is a plain swipe
+        event(&mut tracker, 100, TouchType::Down, 0, 5, 400);
+        event(&mut tracker, 110, TouchType::Motion, 0, 5, 300);
+        assert!(matches!(frame(&mut tracker)[..],
+                         [TouchGesture::Swipe { direction: Direction::Up, .. }]));
+
+        assert_eq!(Edge::near(Point::new(500, 799), AREA, 16), Some(Edge::Bottom));
+        assert_eq!(Edge::near(Point::new(500, 400), AREA, 16), None);
+        assert_eq!(Edge::near(Point::new(0, 0), AREA, 0), None);
+        assert_eq!(Edge::near(Point::new(0, 0), Size::zero(), 16), None);
+    }
+
+    #[test]
+    fn pinches() {
+        let pairs = [(Point::new(0, 0), Point::new(-50, 0)),
+                     (Point::new(100, 0), Point::new(150, 0))];
+        let motion = measure(&pairs);
+        assert_eq!((motion.dx, motion.dy, motion.scale), (0.0, 0.0, 2.0));
+        assert!(motion.rotation.abs() < 1e-9);
+        // A quarter turn clockwise around the middle
+        let pairs = [(Point::new(0, 0), Point::new(50, -50)),
+                     (Point::new(100, 0), Point::new(50, 50))];
+        let motion = measure(&pairs);
+        assert!((motion.scale - 1.0).abs() < 1e-9);
+        assert!((motion.rotation - PI / 2.0).abs() < 1e-9);
+
+        let mut tracker = TouchTracker::new();
+        event(&mut tracker, 0, TouchType::Down, 0, 400, 400);
+        event(&mut tracker, 0, TouchType::Down, 1, 600, 400);
+        event(&mut tracker, 10, TouchType::Motion, 0, 450, 400);
+        event(&mut tracker, 10, TouchType::Motion, 1, 550, 400);
+        assert_eq!(frame(&mut tracker), vec![TouchGesture::Pinch {
+            phase: Phase::Begin, fingers: 2, scale: 0.5, rotation: 0.0
+        }]);
+        // Another finger ends it
+        assert!(matches!(event(&mut tracker, 20, TouchType::Down, 2, 0, 0)[..],
+                         [TouchGesture::Pinch { phase: Phase::End, fingers: 2, .. }]));
+        assert!(frame(&mut tracker).is_empty());
+    }
+}
@@ -109,13 +109,18 @@ bitflags! {
     #[repr(C)]
     pub flags ViewPropertyType: u32 {
         /// View title is being updated
-        const PROPERTY_TITLE = 0,
+        const PROPERTY_TITLE = 1,
+        /// Original typo of PROPERTY_CLASS
+        ///
+        /// # Deprecated
+        /// Please use PROPERTY_CLASS instead.
+        const PROPRETY_CLASS = 2,
         /// View class is being updated
-        const PROPRETY_CLASS = 1,
+        const PROPERTY_CLASS = 2,
         /// View app id is being updated
-        const PROPERTY_APP_ID = 2,
+        const PROPERTY_APP_ID = 4,
         /// PID of the view is being updated
-        const PROPERTY_PID = 4
+        const PROPERTY_PID = 8
     }
 }
 
@@ -406,6 +411,17 @@ impl Geometry {
             point.y <= self.origin.y + self.size.h as i32
     }
 
+    /// Determines if this geometry contains a point, treating the geometry
+    /// as half-open.
+    ///
+    /// The origin is inside the geometry, the far edges at origin plus size
+    /// are not. Unlike `contains_point`, points before the origin are outside.
+    pub fn contains_point_exclusive(self, point: Point) -> bool {
+        point.x >= self.origin.x && point.y >= self.origin.y
+            && (point.x as i64) < self.origin.x as i64 + self.size.w as i64
+            && (point.y as i64) < self.origin.y as i64 + self.size.h as i64
+    }
+
     /// Determines if this geometry contains another.
     ///
     /// If the other geometry's borders could be fully contained (less than
@@ -429,3 +445,23 @@ impl fmt::Display for Geometry {
 /// Not currently supporting libinput
 #[repr(C)]
 pub struct LibinputDevice;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry_contains_point_exclusive() {
+        let geometry = Geometry::new(Point::new(-10, 5), Size::new(20, 10));
+        assert!(geometry.contains_point_exclusive(Point::new(-10, 5)));
+        assert!(geometry.contains_point_exclusive(Point::new(9, 14)));
+        assert!(!geometry.contains_point_exclusive(Point::new(10, 14)));
+        assert!(!geometry.contains_point_exclusive(Point::new(9, 15)));
+        assert!(!geometry.contains_point_exclusive(Point::new(-11, 5)));
+        assert!(!geometry.contains_point_exclusive(Point::new(0, 4)));
+        // The far edge still counts for contains_point
+        assert!(geometry.contains_point(Point::new(10, 15)));
+        let huge = Geometry::new(Point::new(i32::MAX - 1, 0), Size::new(u32::MAX, 1));
+        assert!(huge.contains_point_exclusive(Point::new(i32::MAX, 0)));
+    }
+}
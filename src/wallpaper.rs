@@ -0,0 +1,353 @@
+//! Per-output backgrounds.
+//!
+//! wlc only supports a single background, set with the `WLC_BG`
+//! environment variable. `Wallpapers` draws a `Background` under the views
+//! of each output instead: a solid color, a gradient or an image.
+//!
+//! Backgrounds are rendered once at the output's resolution and kept, so
+//! each frame only copies the finished pixels to the framebuffer. They are
+//! rendered again when the output's resolution changes.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::{Size, WlcOutput};
+//! use rustwlc::wallpaper::{Background, Direction, ScaleMode, Wallpapers};
+//!
+//! lazy_static! {
+//!     static ref WALLPAPERS: Mutex<Wallpapers> = Mutex::new(Wallpapers::new());
+//! }
+//!
+//! extern fn output_created(output: WlcOutput) -> bool {
+//!     let mut wallpapers = WALLPAPERS.lock().unwrap();
+//!     match Background::load("/usr/share/backgrounds/default.png", ScaleMode::Fill) {
+//!         Ok(background) => wallpapers.set(output, background),
+//!         Err(_) => wallpapers.set(output, Background::Gradient {
+//!             from: [0x20, 0x30, 0x40, 0xff],
+//!             to: [0x05, 0x05, 0x10, 0xff],
+//!             direction: Direction::Vertical
+//!         })
+//!     }
+//!     true
+//! }
+//!
+//! extern fn output_render_pre(output: WlcOutput) {
+//!     WALLPAPERS.lock().unwrap().output_render_pre(output);
+//! }
+//!
+//! extern fn output_resolution(output: WlcOutput, from: &Size, to: &Size) {
+//!     WALLPAPERS.lock().unwrap().output_resolution(output, from, to);
+//! }
+//!
+//! extern fn output_destroyed(output: WlcOutput) {
+//!     WALLPAPERS.lock().unwrap().output_destroyed(output);
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use super::WlcOutput;
+use super::types::{Point, Size};
+use super::canvas::Canvas;
+use super::image::Image;
+
+/// Direction a gradient goes in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// From the top to the bottom of the output
+    Vertical,
+    /// From the left to the right of the output
+    Horizontal
+}
+
+/// How an image is fitted to an output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ScaleMode {
+    /// Scaled to cover the whole output, cropping the sides that don't fit
+    Fill,
+    /// Scaled to fit inside the output, with the background color around it
+    Fit,
+    /// Centered at its own size
+    Center,
+    /// Repeated from the top left corner at its own size
+    Tile
+}
+
+/// What to draw under the views of an output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Background {
+    /// A single color, as red, green, blue, alpha
+    Solid([u8; 4]),
+    /// A linear gradient between two colors
+    Gradient {
+        /// Color at the top or left
+        from: [u8; 4],
+        /// Color at the bottom or right
+        to: [u8; 4],
+        /// Which way the colors change
+        direction: Direction
+    },
+    /// An image
+    Image {
+        /// The image to draw
+        image: Image,
+        /// How the image is fitted to the output
+        mode: ScaleMode,
+        /// Color drawn where the image does not cover the output
+        color: [u8; 4]
+    }
+}
+
+impl Background {
+    /// Loads a PPM or PNG image, with black around it. PNG images need the
+    /// `image-png` feature.
+    pub fn load<P: AsRef<Path>>(path: P, mode: ScaleMode) -> io::Result<Background> {
+        let image = Image::load(path)?;
+        Ok(Background::Image { image, mode, color: [0, 0, 0, 255] })
+    }
+
+    /// Draws the background at a size, in pixels.
+    pub fn render(&self, size: Size) -> Canvas {
+        match *self {
+            Background::Solid(color) => Canvas::filled(size, color),
+            Background::Gradient { from, to, direction } => {
+                let mut canvas = Canvas::new(size);
+                let (steps, length) = match direction {
+                    Direction::Vertical => (size.h, size.w),
+                    Direction::Horizontal => (size.w, size.h)
+                };
+                for step in 0..steps {
+                    let t = if steps > 1 { step as f64 / (steps - 1) as f64 } else { 0.0 };
+                    let color = mix(from, to, t);
+                    for across in 0..length {
+                        let (x, y) = match direction {
+                            Direction::Vertical => (across, step),
+                            Direction::Horizontal => (step, across)
+                        };
+                        canvas.set_pixel(x as i32, y as i32, color);
+                    }
+                }
+                canvas
+            },
+            Background::Image { ref image, mode, color } => {
+                let mut canvas = Canvas::filled(size, color);
+                draw_image(&mut canvas, image, mode);
+                canvas
+            }
+        }
+    }
+}
+
+/// Backgrounds for each output.
+#[derive(Debug, Clone, Default)]
+pub struct Wallpapers {
+    default: Option<Background>,
+    backgrounds: HashMap<WlcOutput, Background>,
+    rendered: HashMap<WlcOutput, Canvas>
+}
+
+impl Wallpapers {
+    /// Creates an empty set of wallpapers, which draws nothing.
+    pub fn new() -> Wallpapers {
+        Wallpapers::default()
+    }
+
+    /// Sets the background of outputs without their own, or `None` to draw
+    /// nothing on them.
+    pub fn set_default(&mut self, background: Option<Background>) {
+        self.default = background;
+        let backgrounds = &self.backgrounds;
+        self.rendered.retain(|output, _| backgrounds.contains_key(output));
+    }
+
+    /// Sets the background of an output.
+    pub fn set(&mut self, output: WlcOutput, background: Background) {
+        self.backgrounds.insert(output, background);
+        self.rendered.remove(&output);
+    }
+
+    /// Makes an output use the default background.
+    pub fn unset(&mut self, output: WlcOutput) {
+        self.backgrounds.remove(&output);
+        self.rendered.remove(&output);
+    }
+
+    /// Gets the background drawn on an output.
+    pub fn get(&self, output: WlcOutput) -> Option<&Background> {
+        self.backgrounds.get(&output).or(self.default.as_ref())
+    }
+
+    /// Draws the background of an output.
+    ///
+    /// Call this from the `output_render_pre` callback, so views are drawn
+    /// over it. The background is only rendered again if the output's
+    /// resolution changed.
+    pub fn output_render_pre(&mut self, output: WlcOutput) {
+        let resolution = match output.get_resolution() {
+            Some(resolution) => resolution,
+            None => return
+        };
+        if let Some(canvas) = self.rendered_at(output, resolution) {
+            canvas.push_clipped(resolution, Point::origin());
+        }
+    }
+
+    /// Renders the background of an output for its new resolution.
+    ///
+    /// Call this from the `output_resolution` callback.
+    pub fn output_resolution(&mut self, output: WlcOutput, _from: &Size, to: &Size) {
+        self.rendered.remove(&output);
+        self.rendered_at(output, *to);
+    }
+
+    /// Forgets an output. Call this from the `output_destroyed` callback.
+    pub fn output_destroyed(&mut self, output: WlcOutput) {
+        self.backgrounds.remove(&output);
+        self.rendered.remove(&output);
+    }
+
+    /// Gets the background of an output rendered at a size, rendering it
+    /// if needed.
+    fn rendered_at(&mut self, output: WlcOutput, size: Size) -> Option<&Canvas> {
+        let stale = match self.rendered.get(&output) {
+            Some(canvas) => canvas.size() != size,
+            None => true
+        };
+        if stale {
+            let canvas = self.get(output)?.render(size);
+            self.rendered.insert(output, canvas);
+        }
+        self.rendered.get(&output)
+    }
+}
+
+/// Interpolates between two colors, with `t` from 0 to 1.
+fn mix(from: [u8; 4], to: [u8; 4], t: f64) -> [u8; 4] {
+    let mut color = [0; 4];
+    for channel in 0..4 {
+        let (a, b) = (from[channel] as f64, to[channel] as f64);
+        color[channel] = (a + (b - a) * t).round() as u8;
+    }
+    color
+}
+
+/// Blends an image over a canvas, fitted with a scale mode.
+fn draw_image(canvas: &mut Canvas, image: &Image, mode: ScaleMode) {
+    let (width, height) = (canvas.size().w as f64, canvas.size().h as f64);
+    let (image_width, image_height) = (image.size.w as f64, image.size.h as f64);
+    if image.size.w == 0 || image.size.h == 0 {
+        return
+    }
+    let scale = match mode {
+        ScaleMode::Fill => (width / image_width).max(height / image_height),
+        ScaleMode::Fit => (width / image_width).min(height / image_height),
+        ScaleMode::Center | ScaleMode::Tile => 1.0
+    };
+    if mode == ScaleMode::Tile {
+        for y in 0..canvas.size().h {
+            for x in 0..canvas.size().w {
+                let color = image.rgba(x % image.size.w, y % image.size.h);
+                canvas.blend_pixel(x as i32, y as i32, color);
+            }
+        }
+        return
+    }
+    // The scaled image's top left corner, centered on the canvas
+    let left = ((width - image_width * scale) / 2.0).round();
+    let top = ((height - image_height * scale) / 2.0).round();
+    let first_x = left.max(0.0) as u32;
+    let first_y = top.max(0.0) as u32;
+    let last_x = (left + image_width * scale).round().min(width) as u32;
+    let last_y = (top + image_height * scale).round().min(height) as u32;
+    for y in first_y..last_y {
+        for x in first_x..last_x {
+            let color = if scale == 1.0 {
+                image.rgba((x as f64 - left) as u32, (y as f64 - top) as u32)
+            } else {
+                // Sample at the pixel's center
+                bilinear(image, (x as f64 - left + 0.5) / scale - 0.5,
+                         (y as f64 - top + 0.5) / scale - 0.5)
+            };
+            canvas.blend_pixel(x as i32, y as i32, color);
+        }
+    }
+}
+
+/// Samples an image between pixels, clamping at its edges.
+fn bilinear(image: &Image, x: f64, y: f64) -> [u8; 4] {
+    let max_x = image.size.w as f64 - 1.0;
+    let max_y = image.size.h as f64 - 1.0;
+    let (x, y) = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (x1, y1) = ((x0 + 1.0).min(max_x), (y0 + 1.0).min(max_y));
+    let (tx, ty) = (x - x0, y - y0);
+    let top = mix(image.rgba(x0 as u32, y0 as u32), image.rgba(x1 as u32, y0 as u32), tx);
+    let bottom = mix(image.rgba(x0 as u32, y1 as u32), image.rgba(x1 as u32, y1 as u32), tx);
+    mix(top, bottom, ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::image::PixelFormat;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    fn red_square(side: u32) -> Image {
+        let mut image = Image::new(Size::new(side, side), PixelFormat::Rgba8888);
+        for y in 0..side {
+            for x in 0..side {
+                image.set_pixel(x, y, RED);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn gradients() {
+        let gradient = Background::Gradient {
+            from: [0, 0, 0, 255],
+            to: [200, 100, 0, 255],
+            direction: Direction::Horizontal
+        };
+        let canvas = gradient.render(Size::new(5, 2));
+        assert_eq!(canvas.pixel(0, 1), [0, 0, 0, 255]);
+        assert_eq!(canvas.pixel(2, 0), [100, 50, 0, 255]);
+        assert_eq!(canvas.pixel(4, 1), [200, 100, 0, 255]);
+        let solid = Background::Solid(RED).render(Size::new(2, 2));
+        assert_eq!(solid.pixel(1, 1), RED);
+    }
+
+    #[test]
+    fn scale_modes() {
+        let background = |mode| Background::Image { image: red_square(2), mode, color: BLACK };
+        // Scaled to 4x4 and centered, with bars on the sides
+        let fit = background(ScaleMode::Fit).render(Size::new(8, 4));
+        assert_eq!(fit.pixel(1, 1), BLACK);
+        assert_eq!(fit.pixel(2, 0), RED);
+        assert_eq!(fit.pixel(5, 3), RED);
+        assert_eq!(fit.pixel(6, 3), BLACK);
+        // Scaled to 8x8 and cropped
+        let fill = background(ScaleMode::Fill).render(Size::new(8, 4));
+        assert!((0..8).all(|x| (0..4).all(|y| fill.pixel(x, y) == RED)));
+        let center = background(ScaleMode::Center).render(Size::new(4, 4));
+        assert_eq!(center.pixel(0, 0), BLACK);
+        assert_eq!(center.pixel(1, 1), RED);
+        assert_eq!(center.pixel(3, 3), BLACK);
+
+        let mut checker = red_square(2);
+        checker.set_pixel(1, 0, BLACK);
+        checker.set_pixel(0, 1, BLACK);
+        let tile = Background::Image { image: checker, mode: ScaleMode::Tile, color: BLACK }
+            .render(Size::new(5, 5));
+        assert_eq!(tile.pixel(2, 2), RED);
+        assert_eq!(tile.pixel(3, 2), BLACK);
+        assert_eq!(tile.pixel(4, 4), RED);
+    }
+}
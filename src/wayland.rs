@@ -28,6 +28,7 @@ use libc::{uintptr_t, size_t};
 use std::ptr;
 
 use types::{Size, Geometry, Point};
+use render::SurfaceTextures;
 
 /// ## Requires `wlc-wayland` feature
 ///
@@ -123,4 +124,12 @@ impl WlcResource {
         }
         geo
     }
+
+    /// # Requires `wlc-wayland` feature
+    ///
+    /// Gets the textures wlc's renderer uses for this surface, for custom
+    /// rendering. Returns `None` if the surface is invalid.
+    pub fn get_textures(self) -> Option<SurfaceTextures> {
+        SurfaceTextures::for_surface(self.0)
+    }
 }
@@ -0,0 +1,429 @@
+//! Per-application behaviour with window rules.
+//!
+//! A `Rule` pairs matchers on a view's properties, such as its class, app id
+//! or title, with actions to take on views that match, such as floating
+//! them, moving them to an output or workspace, or giving them a size.
+//!
+//! Rules are evaluated when a view is created and again whenever its
+//! properties change, since many clients only set their title or app id
+//! after their first surface is shown. Each rule is applied to a view at
+//! most once, the first time the view matches it.
+//!
+//! Some actions, such as floating or not taking focus, are decisions for
+//! the compositor's own layout code. These are not applied, but returned in
+//! an `Outcome` for the compositor to act on.
+//!
+//! Patterns match text properties exactly. Regular expression patterns need
+//! the `window-rules-regex` feature.
+//!
+//! # Example
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::{WlcView, ViewPropertyType, VIEW_BIT_MODAL};
+//! use rustwlc::window_rules::{Action, Matcher, Pattern, Rule, WindowRules};
+//!
+//! lazy_static! {
+//!     static ref RULES: Mutex<WindowRules> = Mutex::new(WindowRules::new(vec![
+//!         Rule::new("dialogs", vec![Matcher::Type(VIEW_BIT_MODAL)], vec![Action::Float]),
+//!         Rule::new("browsers",
+//!                   vec![Matcher::Any(vec![Matcher::AppId(Pattern::exact("firefox")),
+//!                                          Matcher::AppId(Pattern::exact("chromium"))])],
+//!                   vec![Action::Mask(0b10)])
+//!     ]));
+//! }
+//!
+//! extern fn view_created(view: WlcView) -> bool {
+//!     let outcome = RULES.lock().unwrap().view_created(view);
+//!     if outcome.focus != Some(false) {
+//!         view.focus();
+//!     }
+//!     !outcome.closed
+//! }
+//!
+//! extern fn view_properties_changed(view: WlcView, _mask: ViewPropertyType) {
+//!     RULES.lock().unwrap().view_properties_changed(view);
+//! }
+//!
+//! extern fn view_destroyed(view: WlcView) {
+//!     RULES.lock().unwrap().view_destroyed(view);
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[cfg(feature = "window-rules-regex")]
+use regex::{self, Regex};
+
+use super::{WlcOutput, WlcView};
+use super::types::{Geometry, ViewState, ViewType, EDGE_NONE};
+
+/// A pattern matched against a text property of a view.
+#[derive(Clone)]
+pub enum Pattern {
+    /// The whole property equals the text
+    Exact(String),
+    /// The regular expression matches somewhere in the property.
+    /// Use `^` and `$` to match the whole property.
+    #[cfg(feature = "window-rules-regex")]
+    Regex(Regex)
+}
+
+impl Pattern {
+    /// Matches the whole property against `text`.
+    pub fn exact<S: Into<String>>(text: S) -> Pattern {
+        Pattern::Exact(text.into())
+    }
+
+    /// Compiles a regular expression pattern.
+    #[cfg(feature = "window-rules-regex")]
+    pub fn regex(pattern: &str) -> Result<Pattern, regex::Error> {
+        Ok(Pattern::Regex(Regex::new(pattern)?))
+    }
+
+    /// Whether the pattern matches `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        match *self {
+            Pattern::Exact(ref exact) => exact == text,
+            #[cfg(feature = "window-rules-regex")]
+            Pattern::Regex(ref regex) => regex.is_match(text)
+        }
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pattern::Exact(ref exact) => write!(f, "Exact({:?})", exact),
+            #[cfg(feature = "window-rules-regex")]
+            Pattern::Regex(ref regex) => write!(f, "Regex({:?})", regex.as_str())
+        }
+    }
+}
+
+/// The properties of a view that rules match against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewProperties {
+    /// `get_class()`
+    pub class: String,
+    /// `get_app_id()`
+    pub app_id: String,
+    /// `get_title()`
+    pub title: String,
+    /// `get_type()`
+    pub view_type: ViewType,
+    /// Whether `get_parent()` is a view
+    pub has_parent: bool
+}
+
+impl ViewProperties {
+    /// Reads the properties of a view.
+    pub fn of(view: WlcView) -> ViewProperties {
+        ViewProperties {
+            class: view.get_class(),
+            app_id: view.get_app_id(),
+            title: view.get_title(),
+            view_type: view.get_type(),
+            has_parent: !view.get_parent().is_root()
+        }
+    }
+}
+
+/// A condition on the properties of a view.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// The class, as set by X11 clients
+    Class(Pattern),
+    /// The app id, as set by Wayland clients
+    AppId(Pattern),
+    /// The title
+    Title(Pattern),
+    /// The view type has all of these bits
+    Type(ViewType),
+    /// Whether the view has a parent
+    HasParent(bool),
+    /// The matcher does not match
+    Not(Box<Matcher>),
+    /// Any of the matchers match
+    Any(Vec<Matcher>)
+}
+
+impl Matcher {
+    /// Whether a view with these properties matches.
+    pub fn matches(&self, properties: &ViewProperties) -> bool {
+        match *self {
+            Matcher::Class(ref pattern) => pattern.is_match(&properties.class),
+            Matcher::AppId(ref pattern) => pattern.is_match(&properties.app_id),
+            Matcher::Title(ref pattern) => pattern.is_match(&properties.title),
+            Matcher::Type(bits) => properties.view_type.contains(bits),
+            Matcher::HasParent(has_parent) => properties.has_parent == has_parent,
+            Matcher::Not(ref matcher) => !matcher.matches(properties),
+            Matcher::Any(ref matchers) => matchers.iter().any(|m| m.matches(properties))
+        }
+    }
+}
+
+/// Something to do with a view which matches a rule.
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// The compositor should float the view, see `Outcome::floating`
+    Float,
+    /// The compositor should tile the view, see `Outcome::floating`
+    Tile,
+    /// Moves the view to an output
+    Output(WlcOutput),
+    /// Sets the visibility mask of the view, such as to put it on a
+    /// workspace
+    Mask(u32),
+    /// Sets the geometry of the view
+    Geometry(Geometry),
+    /// Sets or unsets state flags of the view
+    State(ViewState, bool),
+    /// Focuses the view
+    Focus,
+    /// The compositor should not focus the view, see `Outcome::focus`
+    NoFocus,
+    /// Closes the view. Later actions are not applied.
+    Close
+}
+
+/// A set of matchers and the actions for views which match all of them.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// Name of the rule, for reporting which rules matched
+    pub name: String,
+    /// Conditions which must all hold. A rule without any matches every
+    /// view.
+    pub matchers: Vec<Matcher>,
+    /// Actions, applied in order
+    pub actions: Vec<Action>
+}
+
+impl Rule {
+    /// Makes a rule.
+    pub fn new<S: Into<String>>(name: S, matchers: Vec<Matcher>, actions: Vec<Action>) -> Rule {
+        Rule { name: name.into(), matchers, actions }
+    }
+
+    /// Whether a view with these properties matches every matcher.
+    pub fn matches(&self, properties: &ViewProperties) -> bool {
+        self.matchers.iter().all(|matcher| matcher.matches(properties))
+    }
+}
+
+/// What the compositor should do with a view after rules were applied.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Outcome {
+    /// Names of the rules applied, in order
+    pub rules: Vec<String>,
+    /// `Some(true)` to float the view, `Some(false)` to tile it, `None`
+    /// if no rule said
+    pub floating: Option<bool>,
+    /// `Some(false)` if the view should not be focused, `Some(true)` if a
+    /// rule focused it, `None` if no rule said
+    pub focus: Option<bool>,
+    /// Whether the view was closed
+    pub closed: bool
+}
+
+impl Outcome {
+    /// Folds an action into the outcome, later actions overriding earlier
+    /// ones.
+    fn add(&mut self, action: &Action) {
+        match *action {
+            Action::Float => self.floating = Some(true),
+            Action::Tile => self.floating = Some(false),
+            Action::Focus => self.focus = Some(true),
+            Action::NoFocus => self.focus = Some(false),
+            Action::Close => self.closed = true,
+            _ => {}
+        }
+    }
+}
+
+/// A list of rules and which of them each view has had applied.
+#[derive(Debug, Default)]
+pub struct WindowRules {
+    rules: Vec<Rule>,
+    applied: HashMap<WlcView, HashSet<usize>>
+}
+
+impl WindowRules {
+    /// Makes a rules engine with rules evaluated in order.
+    pub fn new(rules: Vec<Rule>) -> WindowRules {
+        WindowRules { rules, applied: HashMap::new() }
+    }
+
+    /// The rules, in order.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Adds a rule after the others.
+    ///
+    /// Existing views are matched against it the next time their
+    /// properties change.
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    /// Replaces the rules, such as when reloading a configuration.
+    ///
+    /// Which rules views have had applied is forgotten, so views are
+    /// matched against all new rules the next time their properties change.
+    pub fn set_rules(&mut self, rules: Vec<Rule>) {
+        self.rules = rules;
+        for applied in self.applied.values_mut() {
+            applied.clear();
+        }
+    }
+
+    /// Applies matching rules to a new view. Call from the `view_created`
+    /// callback.
+    pub fn view_created(&mut self, view: WlcView) -> Outcome {
+        self.applied.remove(&view);
+        self.evaluate(view)
+    }
+
+    /// Applies rules a view now matches and has not had applied before.
+    /// Call from the `view_properties_changed` callback.
+    pub fn view_properties_changed(&mut self, view: WlcView) -> Outcome {
+        self.evaluate(view)
+    }
+
+    /// Forgets a view. Call from the `view_destroyed` callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.applied.remove(&view);
+    }
+
+    /// Applies the rules a view matches that it has not had applied.
+    pub fn evaluate(&mut self, view: WlcView) -> Outcome {
+        let properties = ViewProperties::of(view);
+        let mut outcome = Outcome::default();
+        for index in self.new_matches(view, &properties) {
+            let rule = &self.rules[index];
+            outcome.rules.push(rule.name.clone());
+            for action in &rule.actions {
+                outcome.add(action);
+                apply(view, action);
+                if outcome.closed {
+                    self.applied.remove(&view);
+                    return outcome
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Indices of the rules which match and were not applied to the view
+    /// yet, marking them as applied.
+    fn new_matches(&mut self, view: WlcView, properties: &ViewProperties) -> Vec<usize> {
+        let applied = self.applied.entry(view).or_default();
+        let mut matches = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !applied.contains(&index) && rule.matches(properties) {
+                applied.insert(index);
+                matches.push(index);
+            }
+        }
+        matches
+    }
+}
+
+fn apply(view: WlcView, action: &Action) {
+    match *action {
+        Action::Output(output) => view.set_output(output),
+        Action::Mask(mask) => view.set_mask(mask),
+        Action::Geometry(geometry) => view.set_geometry(EDGE_NONE, geometry),
+        Action::State(state, toggle) => view.set_state(state, toggle),
+        Action::Focus => view.focus(),
+        Action::Close => view.close(),
+        Action::Float | Action::Tile | Action::NoFocus => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{VIEW_BIT_MODAL, VIEW_BIT_POPUP};
+
+    fn terminal() -> ViewProperties {
+        ViewProperties {
+            class: "URxvt".into(),
+            app_id: "".into(),
+            title: "vim ~/notes.txt".into(),
+            view_type: ViewType::empty(),
+            has_parent: false
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let view = terminal();
+        assert!(Matcher::Class(Pattern::exact("URxvt")).matches(&view));
+        assert!(!Matcher::Class(Pattern::exact("urxvt")).matches(&view));
+        assert!(Matcher::AppId(Pattern::exact("")).matches(&view));
+        assert!(Matcher::Type(ViewType::empty()).matches(&view));
+        assert!(!Matcher::Type(VIEW_BIT_MODAL).matches(&view));
+        assert!(Matcher::HasParent(false).matches(&view));
+        assert!(Matcher::Not(Box::new(Matcher::HasParent(true))).matches(&view));
+        assert!(Matcher::Any(vec![Matcher::HasParent(true),
+                                  Matcher::Class(Pattern::exact("URxvt"))]).matches(&view));
+        assert!(!Matcher::Any(Vec::new()).matches(&view));
+
+        let dialog = ViewProperties {
+            view_type: VIEW_BIT_MODAL | VIEW_BIT_POPUP,
+            has_parent: true,
+            ..terminal()
+        };
+        assert!(Matcher::Type(VIEW_BIT_MODAL).matches(&dialog));
+        assert!(Matcher::Type(VIEW_BIT_MODAL | VIEW_BIT_POPUP).matches(&dialog));
+    }
+
+    #[cfg(feature = "window-rules-regex")]
+    #[test]
+    fn regex_matching() {
+        let view = terminal();
+        assert!(Matcher::Title(Pattern::regex("^vim ").unwrap()).matches(&view));
+        assert!(!Matcher::Title(Pattern::regex("^emacs").unwrap()).matches(&view));
+        assert!(Pattern::regex("(").is_err());
+    }
+
+    #[test]
+    fn rules_apply_once() {
+        let mut rules = WindowRules::new(vec![
+            Rule::new("all", Vec::new(), vec![Action::Tile]),
+            Rule::new("vim", vec![Matcher::Title(Pattern::exact("vim"))],
+                      vec![Action::Float])
+        ]);
+        let view = WlcView(1);
+        let mut properties = terminal();
+        properties.title = "bash".into();
+        assert_eq!(rules.new_matches(view, &properties), vec![0]);
+        assert!(rules.new_matches(view, &properties).is_empty());
+        properties.title = "vim".into();
+        assert_eq!(rules.new_matches(view, &properties), vec![1]);
+        assert_eq!(rules.new_matches(WlcView(2), &properties), vec![0, 1]);
+
+        rules.view_destroyed(view);
+        assert_eq!(rules.new_matches(view, &properties), vec![0, 1]);
+        rules.set_rules(vec![Rule::new("new", Vec::new(), Vec::new())]);
+        assert_eq!(rules.new_matches(view, &properties), vec![0]);
+    }
+
+    #[test]
+    fn outcomes() {
+        let mut outcome = Outcome::default();
+        for action in &[Action::Float, Action::NoFocus, Action::Mask(2), Action::Tile] {
+            outcome.add(action);
+        }
+        assert_eq!(outcome.floating, Some(false));
+        assert_eq!(outcome.focus, Some(false));
+        assert!(!outcome.closed);
+        outcome.add(&Action::Close);
+        assert!(outcome.closed);
+    }
+}
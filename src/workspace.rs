@@ -0,0 +1,434 @@
+//! Virtual desktops built on top of wlc's visibility masks.
+//!
+//! wlc only draws a view on an output if `view.get_mask() & output.get_mask()`
+//! is non-zero. `Workspaces` gives each named workspace one bit of that mask,
+//! so switching workspaces is just a matter of changing the output's mask
+//! and moving a view is just a matter of changing the view's mask.
+//!
+//! Because a mask is a `u32`, at most 32 workspaces can exist at once.
+//!
+//! # Example
+//! ```rust
+//! use rustwlc::workspace::Workspaces;
+//!
+//! let mut workspaces = Workspaces::new(&["1", "2", "web"]).unwrap();
+//! assert_eq!(workspaces.mask_of("1"), Some(0b001));
+//! assert_eq!(workspaces.mask_of("web"), Some(0b100));
+//!
+//! // Put "web" on whichever output is called DVI-I-1
+//! workspaces.assign_output("DVI-I-1", "web").unwrap();
+//! ```
+//!
+//! The callbacks in `callback` are plain `extern` functions, so the
+//! compositor has to forward the relevant events itself:
+//!
+//! ```no_run
+//! # #[macro_use] extern crate lazy_static;
+//! # extern crate rustwlc;
+//! use std::sync::Mutex;
+//! use rustwlc::{WlcOutput, WlcView};
+//! use rustwlc::workspace::Workspaces;
+//!
+//! lazy_static! {
+//!     static ref WORKSPACES: Mutex<Workspaces> =
+//!         Mutex::new(Workspaces::new(&["1", "2", "3"]).unwrap());
+//! }
+//!
+//! extern fn output_created(output: WlcOutput) -> bool {
+//!     WORKSPACES.lock().unwrap().output_created(output);
+//!     true
+//! }
+//!
+//! extern fn output_destroyed(output: WlcOutput) {
+//!     WORKSPACES.lock().unwrap().output_destroyed(output);
+//! }
+//!
+//! extern fn view_created(view: WlcView) -> bool {
+//!     WORKSPACES.lock().unwrap().view_created(view);
+//!     view.focus();
+//!     true
+//! }
+//!
+//! extern fn view_destroyed(view: WlcView) {
+//!     WORKSPACES.lock().unwrap().view_destroyed(view);
+//! }
+//! # fn main() {}
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use super::{WlcOutput, WlcView};
+
+/// Maximum number of workspaces, one per bit of a wlc mask.
+pub const MAX_WORKSPACES: usize = 32;
+
+/// Mask given to sticky views so they are visible on every workspace.
+pub const STICKY_MASK: u32 = !0;
+
+/// A set of named workspaces mapped onto visibility mask bits.
+#[derive(Debug, Clone, Default)]
+pub struct Workspaces {
+    /// Workspace names, the index is the mask bit
+    names: Vec<String>,
+    /// Workspace index currently shown on each output
+    active: HashMap<WlcOutput, usize>,
+    /// Preferred workspace for outputs, by output name
+    assignments: HashMap<String, usize>,
+    /// Views which are shown on every workspace
+    sticky: HashSet<WlcView>
+}
+
+impl Workspaces {
+    /// Creates a set of workspaces with the given names.
+    ///
+    /// The first name gets mask bit `1 << 0`, the second `1 << 1` and so on.
+    /// Returns an error if a name is repeated or there are more than
+    /// `MAX_WORKSPACES` names.
+    pub fn new<S: AsRef<str>>(names: &[S]) -> Result<Workspaces, &'static str> {
+        let mut workspaces = Workspaces::default();
+        for name in names {
+            workspaces.add(name.as_ref())?;
+        }
+        Ok(workspaces)
+    }
+
+    /// Adds a new workspace, returning its mask.
+    pub fn add(&mut self, name: &str) -> Result<u32, &'static str> {
+        if self.index_of(name).is_some() {
+            return Err("Workspace already exists")
+        }
+        if self.names.len() >= MAX_WORKSPACES {
+            return Err("Too many workspaces for a 32 bit mask")
+        }
+        self.names.push(name.to_string());
+        Ok(1 << (self.names.len() - 1))
+    }
+
+    /// The names of all workspaces, in mask bit order.
+    pub fn names(&self) -> Vec<&str> {
+        self.names.iter().map(|name| name.as_str()).collect()
+    }
+
+    /// Gets the mask bit used for the named workspace.
+    pub fn mask_of(&self, name: &str) -> Option<u32> {
+        self.index_of(name).map(|index| 1 << index)
+    }
+
+    /// Gets the names of the workspaces a mask is visible on.
+    pub fn names_in_mask(&self, mask: u32) -> Vec<&str> {
+        self.names.iter().enumerate()
+            .filter(|&(index, _)| mask & (1 << index) != 0)
+            .map(|(_, name)| name.as_str())
+            .collect()
+    }
+
+    /// Gets the workspace currently shown on an output.
+    pub fn current(&self, output: WlcOutput) -> Option<&str> {
+        self.active.get(&output).map(|&index| self.names[index].as_str())
+    }
+
+    /// Gets the output a workspace is currently shown on, if any.
+    pub fn output_showing(&self, name: &str) -> Option<WlcOutput> {
+        let index = self.index_of(name)?;
+        self.active.iter()
+            .find(|&(_, &active)| active == index)
+            .map(|(&output, _)| output)
+    }
+
+    /// Makes outputs with the given name start on a workspace.
+    ///
+    /// Output names are used rather than handles so the assignment
+    /// still applies after a monitor is unplugged and plugged back in.
+    /// The assignment takes effect in `output_created`.
+    pub fn assign_output(&mut self, output_name: &str, workspace: &str)
+                         -> Result<(), &'static str> {
+        let index = self.index_of(workspace).ok_or("No such workspace")?;
+        self.assignments.insert(output_name.to_string(), index);
+        Ok(())
+    }
+
+    /// Removes a workspace assignment for outputs with the given name.
+    pub fn unassign_output(&mut self, output_name: &str) {
+        self.assignments.remove(output_name);
+    }
+
+    /// Picks and shows a workspace on a newly created output.
+    ///
+    /// The workspace assigned with `assign_output` is used if it is not
+    /// already visible elsewhere, otherwise the first workspace that is
+    /// not shown on any other output. If every workspace is in use the
+    /// output shows the first one.
+    ///
+    /// Call this from the `output_created` callback.
+    pub fn output_created(&mut self, output: WlcOutput) {
+        if self.names.is_empty() {
+            return
+        }
+        let index = self.initial_workspace(&output.get_name());
+        self.active.insert(output, index);
+        output.set_mask(1 << index);
+    }
+
+    /// Forgets an output, freeing its workspace for other outputs.
+    ///
+    /// Call this from the `output_destroyed` callback.
+    pub fn output_destroyed(&mut self, output: WlcOutput) {
+        self.active.remove(&output);
+    }
+
+    /// Puts a new view on the current workspace of its output.
+    ///
+    /// Call this from the `view_created` callback.
+    pub fn view_created(&mut self, view: WlcView) {
+        let mask = self.active.get(&view.get_output())
+            .map(|&index| 1 << index)
+            .unwrap_or_else(|| view.get_output().get_mask());
+        view.set_mask(mask);
+    }
+
+    /// Forgets a view's sticky status.
+    ///
+    /// Call this from the `view_destroyed` callback.
+    pub fn view_destroyed(&mut self, view: WlcView) {
+        self.sticky.remove(&view);
+    }
+
+    /// Shows a workspace on an output.
+    ///
+    /// A workspace can only be visible on one output at a time. If the
+    /// workspace is already shown on another output, that output gets this
+    /// output's workspace instead, or the first free one if this output
+    /// showed none, and the views of both workspaces are moved accordingly.
+    /// Returns an error if the other output would be left without a
+    /// workspace.
+    pub fn switch_to(&mut self, output: WlcOutput, name: &str)
+                     -> Result<(), &'static str> {
+        let index = self.index_of(name).ok_or("No such workspace")?;
+        let previous = self.active.get(&output).cloned();
+        if previous == Some(index) {
+            return Ok(())
+        }
+        let other = self.active.iter()
+            .find(|&(&out, &active)| out != output && active == index)
+            .map(|(&out, _)| out);
+        if let Some(other) = other {
+            let given = previous.or_else(|| self.free_workspace())
+                .ok_or("No free workspace for the other output")?;
+            let (bit, given_bit) = (1 << index, 1 << given);
+            // Views live on an output, so swapping means moving both sets.
+            // Views on both workspaces go with the one being shown here.
+            let incoming: Vec<WlcView> = other.get_views().into_iter()
+                .filter(|&view| !self.is_sticky(view) && view.get_mask() & bit != 0)
+                .collect();
+            let outgoing: Vec<WlcView> = output.get_views().into_iter()
+                .filter(|&view| !self.is_sticky(view))
+                .filter(|&view| view.get_mask() & (bit | given_bit) == given_bit)
+                .collect();
+            for view in incoming {
+                view.set_output(output);
+            }
+            for view in outgoing {
+                view.set_output(other);
+            }
+            self.active.insert(other, given);
+            other.set_mask(given_bit);
+            other.schedule_render();
+        }
+        self.active.insert(output, index);
+        output.set_mask(1 << index);
+        output.schedule_render();
+        Ok(())
+    }
+
+    /// Moves a view to a workspace.
+    ///
+    /// If the workspace is shown on another output the view is moved to
+    /// that output too. Sticky views stay sticky and only change output.
+    pub fn move_view(&mut self, view: WlcView, name: &str)
+                     -> Result<(), &'static str> {
+        let index = self.index_of(name).ok_or("No such workspace")?;
+        if let Some(output) = self.output_showing(name) {
+            if view.get_output() != output {
+                view.get_output().schedule_render();
+                view.set_output(output);
+            }
+        }
+        if !self.is_sticky(view) {
+            view.set_mask(1 << index);
+        }
+        view.get_output().schedule_render();
+        Ok(())
+    }
+
+    /// Makes a view visible on all workspaces, or only on the current one.
+    ///
+    /// When a view stops being sticky it stays on the workspace currently
+    /// shown on its output.
+    pub fn set_sticky(&mut self, view: WlcView, sticky: bool) {
+        if sticky {
+            self.sticky.insert(view);
+            view.set_mask(STICKY_MASK);
+        } else {
+            self.sticky.remove(&view);
+            self.view_created(view);
+        }
+        view.get_output().schedule_render();
+    }
+
+    /// Whether a view is visible on all workspaces.
+    pub fn is_sticky(&self, view: WlcView) -> bool {
+        self.sticky.contains(&view)
+    }
+
+    /// Gets the views of an output which are on the named workspace.
+    ///
+    /// Sticky views are included. The views are in stacking order.
+    pub fn views_on(&self, output: WlcOutput, name: &str) -> Vec<WlcView> {
+        let mask = match self.mask_of(name) {
+            Some(mask) => mask,
+            None => return Vec::new()
+        };
+        output.get_views().into_iter()
+            .filter(|view| view.get_mask() & mask != 0)
+            .collect()
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|other| other == name)
+    }
+
+    fn initial_workspace(&self, output_name: &str) -> usize {
+        match self.assignments.get(output_name) {
+            Some(&index) if !self.active.values().any(|&active| active == index) => index,
+            _ => self.free_workspace().unwrap_or(0)
+        }
+    }
+
+    /// The first workspace not shown on any output.
+    fn free_workspace(&self) -> Option<usize> {
+        let in_use: HashSet<usize> = self.active.values().cloned().collect();
+        (0..self.names.len()).find(|index| !in_use.contains(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::WlcOutput;
+
+    #[test]
+    fn masks_follow_insertion_order() {
+        let workspaces = Workspaces::new(&["a", "b", "c"]).unwrap();
+        assert_eq!(workspaces.mask_of("a"), Some(1));
+        assert_eq!(workspaces.mask_of("b"), Some(2));
+        assert_eq!(workspaces.mask_of("c"), Some(4));
+        assert_eq!(workspaces.mask_of("d"), None);
+        assert_eq!(workspaces.names_in_mask(0b101), vec!["a", "c"]);
+        assert_eq!(workspaces.names_in_mask(STICKY_MASK).len(), 3);
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert!(Workspaces::new(&["a", "a"]).is_err());
+        let names: Vec<String> = (0..MAX_WORKSPACES + 1)
+            .map(|index| index.to_string())
+            .collect();
+        assert!(Workspaces::new(&names[..MAX_WORKSPACES]).is_ok());
+        assert!(Workspaces::new(&names).is_err());
+    }
+
+    #[test]
+    fn initial_workspaces() {
+        let mut workspaces = Workspaces::new(&["a", "b", "c"]).unwrap();
+        workspaces.assign_output("HDMI-1", "c").unwrap();
+        assert!(workspaces.assign_output("HDMI-1", "z").is_err());
+        assert_eq!(workspaces.initial_workspace("DP-1"), 0);
+        assert_eq!(workspaces.initial_workspace("HDMI-1"), 2);
+
+        let output = unsafe { WlcOutput::dummy(1) };
+        workspaces.active.insert(output, 0);
+        assert_eq!(workspaces.initial_workspace("DP-1"), 1);
+        assert_eq!(workspaces.current(output), Some("a"));
+        assert_eq!(workspaces.output_showing("a"), Some(output));
+        assert_eq!(workspaces.output_showing("b"), None);
+
+        workspaces.output_destroyed(output);
+        assert_eq!(workspaces.current(output), None);
+        assert_eq!(workspaces.initial_workspace("DP-1"), 0);
+    }
+
+    #[cfg(feature = "dummy")]
+    fn view_on(handle: u32, output: WlcOutput, mask: u32) -> WlcView {
+        let view = unsafe { WlcView::dummy(handle) };
+        view.set_output(output);
+        view.set_mask(mask);
+        view
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn switching_swaps_views() {
+        let mut workspaces = Workspaces::new(&["a", "b", "c"]).unwrap();
+        let (first, second) = unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2)) };
+        workspaces.output_created(first);
+        workspaces.output_created(second);
+        let only_a = view_on(10, first, 0b001);
+        let sticky = view_on(11, first, 0);
+        workspaces.set_sticky(sticky, true);
+        let only_b = view_on(12, second, 0b010);
+        let both = view_on(13, second, 0b011);
+        first.set_views(&[only_a, sticky]).unwrap();
+        second.set_views(&[only_b, both]).unwrap();
+
+        workspaces.switch_to(first, "b").unwrap();
+        assert_eq!(workspaces.current(first), Some("b"));
+        assert_eq!(workspaces.current(second), Some("a"));
+        assert_eq!((first.get_mask(), second.get_mask()), (0b010, 0b001));
+        // Views with more than one workspace move too, sticky ones never
+        assert_eq!(only_a.get_output(), second);
+        assert_eq!(only_b.get_output(), first);
+        assert_eq!(both.get_output(), first);
+        assert_eq!(sticky.get_output(), first);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn switching_without_a_workspace() {
+        let mut workspaces = Workspaces::new(&["a", "b", "c"]).unwrap();
+        let (first, second) = unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2)) };
+        workspaces.output_created(first);
+        let on_b = view_on(10, second, 0b010);
+        second.set_views(&[on_b]).unwrap();
+
+        // The other output gets the first free workspace, with its views
+        workspaces.switch_to(second, "a").unwrap();
+        assert_eq!(workspaces.current(second), Some("a"));
+        assert_eq!(workspaces.current(first), Some("b"));
+        assert_eq!(first.get_mask(), 0b010);
+        assert_eq!(on_b.get_output(), first);
+
+        // With every workspace shown, nothing is left for the other output
+        let (third, fourth) = unsafe { (WlcOutput::dummy(3), WlcOutput::dummy(4)) };
+        workspaces.switch_to(third, "c").unwrap();
+        assert!(workspaces.switch_to(fourth, "a").is_err());
+        assert_eq!(workspaces.current(fourth), None);
+        assert_eq!(workspaces.current(second), Some("a"));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn moving_views() {
+        let mut workspaces = Workspaces::new(&["a", "b"]).unwrap();
+        let (first, second) = unsafe { (WlcOutput::dummy(1), WlcOutput::dummy(2)) };
+        workspaces.output_created(first);
+        workspaces.output_created(second);
+        let view = view_on(10, first, 0b01);
+        workspaces.move_view(view, "b").unwrap();
+        assert_eq!((view.get_output(), view.get_mask()), (second, 0b10));
+
+        workspaces.set_sticky(view, true);
+        workspaces.move_view(view, "a").unwrap();
+        assert_eq!((view.get_output(), view.get_mask()), (first, STICKY_MASK));
+        assert!(workspaces.move_view(view, "z").is_err());
+    }
+}